@@ -0,0 +1,609 @@
+use std::fmt;
+
+// Shape edits recorded as invertible commands (command pattern) with undo/redo stacks
+// Every `&mut self` method of `methods_test::Rectangle` becomes a `Command` value, so an edit can
+// be stored, inverted, merged with the previous one and written to text
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn origin() -> Point {
+        Point { x: 0.0, y: 0.0 }
+    }
+
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+}
+
+// Anything that can be moved, resized and turned
+pub trait Transform {
+    fn translate(&mut self, x: f64, y: f64);
+    // Scales around the shape's own center
+    fn scale(&mut self, factor: f64);
+    // Rotates counterclockwise around the shape's own center
+    fn rotate(&mut self, radians: f64);
+    // Every coordinate finite
+    fn is_finite(&self) -> bool;
+    // Equal up to rounding: what undoing an edit is expected to get back
+    fn same_shape(&self, other: &Self) -> bool;
+}
+
+// Unlike `methods_test::Rectangle` (two corners, always axis aligned), a rectangle which can be
+// rotated is stored as center, size and angle. `from_corners` builds the axis aligned one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rectangle {
+    pub center: Point,
+    pub width: f64,
+    pub height: f64,
+    pub angle: f64,
+}
+
+impl Rectangle {
+    pub fn from_corners(p1: Point, p2: Point) -> Rectangle {
+        Rectangle {
+            center: Point::new((p1.x + p2.x) / 2.0, (p1.y + p2.y) / 2.0),
+            width: (p1.x - p2.x).abs(),
+            height: (p1.y - p2.y).abs(),
+            angle: 0.0,
+        }
+    }
+
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+
+    pub fn perimeter(&self) -> f64 {
+        2.0 * (self.width + self.height)
+    }
+
+    // Corners in counterclockwise order, starting bottom-left before rotation
+    pub fn corners(&self) -> [Point; 4] {
+        let (sin, cos) = self.angle.sin_cos();
+        let (w, h) = (self.width / 2.0, self.height / 2.0);
+        let corner = |dx: f64, dy: f64| {
+            Point::new(
+                self.center.x + dx * cos - dy * sin,
+                self.center.y + dx * sin + dy * cos,
+            )
+        };
+
+        [corner(-w, -h), corner(w, -h), corner(w, h), corner(-w, h)]
+    }
+}
+
+impl Transform for Rectangle {
+    fn translate(&mut self, x: f64, y: f64) {
+        self.center.x += x;
+        self.center.y += y;
+    }
+
+    fn scale(&mut self, factor: f64) {
+        self.width *= factor.abs();
+        self.height *= factor.abs();
+    }
+
+    fn rotate(&mut self, radians: f64) {
+        self.angle += radians;
+    }
+
+    fn is_finite(&self) -> bool {
+        [
+            self.center.x,
+            self.center.y,
+            self.width,
+            self.height,
+            self.angle,
+        ]
+        .iter()
+        .all(|v| v.is_finite())
+    }
+
+    fn same_shape(&self, other: &Rectangle) -> bool {
+        // positions and angles to 1e-9, relative above 1; sizes always relative, a width which
+        // underflowed to 0 isn't the tiny width it was
+        let near =
+            |a: f64, b: f64, unit: f64| (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(unit);
+        near(self.center.x, other.center.x, 1.0)
+            && near(self.center.y, other.center.y, 1.0)
+            && near(self.width, other.width, 0.0)
+            && near(self.height, other.height, 0.0)
+            && near(self.angle, other.angle, 1.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Translate { x: f64, y: f64 },
+    Scale { factor: f64 },
+    Rotate { radians: f64 },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EditError {
+    // Scaling by zero, any NaN/inf argument, or an edit whose result overflows or whose inverse
+    // doesn't get the shape back can't be undone
+    NotInvertible(Command),
+    NothingToUndo,
+    NothingToRedo,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::NotInvertible(cmd) => write!(f, "command `{}` can not be undone", cmd),
+            EditError::NothingToUndo => write!(f, "nothing to undo"),
+            EditError::NothingToRedo => write!(f, "nothing to redo"),
+        }
+    }
+}
+
+impl Command {
+    pub fn apply<S: Transform>(&self, shape: &mut S) {
+        match *self {
+            Command::Translate { x, y } => shape.translate(x, y),
+            Command::Scale { factor } => shape.scale(factor),
+            Command::Rotate { radians } => shape.rotate(radians),
+        }
+    }
+
+    pub fn inverse(&self) -> Result<Command, EditError> {
+        match *self {
+            // inf + -inf is NaN, a non-finite offset can't be taken back
+            Command::Translate { x, y } if x.is_finite() && y.is_finite() => {
+                Ok(Command::Translate { x: -x, y: -y })
+            }
+            // a tiny factor has an infinite inverse
+            Command::Scale { factor } if factor.is_finite() && (1.0 / factor).is_finite() => {
+                Ok(Command::Scale {
+                    factor: 1.0 / factor,
+                })
+            }
+            Command::Rotate { radians } if radians.is_finite() => {
+                Ok(Command::Rotate { radians: -radians })
+            }
+            _ => Err(EditError::NotInvertible(*self)),
+        }
+    }
+
+    // Two consecutive commands of the same kind collapse into one: dragging a shape around
+    // records a single translate instead of one per mouse move
+    pub fn merge(&self, next: &Command) -> Option<Command> {
+        match (*self, *next) {
+            (Command::Translate { x: x1, y: y1 }, Command::Translate { x: x2, y: y2 }) => {
+                Some(Command::Translate {
+                    x: x1 + x2,
+                    y: y1 + y2,
+                })
+            }
+            (Command::Scale { factor: f1 }, Command::Scale { factor: f2 }) => {
+                Some(Command::Scale { factor: f1 * f2 })
+            }
+            (Command::Rotate { radians: r1 }, Command::Rotate { radians: r2 }) => {
+                Some(Command::Rotate { radians: r1 + r2 })
+            }
+            _ => None,
+        }
+    }
+}
+
+// One command per line: `translate 1 2`, `scale 0.5`, `rotate 1.5707963267948966`
+// `{}` prints the shortest representation of an `f64` which parses back to the same value, so
+// a saved history replays exactly
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Translate { x, y } => write!(f, "translate {} {}", x, y),
+            Command::Scale { factor } => write!(f, "scale {}", factor),
+            Command::Rotate { radians } => write!(f, "rotate {}", radians),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseCommandError {
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseCommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+impl Command {
+    fn parse(line: &str) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or_else(|| "empty command".to_owned())?;
+        let args = words
+            .map(|w| w.parse::<f64>().map_err(|e| format!("{:?}: {}", w, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match (name, args.as_slice()) {
+            ("translate", &[x, y]) => Ok(Command::Translate { x, y }),
+            ("scale", &[factor]) => Ok(Command::Scale { factor }),
+            ("rotate", &[radians]) => Ok(Command::Rotate { radians }),
+            ("translate", _) | ("scale", _) | ("rotate", _) => {
+                Err(format!("wrong number of arguments for `{}`", name))
+            }
+            _ => Err(format!("unknown command `{}`", name)),
+        }
+    }
+}
+
+// Owns the edited shape together with its history
+// `undo` holds the applied commands (most recent last), `redo` the undone ones. Any new edit
+// clears `redo`, bz the undone commands no longer apply to the new state
+pub struct Editor<S> {
+    shape: S,
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    coalesce: bool,
+}
+
+impl<S: Transform + Clone> Editor<S> {
+    pub fn new(shape: S) -> Editor<S> {
+        Editor {
+            shape,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            coalesce: false,
+        }
+    }
+
+    // With coalescing on, an edit of the same kind as the last one is merged into it and a single
+    // `undo` reverts both
+    pub fn with_coalescing(mut self, coalesce: bool) -> Editor<S> {
+        self.coalesce = coalesce;
+        self
+    }
+
+    pub fn shape(&self) -> &S {
+        &self.shape
+    }
+
+    // `before` with `cmd` applied, if the result is finite and the inverse of `cmd` gets `before`
+    // back
+    fn try_apply(before: &S, cmd: &Command) -> Result<S, EditError> {
+        let inverse = cmd.inverse()?;
+        let mut after = before.clone();
+        cmd.apply(&mut after);
+        let mut back = after.clone();
+        inverse.apply(&mut back);
+        if after.is_finite() && back.same_shape(before) {
+            Ok(after)
+        } else {
+            Err(EditError::NotInvertible(*cmd))
+        }
+    }
+
+    pub fn execute(&mut self, cmd: Command) -> Result<(), EditError> {
+        // Tried on a copy first, so every recorded command can be undone
+        let after = Editor::try_apply(&self.shape, &cmd)?;
+        let before = std::mem::replace(&mut self.shape, after);
+        self.redo.clear();
+
+        let merged = match self.undo.last() {
+            Some(last) if self.coalesce => last.merge(&cmd).map(|merged| (*last, merged)),
+            _ => None,
+        };
+        match merged {
+            // The merged command has to undo both from here, two huge offsets for instance
+            // overflow when added, keep both commands then
+            Some((last, merged)) if self.undoes_both(&before, &last, &merged) => {
+                self.undo.pop();
+                self.undo.push(merged);
+            }
+            _ => self.undo.push(cmd),
+        }
+
+        Ok(())
+    }
+
+    // `merged` leads from the shape before `last` to the current one, and back
+    fn undoes_both(&self, before: &S, last: &Command, merged: &Command) -> bool {
+        let mut start = before.clone();
+        match last.inverse() {
+            Ok(inverse) => inverse.apply(&mut start),
+            Err(_) => return false,
+        }
+        Editor::try_apply(&start, merged).is_ok_and(|after| after.same_shape(&self.shape))
+    }
+
+    // The command stays on the undo stack if it can't be inverted
+    pub fn undo(&mut self) -> Result<Command, EditError> {
+        let cmd = *self.undo.last().ok_or(EditError::NothingToUndo)?;
+        let inverse = cmd.inverse()?;
+        self.undo.pop();
+        inverse.apply(&mut self.shape);
+        self.redo.push(cmd);
+        Ok(cmd)
+    }
+
+    pub fn redo(&mut self) -> Result<Command, EditError> {
+        let cmd = self.redo.pop().ok_or(EditError::NothingToRedo)?;
+        cmd.apply(&mut self.shape);
+        self.undo.push(cmd);
+        Ok(cmd)
+    }
+
+    // Consumes the editor like `Pair::destroy`, dropping the history and keeping the shape
+    pub fn into_shape(self) -> S {
+        self.shape
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    // Applied commands oldest first, prefixed with `do`, then the redo stack prefixed with `redo`
+    // in the order `redo` would replay them
+    pub fn save_history(&self) -> String {
+        let mut out = String::new();
+        for cmd in &self.undo {
+            out.push_str(&format!("do {}\n", cmd));
+        }
+        for cmd in self.redo.iter().rev() {
+            out.push_str(&format!("redo {}\n", cmd));
+        }
+        out
+    }
+
+    // Rebuilds an editor by replaying a saved history on top of the shape it started from. The
+    // `redo` entries are checked like `execute` would, on the shapes they would be redone on
+    pub fn load_history(initial: S, history: &str) -> Result<Editor<S>, ParseCommandError> {
+        let mut editor = Editor::new(initial);
+        let mut redo = Vec::new();
+        let mut redone: Option<S> = None;
+
+        for (i, line) in history.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |reason: String| ParseCommandError {
+                line: i + 1,
+                reason,
+            };
+
+            let (kind, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let cmd = Command::parse(rest).map_err(error)?;
+            match kind {
+                "do" if redo.is_empty() => editor.execute(cmd).map_err(|e| error(e.to_string()))?,
+                "do" => return Err(error("`do` after `redo`".to_owned())),
+                "redo" => {
+                    let before = redone.get_or_insert_with(|| editor.shape.clone());
+                    *before = Editor::try_apply(before, &cmd).map_err(|e| error(e.to_string()))?;
+                    redo.push(cmd);
+                }
+                _ => return Err(error(format!("unknown entry `{}`", kind))),
+            }
+        }
+
+        redo.reverse();
+        editor.redo = redo;
+        Ok(editor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn unit_square() -> Rectangle {
+        Rectangle::from_corners(Point::origin(), Point::new(1.0, 1.0))
+    }
+
+    fn close(a: &Rectangle, b: &Rectangle) -> bool {
+        let eps = 1e-9;
+        (a.center.x - b.center.x).abs() < eps
+            && (a.center.y - b.center.y).abs() < eps
+            && (a.width - b.width).abs() < eps
+            && (a.height - b.height).abs() < eps
+            && (a.angle - b.angle).abs() < eps
+    }
+
+    #[test]
+    fn undo_restores_and_redo_reapplies() {
+        let mut editor = Editor::new(unit_square());
+        editor
+            .execute(Command::Translate { x: 1.0, y: 1.0 })
+            .unwrap();
+        editor.execute(Command::Scale { factor: 3.0 }).unwrap();
+        editor
+            .execute(Command::Rotate { radians: FRAC_PI_2 })
+            .unwrap();
+        let edited = *editor.shape();
+        assert!((edited.area() - 9.0).abs() < 1e-9);
+
+        while editor.can_undo() {
+            editor.undo().unwrap();
+        }
+        assert!(close(editor.shape(), &unit_square()));
+        assert_eq!(editor.undo(), Err(EditError::NothingToUndo));
+
+        while editor.can_redo() {
+            editor.redo().unwrap();
+        }
+        assert!(close(editor.shape(), &edited));
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut editor = Editor::new(unit_square());
+        editor.execute(Command::Scale { factor: 2.0 }).unwrap();
+        editor.undo().unwrap();
+        editor.execute(Command::Rotate { radians: 1.0 }).unwrap();
+        assert!(!editor.can_redo());
+    }
+
+    #[test]
+    fn non_invertible_commands_are_rejected() {
+        let mut editor = Editor::new(unit_square());
+        let zero = Command::Scale { factor: 0.0 };
+        assert_eq!(editor.execute(zero), Err(EditError::NotInvertible(zero)));
+        assert_eq!(*editor.shape(), unit_square());
+        assert!(!editor.can_undo());
+
+        // a failed undo keeps the command where it was
+        editor.undo.push(zero);
+        assert_eq!(editor.undo(), Err(EditError::NotInvertible(zero)));
+        assert!(editor.can_undo() && !editor.can_redo());
+    }
+
+    #[test]
+    fn non_finite_translates_are_refused() {
+        let mut editor = Editor::new(unit_square());
+        editor
+            .execute(Command::Translate { x: 1.0, y: 2.0 })
+            .unwrap();
+        for bad in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+            let cmd = Command::Translate { x: bad, y: 0.0 };
+            assert!(editor.execute(cmd).is_err());
+            let cmd = Command::Translate { x: 0.0, y: bad };
+            assert!(editor.execute(cmd).is_err());
+        }
+        // the shape is untouched and `undo` still takes back the last valid edit only
+        assert_eq!(editor.undo(), Ok(Command::Translate { x: 1.0, y: 2.0 }));
+        assert_eq!(*editor.shape(), unit_square());
+        assert_eq!(editor.undo(), Err(EditError::NothingToUndo));
+    }
+
+    #[test]
+    fn edits_which_overflow_are_refused() {
+        // centered on the origin, so a huge offset doesn't swallow the center
+        let square = Rectangle::from_corners(Point::new(-0.5, -0.5), Point::new(0.5, 0.5));
+        let mut editor = Editor::new(square);
+
+        // the second step would take the center to inf
+        let step = Command::Translate {
+            x: 0.6 * f64::MAX,
+            y: 0.0,
+        };
+        editor.execute(step).unwrap();
+        assert_eq!(editor.shape().center.x, 0.6 * f64::MAX);
+        assert_eq!(editor.execute(step), Err(EditError::NotInvertible(step)));
+        assert_eq!(editor.shape().center.x, 0.6 * f64::MAX);
+        assert_eq!(editor.undo(), Ok(step));
+        assert_eq!(*editor.shape(), square);
+
+        // on the unit square f64::MAX swallows the 0.5 of the center, undoing wouldn't get it back
+        let max = Command::Translate {
+            x: f64::MAX,
+            y: 0.0,
+        };
+        let mut editor = Editor::new(unit_square());
+        assert_eq!(editor.execute(max), Err(EditError::NotInvertible(max)));
+        assert_eq!(*editor.shape(), unit_square());
+
+        // 1 / 1e-310 is inf
+        let tiny = Command::Scale { factor: 1e-310 };
+        assert_eq!(editor.execute(tiny), Err(EditError::NotInvertible(tiny)));
+        assert_eq!(*editor.shape(), unit_square());
+        // 1e-200 has a finite inverse, but a second one takes the width down to 0
+        let small = Command::Scale { factor: 1e-200 };
+        editor.execute(small).unwrap();
+        assert_eq!(editor.shape().width, 1e-200);
+        assert_eq!(editor.execute(small), Err(EditError::NotInvertible(small)));
+        assert_eq!(editor.shape().width, 1e-200);
+        assert_eq!(editor.undo(), Ok(small));
+        assert!(editor.shape().same_shape(&unit_square()));
+        assert_eq!(editor.undo(), Err(EditError::NothingToUndo));
+    }
+
+    #[test]
+    fn coalescing_keeps_commands_which_overflow_together() {
+        let square = Rectangle::from_corners(Point::new(-0.5, -0.5), Point::new(0.5, 0.5));
+        let mut editor = Editor::new(square).with_coalescing(true);
+        let step = Command::Translate {
+            x: 0.6 * f64::MAX,
+            y: 0.0,
+        };
+        editor
+            .execute(Command::Translate {
+                x: -0.6 * f64::MAX,
+                y: 0.0,
+            })
+            .unwrap();
+        editor.execute(Command::Rotate { radians: 0.1 }).unwrap();
+        editor.execute(step).unwrap();
+        editor.execute(step).unwrap();
+        assert_eq!(editor.shape().center.x, 0.6 * f64::MAX);
+
+        // merged, the two steps would be an infinite offset: they stay two commands
+        assert_eq!(editor.undo(), Ok(step));
+        assert_eq!(editor.shape().center.x, 0.0);
+        assert_eq!(editor.undo(), Ok(step));
+        assert_eq!(editor.shape().center.x, -0.6 * f64::MAX);
+    }
+
+    #[test]
+    fn coalescing_merges_same_kind_commands() {
+        let mut editor = Editor::new(unit_square()).with_coalescing(true);
+        for _ in 0..5 {
+            editor
+                .execute(Command::Translate { x: 1.0, y: 0.5 })
+                .unwrap();
+        }
+        editor.execute(Command::Scale { factor: 2.0 }).unwrap();
+
+        assert_eq!(editor.undo(), Ok(Command::Scale { factor: 2.0 }));
+        assert_eq!(editor.undo(), Ok(Command::Translate { x: 5.0, y: 2.5 }));
+        assert!(!editor.can_undo());
+    }
+
+    #[test]
+    fn history_round_trips_through_text() {
+        let mut editor = Editor::new(unit_square());
+        editor
+            .execute(Command::Translate { x: 0.1, y: -2.0 })
+            .unwrap();
+        editor.execute(Command::Rotate { radians: 0.3 }).unwrap();
+        editor.execute(Command::Scale { factor: 1.5 }).unwrap();
+        editor.undo().unwrap();
+        let saved = editor.save_history();
+
+        let mut loaded = Editor::load_history(unit_square(), &saved).unwrap();
+        assert_eq!(loaded.shape(), editor.shape());
+        assert_eq!(loaded.save_history(), saved);
+        assert_eq!(loaded.redo(), Ok(Command::Scale { factor: 1.5 }));
+    }
+
+    #[test]
+    fn malformed_history_reports_the_line() {
+        let err = Editor::load_history(unit_square(), "do scale 2\ndo shear 1\n")
+            .err()
+            .unwrap();
+        assert_eq!(err.line, 2);
+        assert!(Editor::load_history(unit_square(), "do translate 1").is_err());
+        assert!(Editor::load_history(unit_square(), "do translate inf 0").is_err());
+
+        // `redo` entries are checked on the shape they'd be redone on
+        let err = Editor::load_history(unit_square(), "do scale 2\nredo translate inf 0\n")
+            .err()
+            .unwrap();
+        assert_eq!(err.line, 2);
+        let history = "redo scale 1e-200\nredo scale 1e-200\n";
+        let err = Editor::load_history(unit_square(), history).err().unwrap();
+        assert_eq!(err.line, 2);
+        assert!(err.reason.ends_with("can not be undone"));
+    }
+
+    #[test]
+    fn history_accepts_any_whitespace() {
+        let loaded =
+            Editor::load_history(unit_square(), "do\tscale 2\n  redo \t rotate\t1  \n").unwrap();
+        assert_eq!(loaded.shape().area(), 4.0);
+        assert!(loaded.can_redo());
+    }
+}
@@ -0,0 +1,371 @@
+use std::collections::VecDeque;
+use std::iter::Fuse;
+
+// Extra adaptors for every `std::iter::Iterator`, brought into scope with `use iter_ext::IteratorExt`
+// Unlike the `pub trait Iterator` re-declarations in `closure_std`, this is an extension trait: it
+// is implemented once for all `I: Iterator` and only adds methods
+//
+// Laziness follows the std convention:
+// - `chunks`, `windows`, `dedup_by_key`, `interleave` and `group_by` are LAZY, they return an
+//   adaptor which does nothing until `next` is called, so they work on infinite iterators
+// - `minmax`, `partition_map` and `sorted_by_key` are EAGER, they consume the whole iterator
+//   when called (like `max`, `partition` or `collect`) and must not be used on infinite ones
+
+pub trait IteratorExt: Iterator + Sized {
+    // Non-overlapping chunks of `size` items, the last chunk may be shorter. LAZY
+    fn chunks(self, size: usize) -> Chunks<Self> {
+        assert!(size > 0, "chunk size must be non-zero");
+        Chunks { iter: self, size }
+    }
+
+    // Overlapping windows of `size` consecutive items, each item is cloned into every window it
+    // belongs to. Yields nothing when there are fewer than `size` items. LAZY
+    fn windows(self, size: usize) -> Windows<Self>
+    where
+        Self::Item: Clone,
+    {
+        assert!(size > 0, "window size must be non-zero");
+        Windows {
+            iter: self,
+            size,
+            window: VecDeque::with_capacity(size),
+        }
+    }
+
+    // Drops items whose key equals the key of the previous item, keeping the first of each run.
+    // LAZY
+    fn dedup_by_key<K, F>(self, key: F) -> DedupByKey<Self, K, F>
+    where
+        K: PartialEq,
+        F: FnMut(&Self::Item) -> K,
+    {
+        DedupByKey {
+            iter: self,
+            key,
+            last: None,
+        }
+    }
+
+    // Alternates items from `self` and `other`, starting with `self`. Once one side is exhausted
+    // the rest of the other side follows. LAZY
+    fn interleave<J>(self, other: J) -> Interleave<Self, J::IntoIter>
+    where
+        J: IntoIterator<Item = Self::Item>,
+    {
+        Interleave {
+            a: self.fuse(),
+            b: other.into_iter().fuse(),
+            flag: false,
+        }
+    }
+
+    // Groups runs of CONSECUTIVE items with equal keys: `[1, 1, 2, 1]` keyed by identity gives
+    // `(1, [1, 1]), (2, [2]), (1, [1])`. Sort first to group by key globally. LAZY, but each group
+    // is buffered in a `Vec`
+    fn group_by<K, F>(self, key: F) -> GroupBy<Self, K, F>
+    where
+        K: PartialEq,
+        F: FnMut(&Self::Item) -> K,
+    {
+        GroupBy {
+            iter: self,
+            key,
+            pending: None,
+        }
+    }
+
+    // Smallest and largest item in a single pass. On ties the first minimum and the last maximum
+    // win, like `min` and `max`. EAGER
+    fn minmax(mut self) -> MinMaxResult<Self::Item>
+    where
+        Self::Item: PartialOrd + Clone,
+    {
+        let first = match self.next() {
+            None => return MinMaxResult::Empty,
+            Some(first) => first,
+        };
+
+        let (min, max) = self.fold((first.clone(), first), |(min, max), x| {
+            if x < min {
+                (x, max)
+            } else if x >= max {
+                (min, x)
+            } else {
+                (min, max)
+            }
+        });
+
+        if min == max {
+            MinMaxResult::One(min)
+        } else {
+            MinMaxResult::MinMax(min, max)
+        }
+    }
+
+    // Splits items into two collections of possibly different types, `f` decides the side. EAGER
+    fn partition_map<L, R, F>(self, mut f: F) -> (Vec<L>, Vec<R>)
+    where
+        F: FnMut(Self::Item) -> Either<L, R>,
+    {
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        for item in self {
+            match f(item) {
+                Either::Left(l) => left.push(l),
+                Either::Right(r) => right.push(r),
+            }
+        }
+
+        (left, right)
+    }
+
+    // Collects and stably sorts all items by key, then iterates over them. EAGER
+    fn sorted_by_key<K, F>(self, key: F) -> std::vec::IntoIter<Self::Item>
+    where
+        K: Ord,
+        F: FnMut(&Self::Item) -> K,
+    {
+        let mut items: Vec<_> = self.collect();
+        items.sort_by_key(key);
+        items.into_iter()
+    }
+}
+
+impl<I: Iterator> IteratorExt for I {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MinMaxResult<T> {
+    Empty,
+    // Every item compared equal (or there was a single one)
+    One(T),
+    MinMax(T, T),
+}
+
+pub struct Chunks<I> {
+    iter: I,
+    size: usize,
+}
+
+impl<I: Iterator> Iterator for Chunks<I> {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        let chunk: Vec<_> = self.iter.by_ref().take(self.size).collect();
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+pub struct Windows<I: Iterator> {
+    iter: I,
+    size: usize,
+    window: VecDeque<I::Item>,
+}
+
+impl<I> Iterator for Windows<I>
+where
+    I: Iterator,
+    I::Item: Clone,
+{
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Vec<I::Item>> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        while self.window.len() < self.size {
+            self.window.push_back(self.iter.next()?);
+        }
+
+        Some(self.window.iter().cloned().collect())
+    }
+}
+
+pub struct DedupByKey<I, K, F> {
+    iter: I,
+    key: F,
+    last: Option<K>,
+}
+
+impl<I, K, F> Iterator for DedupByKey<I, K, F>
+where
+    I: Iterator,
+    K: PartialEq,
+    F: FnMut(&I::Item) -> K,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        for item in self.iter.by_ref() {
+            let key = (self.key)(&item);
+            if self.last.as_ref() != Some(&key) {
+                self.last = Some(key);
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct Interleave<I, J> {
+    a: Fuse<I>,
+    b: Fuse<J>,
+    // `true` when `b` goes next
+    flag: bool,
+}
+
+impl<I, J> Iterator for Interleave<I, J>
+where
+    I: Iterator,
+    J: Iterator<Item = I::Item>,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        self.flag = !self.flag;
+        if self.flag {
+            self.a.next().or_else(|| self.b.next())
+        } else {
+            self.b.next().or_else(|| self.a.next())
+        }
+    }
+}
+
+pub struct GroupBy<I: Iterator, K, F> {
+    iter: I,
+    key: F,
+    // First item of the next group, read while looking for the end of the current one
+    pending: Option<(K, I::Item)>,
+}
+
+impl<I, K, F> Iterator for GroupBy<I, K, F>
+where
+    I: Iterator,
+    K: PartialEq,
+    F: FnMut(&I::Item) -> K,
+{
+    type Item = (K, Vec<I::Item>);
+
+    fn next(&mut self) -> Option<(K, Vec<I::Item>)> {
+        let (key, first) = match self.pending.take() {
+            Some(pending) => pending,
+            None => {
+                let first = self.iter.next()?;
+                ((self.key)(&first), first)
+            }
+        };
+
+        let mut group = vec![first];
+        for item in self.iter.by_ref() {
+            let item_key = (self.key)(&item);
+            if item_key == key {
+                group.push(item);
+            } else {
+                self.pending = Some((item_key, item));
+                break;
+            }
+        }
+
+        Some((key, group))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_keep_the_short_tail() {
+        let chunks: Vec<_> = (1..=7).chunks(3).collect();
+        assert_eq!(chunks, vec![vec![1, 2, 3], vec![4, 5, 6], vec![7]]);
+        assert_eq!((0..0).chunks(3).count(), 0);
+    }
+
+    #[test]
+    fn windows_overlap() {
+        let windows: Vec<_> = (1..=4).windows(2).collect();
+        assert_eq!(windows, vec![vec![1, 2], vec![2, 3], vec![3, 4]]);
+        assert_eq!((1..=2).windows(3).count(), 0);
+    }
+
+    #[test]
+    fn dedup_by_key_only_removes_consecutive_duplicates() {
+        let words = vec!["apple", "avocado", "banana", "blueberry", "apricot"];
+        let deduped: Vec<_> = words
+            .into_iter()
+            .dedup_by_key(|w| w.as_bytes()[0])
+            .collect();
+        assert_eq!(deduped, vec!["apple", "banana", "apricot"]);
+    }
+
+    #[test]
+    fn interleave_drains_the_longer_side() {
+        let mixed: Vec<_> = vec![1, 3, 5, 7]
+            .into_iter()
+            .interleave(vec![2, 4])
+            .collect();
+        assert_eq!(mixed, vec![1, 2, 3, 4, 5, 7]);
+        let mixed: Vec<_> = vec![1].into_iter().interleave(vec![2, 4, 6]).collect();
+        assert_eq!(mixed, vec![1, 2, 4, 6]);
+    }
+
+    #[test]
+    fn group_by_groups_consecutive_keys() {
+        let groups: Vec<_> = vec![1, 3, 2, 4, 5]
+            .into_iter()
+            .group_by(|x| x % 2)
+            .collect();
+        assert_eq!(groups, vec![(1, vec![1, 3]), (0, vec![2, 4]), (1, vec![5])]);
+    }
+
+    #[test]
+    fn minmax_cases() {
+        assert_eq!((0..0).minmax(), MinMaxResult::Empty);
+        assert_eq!(vec![4, 4].into_iter().minmax(), MinMaxResult::One(4));
+        assert_eq!(
+            vec![3, 9, 1, 7].into_iter().minmax(),
+            MinMaxResult::MinMax(1, 9)
+        );
+    }
+
+    #[test]
+    fn partition_map_splits_types() {
+        let (numbers, words): (Vec<i32>, Vec<&str>) = vec!["1", "two", "3"]
+            .into_iter()
+            .partition_map(|s| match s.parse() {
+                Ok(n) => Either::Left(n),
+                Err(_) => Either::Right(s),
+            });
+        assert_eq!(numbers, vec![1, 3]);
+        assert_eq!(words, vec!["two"]);
+    }
+
+    #[test]
+    fn sorted_by_key_is_stable() {
+        let sorted: Vec<_> = vec![(2, 'a'), (1, 'b'), (2, 'c'), (1, 'd')]
+            .into_iter()
+            .sorted_by_key(|&(k, _)| k)
+            .collect();
+        assert_eq!(sorted, vec![(1, 'b'), (1, 'd'), (2, 'a'), (2, 'c')]);
+    }
+
+    #[test]
+    fn lazy_adaptors_work_on_infinite_iterators() {
+        let mut chunks = (0..).chunks(2);
+        assert_eq!(chunks.next(), Some(vec![0, 1]));
+        let mut windows = (0..).windows(3);
+        assert_eq!(windows.nth(10), Some(vec![10, 11, 12]));
+        let first_groups: Vec<_> = (0..).group_by(|x| x / 3).take(2).collect();
+        assert_eq!(first_groups, vec![(0, vec![0, 1, 2]), (1, vec![3, 4, 5])]);
+    }
+}
@@ -1,4 +1,14 @@
 #![feature(never_type)]
+#![cfg_attr(test, feature(test))]
+#[cfg(test)]
+extern crate test;
+
+mod geometry;
+mod iter_ext;
+mod memoize;
+mod pipeline;
+mod sequences;
+
 fn main() {
     base_fn_test();
     methods_test();
@@ -11,6 +21,258 @@ fn main() {
     closure_std();
     higher_order_functions();
     derive_functions();
+    lazy_sequences();
+    closure_pipeline();
+    memoized_closures();
+    iterator_extensions();
+    shape_edit_history();
+}
+
+// `Rectangle::translate` from `methods_test` turned into recorded, undoable commands
+fn shape_edit_history() {
+    use geometry::{Command, Editor, Point, Rectangle};
+
+    let square = Rectangle::from_corners(Point::origin(), Point::new(1.0, 1.0));
+    let mut editor = Editor::new(square).with_coalescing(true);
+
+    // A drag produces many small translates which coalesce into a single command
+    for _ in 0..3 {
+        editor
+            .execute(Command::Translate { x: 1.0, y: 1.0 })
+            .unwrap();
+    }
+    editor.execute(Command::Scale { factor: 2.0 }).unwrap();
+    editor
+        .execute(Command::Rotate {
+            radians: std::f64::consts::FRAC_PI_4,
+        })
+        .unwrap();
+    println!(
+        "edited: {:?}, area {}, perimeter {}",
+        editor.shape(),
+        editor.shape().area(),
+        editor.shape().perimeter()
+    );
+    println!("corners: {:?}", editor.shape().corners());
+
+    if let Err(e) = editor.execute(Command::Scale { factor: 0.0 }) {
+        println!("rejected: {}", e);
+    }
+
+    println!("undo {:?}", editor.undo());
+    println!("undo {:?}", editor.undo());
+    println!("after undo: {:?}", editor.shape());
+
+    let saved = editor.save_history();
+    println!("saved history:\n{}", saved);
+
+    let mut restored = Editor::load_history(square, &saved).unwrap();
+    println!(
+        "redo {:?} on restored editor (can undo: {}, can redo again: {})",
+        restored.redo(),
+        restored.can_undo(),
+        restored.can_redo()
+    );
+
+    // `into_shape` consumes the editor like `Pair::destroy` consumes the pair
+    let shape = restored.into_shape();
+    println!("final shape: {:?}", shape);
+}
+
+// The adaptors below take closures exactly like `any`/`find` in `closure_std`, but come from an
+// extension trait implemented for every `std::iter::Iterator`
+fn iterator_extensions() {
+    use iter_ext::{Either, IteratorExt};
+
+    let readings = [3, 3, 4, 8, 8, 8, 2, 5];
+
+    println!(
+        "chunks: {:?}",
+        readings.iter().chunks(3).collect::<Vec<_>>()
+    );
+    println!(
+        "windows: {:?}",
+        readings.iter().windows(3).collect::<Vec<_>>()
+    );
+    println!(
+        "dedup: {:?}",
+        readings.iter().dedup_by_key(|&&x| x).collect::<Vec<_>>()
+    );
+    println!(
+        "interleave: {:?}",
+        (1..4).interleave(vec![10, 20]).collect::<Vec<_>>()
+    );
+    for (even, group) in readings.iter().group_by(|&&x| x % 2 == 0) {
+        println!("group even={}: {:?}", even, group);
+    }
+    println!("minmax: {:?}", readings.iter().minmax());
+
+    let (evens, odds): (Vec<i32>, Vec<String>) = readings.iter().partition_map(|&x| {
+        if x % 2 == 0 {
+            Either::Left(x)
+        } else {
+            Either::Right(format!("odd {}", x))
+        }
+    });
+    println!("partition_map: {:?} / {:?}", evens, odds);
+
+    let names = ["Carol", "al", "Bob"];
+    println!(
+        "sorted by length: {:?}",
+        names.iter().sorted_by_key(|n| n.len()).collect::<Vec<_>>()
+    );
+}
+
+// Closures capturing nothing but their argument are pure, so their results can be cached
+fn memoized_closures() {
+    use memoize::{
+        memoize, memoize_lru, memoize_rec, sync_memoize, sync_memoize_lru, sync_memoize_rec,
+    };
+    use std::sync::Arc;
+    use std::thread;
+
+    let slow_square = memoize(|x: u64| {
+        println!("computing {} * {}", x, x);
+        x * x
+    });
+    println!("square(12) = {}", slow_square.call(12));
+    println!("square(12) = {}", slow_square.call(12));
+    println!("stats: {:?}", slow_square.stats());
+
+    let recent = memoize_lru(2, |name: &'static str| name.len());
+    for name in &["red", "green", "red", "blue", "green"] {
+        recent.call(name);
+    }
+    println!(
+        "lru stats: {:?}, cached: {}, hit ratio: {:.2}",
+        recent.stats(),
+        recent.len(),
+        recent.stats().hit_ratio()
+    );
+
+    // Recursive calls go through the `fib` handle, not through the closure itself
+    let fib = memoize_rec(
+        |fib: &dyn Fn(u64) -> u64, n: u64| {
+            if n < 2 {
+                n
+            } else {
+                fib(n - 1) + fib(n - 2)
+            }
+        },
+    );
+    println!("fib(80) = {}, stats: {:?}", fib.call(80), fib.stats());
+
+    let shared = Arc::new(sync_memoize(|x: u64| x.pow(3)));
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || (1..=10).map(|x| shared.call(x)).sum::<u64>())
+        })
+        .collect();
+    for handle in handles {
+        println!("sum of cubes: {}", handle.join().unwrap());
+    }
+    println!(
+        "shared stats: {:?}, cached: {}",
+        shared.stats(),
+        shared.len()
+    );
+
+    let shared_recent = sync_memoize_lru(8, |x: u64| x % 7);
+    let shared_fib = sync_memoize_rec(|fib: &dyn Fn(u32) -> u128, n: u32| {
+        if n < 2 {
+            n as u128
+        } else {
+            fib(n - 1) + fib(n - 2)
+        }
+    });
+    println!(
+        "lru empty before first call: {}, fib empty: {}",
+        shared_recent.is_empty(),
+        fib.is_empty()
+    );
+    println!("100 mod 7 = {}", shared_recent.call(100));
+    println!("fib(150) = {}", shared_fib.call(150));
+}
+
+// The `Fn`/`FnMut`/`FnOnce` closures of `closure_as_input_param` chained into a pipeline instead
+// of being nested by hand
+fn closure_pipeline() {
+    use pipeline::Pipeline;
+
+    let double = |x: i32| 2 * x;
+    let mut requests = 0;
+
+    let mut pipeline = Pipeline::<&str, &str, String>::new()
+        // may fail: requires `Fn(T) -> Result<U, E>`
+        .try_map("parse", |req| {
+            req.trim()
+                .parse::<i32>()
+                .map_err(|e| format!("{:?}: {}", req, e))
+        })
+        // requires `Fn(T) -> U`
+        .map("double", double)
+        // mutates captured state: requires `FnMut(T) -> U`
+        .map_mut("count", move |x| {
+            requests += 1;
+            format!("request #{} -> {}", requests, x)
+        });
+
+    for req in &["3", " 21 ", "three"] {
+        match pipeline.run(req) {
+            Ok(out) => println!("{}", out),
+            Err(e) => println!("{}", e),
+        }
+        for timing in pipeline.timings() {
+            println!("  {} took {:?}", timing.stage, timing.elapsed);
+        }
+    }
+    println!("last run took {:?}", pipeline.total_elapsed());
+
+    // The finalizer moves `farewell` out: requires `FnOnce(T) -> R`
+    let farewell = "goodbye".to_owned();
+    let said = pipeline.run_once("5", move |out| format!("{}, {}", out, farewell));
+    println!("{:?}", said);
+}
+
+// The sums from `derive_functions` and `higher_order_functions` rebuilt from lazy sequences
+fn lazy_sequences() {
+    use sequences::{below, checked_sum, evens, odds, primes, squares, triangular};
+
+    println!(
+        "sum of odd numbers up to 9(excluding): imperative {}, iterator {}",
+        sequences::sum_odd_imperative(9),
+        sequences::sum_odd_iter(9)
+    );
+    println!(
+        "sum of squared odd numbers under 1000: imperative {}, iterator {}",
+        sequences::sum_squared_odd_imperative(1000),
+        sequences::sum_squared_odd_iter(1000)
+    );
+
+    println!("odds under 10: {:?}", below(odds(), 10).collect::<Vec<_>>());
+    println!(
+        "evens under 10: {:?}",
+        below(evens(), 10).collect::<Vec<_>>()
+    );
+    println!(
+        "primes under 30: {:?}",
+        below(primes(), 30).collect::<Vec<_>>()
+    );
+    println!(
+        "first 5 triangular numbers: {:?}",
+        triangular().take(5).collect::<Vec<_>>()
+    );
+
+    // The sequences are infinite, so only a bounded prefix is summed
+    println!(
+        "checked sum of squares under 1_000_000: {:?}",
+        checked_sum(below(squares(), 1_000_000))
+    );
+    println!(
+        "checked sum overflowing u64: {:?}",
+        checked_sum(vec![u64::MAX, 1])
+    );
 }
 // NEVER return using `!` empty type
 
@@ -0,0 +1,476 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+
+// Wrapping an expensive `Fn(K) -> V` in a cache
+// The wrapped closure only needs `Fn`: the cache is the only thing mutated between calls, and it
+// lives behind interior mutability (`RefCell` for a single thread, `RwLock`/`Mutex` when shared),
+// so a memoized function is called through `&self` exactly like the closure it wraps
+//
+// Every wrapped function receives a handle to the memoized function itself as first argument, so
+// recursive calls go through the cache too (see `memoize_rec`)
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl CacheStats {
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+// The shape of every memoized function: the handle to recurse through, then the argument
+pub trait MemoFn<K, V>: Fn(&dyn Fn(K) -> V, K) -> V {}
+
+impl<K, V, F: Fn(&dyn Fn(K) -> V, K) -> V> MemoFn<K, V> for F {}
+
+// Storage policy of a memoized function
+pub trait Cache<K, V> {
+    fn lookup(&mut self, key: &K) -> Option<V>;
+    // Returns `true` when storing `key` evicted another entry
+    fn store(&mut self, key: K, value: V) -> bool;
+    fn len(&self) -> usize;
+}
+
+// Unbounded: every result is kept forever
+impl<K: Hash + Eq, V: Clone> Cache<K, V> for HashMap<K, V> {
+    fn lookup(&mut self, key: &K) -> Option<V> {
+        self.get(key).cloned()
+    }
+
+    fn store(&mut self, key: K, value: V) -> bool {
+        self.insert(key, value);
+        false
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+// Bounded: keeps at most `capacity` results and evicts the least recently used one
+// Every access stamps the entry with a fresh tick, `order` maps ticks back to keys so the oldest
+// entry is simply the first one in the `BTreeMap`
+pub struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V> Lru<K, V> {
+    pub fn new(capacity: usize) -> Lru<K, V> {
+        assert!(
+            capacity > 0,
+            "an LRU cache needs room for at least one entry"
+        );
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn touch(&mut self, key: &K) -> u64 {
+        self.tick += 1;
+        self.order.insert(self.tick, key.clone());
+        self.tick
+    }
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Cache<K, V> for Lru<K, V> {
+    fn lookup(&mut self, key: &K) -> Option<V> {
+        let (value, old_tick) = match self.entries.get(key) {
+            Some((value, tick)) => (value.clone(), *tick),
+            None => return None,
+        };
+        self.order.remove(&old_tick);
+        let tick = self.touch(key);
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.1 = tick;
+        }
+
+        Some(value)
+    }
+
+    fn store(&mut self, key: K, value: V) -> bool {
+        let mut evicted = false;
+        if let Some((_, old_tick)) = self.entries.remove(&key) {
+            self.order.remove(&old_tick);
+        } else if self.entries.len() == self.capacity {
+            let oldest = self.order.keys().next().copied();
+            if let Some(oldest) = oldest {
+                if let Some(key) = self.order.remove(&oldest) {
+                    self.entries.remove(&key);
+                    evicted = true;
+                }
+            }
+        }
+        let tick = self.touch(&key);
+        self.entries.insert(key, (value, tick));
+
+        evicted
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+// Single-threaded memoized function
+pub struct Memoized<K, V, F, C = HashMap<K, V>> {
+    f: F,
+    cache: RefCell<C>,
+    stats: Cell<CacheStats>,
+    _marker: std::marker::PhantomData<fn(K) -> V>,
+}
+
+impl<K, V, F, C> Memoized<K, V, F, C>
+where
+    K: Clone,
+    V: Clone,
+    F: MemoFn<K, V>,
+    C: Cache<K, V>,
+{
+    pub fn with_cache(cache: C, f: F) -> Self {
+        Memoized {
+            f,
+            cache: RefCell::new(cache),
+            stats: Cell::new(CacheStats::default()),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn call(&self, key: K) -> V {
+        // The borrow of the cache must end before `f` runs, bz a recursive `f` calls back in here
+        let cached = self.cache.borrow_mut().lookup(&key);
+        let mut stats = self.stats.get();
+        if let Some(value) = cached {
+            stats.hits += 1;
+            self.stats.set(stats);
+            return value;
+        }
+        stats.misses += 1;
+        self.stats.set(stats);
+
+        let value = (self.f)(&|k| self.call(k), key.clone());
+
+        let evicted = self.cache.borrow_mut().store(key, value.clone());
+        if evicted {
+            let mut stats = self.stats.get();
+            stats.evictions += 1;
+            self.stats.set(stats);
+        }
+
+        value
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats.get()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// Unbounded, map-backed memoization of a plain `Fn(K) -> V`
+pub fn memoize<K, V, F>(f: F) -> Memoized<K, V, impl MemoFn<K, V>>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    F: Fn(K) -> V,
+{
+    Memoized::with_cache(HashMap::new(), move |_: &dyn Fn(K) -> V, k| f(k))
+}
+
+// LRU-bounded memoization of a plain `Fn(K) -> V`
+pub fn memoize_lru<K, V, F>(capacity: usize, f: F) -> Memoized<K, V, impl MemoFn<K, V>, Lru<K, V>>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    F: Fn(K) -> V,
+{
+    Memoized::with_cache(Lru::new(capacity), move |_: &dyn Fn(K) -> V, k| f(k))
+}
+
+// Memoization of a recursive function: `f` gets the memoized function as first argument and must
+// recurse through it instead of calling itself
+//
+// let fib = memoize_rec(|fib, n: u64| if n < 2 { n } else { fib(n - 1) + fib(n - 2) });
+pub fn memoize_rec<K, V, F>(f: F) -> Memoized<K, V, F>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    F: MemoFn<K, V>,
+{
+    Memoized::with_cache(HashMap::new(), f)
+}
+
+// Storage policy of a memoized function shared between threads
+// Locks are only held while looking up or storing, never while the wrapped function runs: two
+// threads missing the same key may both compute it, but a slow call never blocks readers
+pub trait SharedCache<K, V>: Send + Sync {
+    fn lookup(&self, key: &K) -> Option<V>;
+    fn store(&self, key: K, value: V) -> bool;
+    fn len(&self) -> usize;
+}
+
+// Unbounded lookups don't mutate anything, so readers share a `RwLock`
+impl<K, V> SharedCache<K, V> for RwLock<HashMap<K, V>>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    fn lookup(&self, key: &K) -> Option<V> {
+        self.read().unwrap().get(key).cloned()
+    }
+
+    fn store(&self, key: K, value: V) -> bool {
+        self.write().unwrap().insert(key, value);
+        false
+    }
+
+    fn len(&self) -> usize {
+        self.read().unwrap().len()
+    }
+}
+
+// Any other policy (e.g. `Lru`, whose lookups reorder entries) needs exclusive access
+impl<K, V, C> SharedCache<K, V> for Mutex<C>
+where
+    C: Cache<K, V> + Send,
+{
+    fn lookup(&self, key: &K) -> Option<V> {
+        self.lock().unwrap().lookup(key)
+    }
+
+    fn store(&self, key: K, value: V) -> bool {
+        self.lock().unwrap().store(key, value)
+    }
+
+    fn len(&self) -> usize {
+        self.lock().unwrap().len()
+    }
+}
+
+// Thread-safe memoized function, `Sync` so it can be shared through an `Arc`
+pub struct SyncMemoized<K, V, F, C = RwLock<HashMap<K, V>>> {
+    f: F,
+    cache: C,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    _marker: std::marker::PhantomData<fn(K) -> V>,
+}
+
+impl<K, V, F, C> SyncMemoized<K, V, F, C>
+where
+    K: Clone,
+    V: Clone,
+    F: MemoFn<K, V> + Send + Sync,
+    C: SharedCache<K, V>,
+{
+    pub fn with_cache(cache: C, f: F) -> Self {
+        SyncMemoized {
+            f,
+            cache,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn call(&self, key: K) -> V {
+        if let Some(value) = self.cache.lookup(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return value;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let value = (self.f)(&|k| self.call(k), key.clone());
+        if self.cache.store(key, value.clone()) {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+
+        value
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// `RwLock`-backed, unbounded
+pub fn sync_memoize<K, V, F>(f: F) -> SyncMemoized<K, V, impl MemoFn<K, V> + Send + Sync>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    F: Fn(K) -> V + Send + Sync,
+{
+    SyncMemoized::with_cache(RwLock::new(HashMap::new()), move |_: &dyn Fn(K) -> V, k| {
+        f(k)
+    })
+}
+
+// `Mutex`-backed, LRU-bounded
+pub fn sync_memoize_lru<K, V, F>(
+    capacity: usize,
+    f: F,
+) -> SyncMemoized<K, V, impl MemoFn<K, V> + Send + Sync, Mutex<Lru<K, V>>>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    F: Fn(K) -> V + Send + Sync,
+{
+    SyncMemoized::with_cache(
+        Mutex::new(Lru::new(capacity)),
+        move |_: &dyn Fn(K) -> V, k| f(k),
+    )
+}
+
+// `RwLock`-backed, unbounded, recursive
+pub fn sync_memoize_rec<K, V, F>(f: F) -> SyncMemoized<K, V, F>
+where
+    K: Hash + Eq + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+    F: MemoFn<K, V> + Send + Sync,
+{
+    SyncMemoized::with_cache(RwLock::new(HashMap::new()), f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn unbounded_cache_counts_hits_and_misses() {
+        let calls = Cell::new(0);
+        let square = memoize(|x: i32| {
+            calls.set(calls.get() + 1);
+            x * x
+        });
+
+        assert_eq!(square.call(4), 16);
+        assert_eq!(square.call(4), 16);
+        assert_eq!(square.call(5), 25);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(
+            square.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 2,
+                evictions: 0
+            }
+        );
+    }
+
+    #[test]
+    fn lru_evicts_least_recently_used() {
+        let calls = Cell::new(0);
+        let double = memoize_lru(2, |x: i32| {
+            calls.set(calls.get() + 1);
+            x * 2
+        });
+
+        double.call(1);
+        double.call(2);
+        // touching 1 makes 2 the least recently used entry
+        double.call(1);
+        double.call(3);
+        assert_eq!(double.len(), 2);
+        assert_eq!(double.stats().evictions, 1);
+
+        double.call(1);
+        assert_eq!(calls.get(), 3);
+        double.call(2);
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn recursive_fibonacci_reuses_subproblems() {
+        let fib = memoize_rec(
+            |fib: &dyn Fn(u64) -> u64, n: u64| {
+                if n < 2 {
+                    n
+                } else {
+                    fib(n - 1) + fib(n - 2)
+                }
+            },
+        );
+
+        assert_eq!(fib.call(90), 2_880_067_194_370_816_120);
+        // every n in 0..=90 is computed exactly once
+        assert_eq!(fib.stats().misses, 91);
+    }
+
+    #[test]
+    fn sync_variants_share_cache_between_threads() {
+        let square = Arc::new(sync_memoize(|x: u64| x * x));
+        let lru = Arc::new(sync_memoize_lru(4, |x: u64| x + 1));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let square = Arc::clone(&square);
+                let lru = Arc::clone(&lru);
+                thread::spawn(move || {
+                    for x in 0..8 {
+                        assert_eq!(square.call(x), x * x);
+                        assert_eq!(lru.call(x % 4), x % 4 + 1);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(square.len(), 8);
+        assert_eq!(square.stats().hits + square.stats().misses, 32);
+        assert!(lru.len() <= 4);
+    }
+
+    #[test]
+    fn sync_recursive_fibonacci() {
+        let fib = sync_memoize_rec(|fib: &dyn Fn(u32) -> u128, n: u32| {
+            if n < 2 {
+                n as u128
+            } else {
+                fib(n - 1) + fib(n - 2)
+            }
+        });
+
+        assert_eq!(fib.call(150), 9_969_216_677_189_303_386_214_405_760_200);
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+// A chain of closures `I -> ... -> O` built stage by stage instead of nesting closures by hand
+// Every stage is stored boxed behind the same `FnMut(I) -> Result<O, _>` shape, so `Fn`, `FnMut`
+// and fallible stages can be mixed freely. `FnOnce` closures can only be called once, so they are
+// accepted as the finalizer of a one-shot run (`run_once`) rather than as a regular stage
+
+// Wall-clock time spent in one stage during the last run
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    pub elapsed: Duration,
+}
+
+// The first stage error short-circuits the run, tagged with the name of the failing stage
+#[derive(Debug, PartialEq)]
+pub struct StageError<E> {
+    pub stage: &'static str,
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for StageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "stage `{}` failed: {}", self.stage, self.error)
+    }
+}
+
+type Stages<'a, I, O, E> =
+    Box<dyn FnMut(I, &mut Vec<StageTiming>) -> Result<O, StageError<E>> + 'a>;
+
+pub struct Pipeline<'a, I, O, E> {
+    stages: Stages<'a, I, O, E>,
+    timings: Vec<StageTiming>,
+}
+
+impl<'a, I: 'a, E: 'a> Pipeline<'a, I, I, E> {
+    // An empty pipeline passes its input through untouched
+    pub fn new() -> Self {
+        Pipeline {
+            stages: Box::new(|input, _| Ok(input)),
+            timings: Vec::new(),
+        }
+    }
+}
+
+impl<'a, I: 'a, E: 'a> Default for Pipeline<'a, I, I, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, I: 'a, O: 'a, E: 'a> Pipeline<'a, I, O, E> {
+    // The most general stage: may keep state between runs and may fail
+    pub fn try_map_mut<U, F>(self, stage: &'static str, mut f: F) -> Pipeline<'a, I, U, E>
+    where
+        F: FnMut(O) -> Result<U, E> + 'a,
+    {
+        let mut prev = self.stages;
+        Pipeline {
+            stages: Box::new(move |input, timings| {
+                // `?` stops here when an earlier stage failed, later stages never run
+                let value = prev(input, timings)?;
+
+                let start = Instant::now();
+                let result = f(value);
+                timings.push(StageTiming {
+                    stage,
+                    elapsed: start.elapsed(),
+                });
+
+                result.map_err(|error| StageError { stage, error })
+            }),
+            timings: self.timings,
+        }
+    }
+
+    // A stateless, infallible stage: `Fn(O) -> U`
+    pub fn map<U, F>(self, stage: &'static str, f: F) -> Pipeline<'a, I, U, E>
+    where
+        F: Fn(O) -> U + 'a,
+    {
+        self.try_map_mut(stage, move |value| Ok(f(value)))
+    }
+
+    // A stage owning mutable state (a counter, a cache ...): `FnMut(O) -> U`
+    pub fn map_mut<U, F>(self, stage: &'static str, mut f: F) -> Pipeline<'a, I, U, E>
+    where
+        F: FnMut(O) -> U + 'a,
+    {
+        self.try_map_mut(stage, move |value| Ok(f(value)))
+    }
+
+    // A stateless stage which may reject its input: `Fn(O) -> Result<U, E>`
+    pub fn try_map<U, F>(self, stage: &'static str, f: F) -> Pipeline<'a, I, U, E>
+    where
+        F: Fn(O) -> Result<U, E> + 'a,
+    {
+        self.try_map_mut(stage, f)
+    }
+
+    // Runs every stage in order. The pipeline is only borrowed so it can be run again
+    pub fn run(&mut self, input: I) -> Result<O, StageError<E>> {
+        self.timings.clear();
+        (self.stages)(input, &mut self.timings)
+    }
+
+    // Runs the pipeline one last time and hands the output to an `FnOnce` finalizer
+    // Consumes the pipeline bz the finalizer may consume whatever it captured
+    pub fn run_once<R, F>(mut self, input: I, finalizer: F) -> Result<R, StageError<E>>
+    where
+        F: FnOnce(O) -> R,
+    {
+        let output = self.run(input)?;
+        Ok(finalizer(output))
+    }
+
+    // Per-stage timings of the last run, in execution order. A failed run only reports the
+    // stages which actually ran
+    pub fn timings(&self) -> &[StageTiming] {
+        &self.timings
+    }
+
+    pub fn total_elapsed(&self) -> Duration {
+        self.timings.iter().map(|t| t.elapsed).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<i32, String> {
+        input
+            .trim()
+            .parse()
+            .map_err(|_| format!("not a number: {:?}", input))
+    }
+
+    #[test]
+    fn stages_run_in_order() {
+        let mut pipeline = Pipeline::<&str, &str, String>::new()
+            .try_map("parse", parse)
+            .map("double", |x| x * 2)
+            .map("describe", |x| format!("got {}", x));
+
+        assert_eq!(pipeline.run(" 21 "), Ok("got 42".to_owned()));
+        let stages: Vec<_> = pipeline.timings().iter().map(|t| t.stage).collect();
+        assert_eq!(stages, vec!["parse", "double", "describe"]);
+    }
+
+    #[test]
+    fn errors_short_circuit() {
+        let mut pipeline = Pipeline::<&str, &str, String>::new()
+            .try_map("parse", parse)
+            .map("double", |x| x * 2);
+
+        let err = pipeline.run("abc").unwrap_err();
+        assert_eq!(err.stage, "parse");
+        assert_eq!(pipeline.timings().len(), 1);
+    }
+
+    #[test]
+    fn mutable_stages_keep_state_between_runs() {
+        let mut seen = 0;
+        let mut pipeline = Pipeline::<i32, i32, ()>::new().map_mut("count", move |x| {
+            seen += 1;
+            (seen, x)
+        });
+
+        assert_eq!(pipeline.run(10), Ok((1, 10)));
+        assert_eq!(pipeline.run(20), Ok((2, 20)));
+    }
+
+    #[test]
+    fn finalizer_consumes_captured_state() {
+        let mut log = vec!["start".to_owned()];
+        let pipeline = Pipeline::<i32, i32, ()>::new().map("square", |x| x * x);
+
+        let log = pipeline
+            .run_once(7, move |x| {
+                log.push(format!("result {}", x));
+                log
+            })
+            .unwrap();
+        assert_eq!(log, vec!["start".to_owned(), "result 49".to_owned()]);
+    }
+}
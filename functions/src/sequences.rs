@@ -0,0 +1,203 @@
+// Lazily generated numeric sequences
+// Every generator returns an iterator that computes nothing until it is driven by a consumer
+// (`take_while`, `sum`, `for` ...), so the unbounded ones are fine as long as they get a bound
+
+// 1, 3, 5, 7, ...
+pub fn odds() -> impl Iterator<Item = u64> {
+    (1..).step_by(2)
+}
+
+// 0, 2, 4, 6, ...
+pub fn evens() -> impl Iterator<Item = u64> {
+    (0..).step_by(2)
+}
+
+// 0, 1, 4, 9, ... up to the last square which fits an `u64`
+pub fn squares() -> impl Iterator<Item = u64> {
+    squares_from(0)
+}
+
+fn squares_from(n: u64) -> impl Iterator<Item = u64> {
+    (n..).map_while(|n| n.checked_mul(n))
+}
+
+// 0, 1, 3, 6, 10, ... the n-th term is the sum of 0..=n, up to the last one which fits an `u64`
+pub fn triangular() -> impl Iterator<Item = u64> {
+    triangular_from(0, 0)
+}
+
+// The terms from index `n` on, `sum` being the term before it
+fn triangular_from(n: u64, sum: u64) -> impl Iterator<Item = u64> {
+    (n..).scan(sum, |acc, n| {
+        *acc = acc.checked_add(n)?;
+        Some(*acc)
+    })
+}
+
+// 2, 3, 5, 7, 11, ...
+pub fn primes() -> Primes {
+    Primes { found: Vec::new() }
+}
+
+// Incremental trial division: a candidate only has to be checked against the primes found so far
+// which are not greater than its square root
+pub struct Primes {
+    found: Vec<u64>,
+}
+
+impl Iterator for Primes {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let mut candidate = match self.found.last() {
+            None => 2,
+            Some(2) => 3,
+            Some(&last) => last + 2,
+        };
+
+        while !self
+            .found
+            .iter()
+            .take_while(|&&p| p * p <= candidate)
+            .all(|&p| candidate % p != 0)
+        {
+            candidate += 2;
+        }
+
+        self.found.push(candidate);
+        Some(candidate)
+    }
+}
+
+// Bounds any (ascending) sequence to the values strictly below `upper`
+// `take_while` stops at the first value failing the predicate, which is what makes it safe for
+// infinite sequences where `filter` would loop forever
+pub fn below<I>(seq: I, upper: u64) -> impl Iterator<Item = u64>
+where
+    I: IntoIterator<Item = u64>,
+{
+    seq.into_iter().take_while(move |&n| n < upper)
+}
+
+// Sums the sequence, returning `None` instead of wrapping (release) or panicking (debug) on
+// overflow. `try_fold` short-circuits at the first `None` produced by `checked_add`
+pub fn checked_sum<I>(seq: I) -> Option<u64>
+where
+    I: IntoIterator<Item = u64>,
+{
+    seq.into_iter().try_fold(0u64, |acc, n| acc.checked_add(n))
+}
+
+// `derive_functions::sume_odd_numbers`: sum of the odd numbers in 0..up_to
+pub fn sum_odd_imperative(up_to: u64) -> u64 {
+    let mut acc = 0;
+    for i in 0..up_to {
+        if i % 2 == 1 {
+            acc += i;
+        }
+    }
+
+    acc
+}
+
+pub fn sum_odd_iter(up_to: u64) -> u64 {
+    below(odds(), up_to).sum()
+}
+
+// `higher_order_functions`: sum of all the squared odd numbers under `upper`
+pub fn sum_squared_odd_imperative(upper: u64) -> u64 {
+    let mut acc = 0;
+    for n in 0.. {
+        let n_squared = n * n;
+
+        if n_squared >= upper {
+            break;
+        } else if n_squared % 2 == 1 {
+            acc += n_squared;
+        }
+    }
+
+    acc
+}
+
+pub fn sum_squared_odd_iter(upper: u64) -> u64 {
+    below(squares(), upper).filter(|n| n % 2 == 1).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test::{black_box, Bencher};
+
+    #[test]
+    fn generators_yield_expected_prefixes() {
+        assert_eq!(odds().take(4).collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+        assert_eq!(evens().take(4).collect::<Vec<_>>(), vec![0, 2, 4, 6]);
+        assert_eq!(squares().take(4).collect::<Vec<_>>(), vec![0, 1, 4, 9]);
+        assert_eq!(
+            triangular().take(5).collect::<Vec<_>>(),
+            vec![0, 1, 3, 6, 10]
+        );
+        assert_eq!(
+            primes().take(8).collect::<Vec<_>>(),
+            vec![2, 3, 5, 7, 11, 13, 17, 19]
+        );
+    }
+
+    #[test]
+    fn generators_end_before_overflowing() {
+        // (2^32 - 1)^2 is the last square below 2^64
+        let last = u64::from(u32::MAX);
+        assert_eq!(squares_from(last).collect::<Vec<_>>(), vec![last * last]);
+
+        // T(n) = n (n + 1) / 2, the last one which fits is T(6_074_000_999)
+        let n = 6_074_000_999u64;
+        let before = (n - 1) / 2 * n;
+        assert_eq!(
+            triangular_from(n, before).collect::<Vec<_>>(),
+            vec![before + n]
+        );
+    }
+
+    #[test]
+    fn below_stops_infinite_sequences() {
+        assert_eq!(below(primes(), 20).count(), 8);
+        assert_eq!(below(odds(), 1).count(), 0);
+    }
+
+    #[test]
+    fn imperative_and_iterator_forms_agree() {
+        for n in 0..200 {
+            assert_eq!(sum_odd_imperative(n), sum_odd_iter(n));
+        }
+        assert_eq!(sum_squared_odd_imperative(1000), 5456);
+        assert_eq!(sum_squared_odd_iter(1000), 5456);
+    }
+
+    #[test]
+    fn checked_sum_detects_overflow() {
+        assert_eq!(checked_sum(below(odds(), 10)), Some(25));
+        assert_eq!(checked_sum(vec![u64::MAX, 1]), None);
+        assert_eq!(checked_sum(vec![u64::MAX - 1, 1]), Some(u64::MAX));
+    }
+
+    #[bench]
+    fn bench_sum_squared_odd_imperative(b: &mut Bencher) {
+        b.iter(|| sum_squared_odd_imperative(black_box(1_000_000)));
+    }
+
+    #[bench]
+    fn bench_sum_squared_odd_iter(b: &mut Bencher) {
+        b.iter(|| sum_squared_odd_iter(black_box(1_000_000)));
+    }
+
+    #[bench]
+    fn bench_sum_odd_imperative(b: &mut Bencher) {
+        b.iter(|| sum_odd_imperative(black_box(100_000)));
+    }
+
+    #[bench]
+    fn bench_sum_odd_iter(b: &mut Bencher) {
+        b.iter(|| sum_odd_iter(black_box(100_000)));
+    }
+}
@@ -0,0 +1,333 @@
+use std::fmt;
+use std::ops::Sub;
+use std::str::FromStr;
+
+// Calendar-correct replacements for the `Years`/`Days` newtypes of `generic_new_type_idiom`
+// A `Date` is a day count in the proleptic Gregorian calendar (the Gregorian leap year rules
+// extended backwards before 1582), so the 365-day shortcut is never needed:
+// - `Date - Date` is an exact number of `Days`
+// - `Date::age_on` counts the birthdays that actually happened, leap days included
+
+// Supported years, four digits in ISO-8601 form
+pub const MIN_YEAR: i32 = 0;
+pub const MAX_YEAR: i32 = 9999;
+
+// A signed number of days
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Days(pub i64);
+
+// A number of complete years, e.g. an age
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Years(pub u32);
+
+impl Days {
+    pub fn checked_add(self, rhs: Days) -> Option<Days> {
+        self.0.checked_add(rhs.0).map(Days)
+    }
+
+    pub fn checked_sub(self, rhs: Days) -> Option<Days> {
+        self.0.checked_sub(rhs.0).map(Days)
+    }
+}
+
+// Only valid dates can be built: `Date::new` and parsing check the calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    // days since 1970-01-01
+    days: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateError {
+    YearOutOfRange(i32),
+    InvalidMonth(u32),
+    InvalidDay { year: i32, month: u32, day: u32 },
+    // The string is not `YYYY-MM-DD`
+    Format(String),
+    Overflow,
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DateError::YearOutOfRange(y) => {
+                write!(f, "year {} is outside {}..={}", y, MIN_YEAR, MAX_YEAR)
+            }
+            DateError::InvalidMonth(m) => write!(f, "month {} is not in 1..=12", m),
+            DateError::InvalidDay { year, month, day } => {
+                write!(f, "{:04}-{:02} has no day {}", year, month, day)
+            }
+            DateError::Format(s) => write!(f, "{:?} is not an ISO-8601 date (YYYY-MM-DD)", s),
+            DateError::Overflow => write!(f, "date arithmetic out of range"),
+        }
+    }
+}
+
+pub fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+// The conversions below follow H. Hinnant's `days_from_civil`/`civil_from_days`: shifting the
+// year to start in March puts the leap day at the very end, so every other month has a fixed
+// offset and a 400-year era always has 146097 days
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year } as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+
+    (year, month, day)
+}
+
+impl Date {
+    pub fn new(year: i32, month: u32, day: u32) -> Result<Date, DateError> {
+        if !(MIN_YEAR..=MAX_YEAR).contains(&year) {
+            return Err(DateError::YearOutOfRange(year));
+        }
+        if !(1..=12).contains(&month) {
+            return Err(DateError::InvalidMonth(month));
+        }
+        if day == 0 || day > days_in_month(year, month) {
+            return Err(DateError::InvalidDay { year, month, day });
+        }
+
+        Ok(Date {
+            days: days_from_civil(year, month, day),
+        })
+    }
+
+    // 1970-01-01 is day 0
+    pub fn from_days_since_epoch(days: i64) -> Result<Date, DateError> {
+        let min = days_from_civil(MIN_YEAR, 1, 1);
+        let max = days_from_civil(MAX_YEAR, 12, 31);
+        if (min..=max).contains(&days) {
+            Ok(Date { days })
+        } else {
+            Err(DateError::Overflow)
+        }
+    }
+
+    pub fn days_since_epoch(&self) -> i64 {
+        self.days
+    }
+
+    pub fn year(&self) -> i32 {
+        civil_from_days(self.days).0
+    }
+
+    pub fn month(&self) -> u32 {
+        civil_from_days(self.days).1
+    }
+
+    pub fn day(&self) -> u32 {
+        civil_from_days(self.days).2
+    }
+
+    pub fn checked_add(self, days: Days) -> Option<Date> {
+        let days = self.days.checked_add(days.0)?;
+        Date::from_days_since_epoch(days).ok()
+    }
+
+    pub fn checked_sub(self, days: Days) -> Option<Date> {
+        let days = self.days.checked_sub(days.0)?;
+        Date::from_days_since_epoch(days).ok()
+    }
+
+    // Same month and day `years` later. Feb 29 becomes Feb 28 in a non-leap target year
+    pub fn checked_add_years(self, years: i32) -> Option<Date> {
+        let (year, month, day) = civil_from_days(self.days);
+        let year = year.checked_add(years)?;
+        let day = day.min(days_in_month(year, month));
+        Date::new(year, month, day).ok()
+    }
+
+    // Complete years from `self` to `on`, or `None` when `on` is before `self`
+    // A birthday counts once its month and day have been reached. For someone born on Feb 29
+    // that is Mar 1 in non-leap years
+    pub fn age_on(&self, on: Date) -> Option<Years> {
+        if on < *self {
+            return None;
+        }
+        let (by, bm, bd) = civil_from_days(self.days);
+        let (y, m, d) = civil_from_days(on.days);
+
+        let mut years = y - by;
+        if (m, d) < (bm, bd) {
+            years -= 1;
+        }
+        Some(Years(years as u32))
+    }
+}
+
+// The exact number of days between two dates
+impl Sub for Date {
+    type Output = Days;
+
+    fn sub(self, rhs: Date) -> Days {
+        Days(self.days - rhs.days)
+    }
+}
+
+// ISO-8601 extended calendar date: `YYYY-MM-DD`
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (year, month, day) = civil_from_days(self.days);
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = DateError;
+
+    fn from_str(s: &str) -> Result<Date, DateError> {
+        let format = || DateError::Format(s.to_owned());
+        let bytes = s.as_bytes();
+        if !s.is_ascii() || bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+            return Err(format());
+        }
+        let field = |range: std::ops::Range<usize>| {
+            let digits = &s[range];
+            if digits.bytes().all(|b| b.is_ascii_digit()) {
+                digits.parse::<u32>().map_err(|_| format())
+            } else {
+                Err(format())
+            }
+        };
+
+        Date::new(field(0..4)? as i32, field(5..7)?, field(8..10)?)
+    }
+}
+
+// The original `old_enough` compared `age.0 > 18`, so it was wrong on the 18th birthday itself
+pub fn old_enough(birth: Date, today: Date, min_age: Years) -> bool {
+    birth.age_on(today).is_some_and(|age| age >= min_age)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn round_trips_every_day_of_a_few_eras() {
+        let start = date("1599-01-01").days_since_epoch();
+        let end = date("2401-12-31").days_since_epoch();
+        for days in start..=end {
+            let d = Date::from_days_since_epoch(days).unwrap();
+            assert_eq!(Date::new(d.year(), d.month(), d.day()).unwrap(), d);
+            assert_eq!(date(&d.to_string()), d);
+        }
+    }
+
+    #[test]
+    fn known_day_counts() {
+        assert_eq!(date("1970-01-01").days_since_epoch(), 0);
+        assert_eq!(date("2000-03-01").days_since_epoch(), 11_017);
+        assert_eq!(date("2024-03-01") - date("2024-02-01"), Days(29));
+        assert_eq!(date("2023-03-01") - date("2023-02-01"), Days(28));
+        assert_eq!(date("2001-01-01") - date("2000-01-01"), Days(366));
+        assert_eq!(date("1901-01-01") - date("1900-01-01"), Days(365));
+    }
+
+    #[test]
+    fn leap_years() {
+        assert!(is_leap_year(2000));
+        assert!(is_leap_year(2024));
+        assert!(!is_leap_year(1900));
+        assert!(!is_leap_year(2023));
+        assert!(Date::new(2023, 2, 29).is_err());
+        assert!(Date::new(2024, 2, 29).is_ok());
+    }
+
+    #[test]
+    fn age_counts_actual_birthdays() {
+        let birth = date("2006-05-20");
+        assert_eq!(birth.age_on(date("2024-05-19")), Some(Years(17)));
+        assert_eq!(birth.age_on(date("2024-05-20")), Some(Years(18)));
+        assert_eq!(birth.age_on(date("2000-01-01")), None);
+        assert!(old_enough(birth, date("2024-05-20"), Years(18)));
+        assert!(!old_enough(birth, date("2024-05-19"), Years(18)));
+
+        let leapling = date("2004-02-29");
+        assert_eq!(leapling.age_on(date("2022-02-28")), Some(Years(17)));
+        assert_eq!(leapling.age_on(date("2022-03-01")), Some(Years(18)));
+        assert_eq!(leapling.age_on(date("2024-02-29")), Some(Years(20)));
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(
+            date("2024-02-28").checked_add(Days(1)),
+            Some(date("2024-02-29"))
+        );
+        assert_eq!(
+            date("2024-03-01").checked_sub(Days(1)),
+            Some(date("2024-02-29"))
+        );
+        assert_eq!(date("9999-12-31").checked_add(Days(1)), None);
+        assert_eq!(date("0000-01-01").checked_sub(Days(1)), None);
+        assert_eq!(date("2000-01-01").checked_add(Days(i64::MAX)), None);
+        assert_eq!(
+            date("2024-02-29").checked_add_years(1),
+            Some(date("2025-02-28"))
+        );
+        assert_eq!(Days(i64::MAX).checked_add(Days(1)), None);
+    }
+
+    #[test]
+    fn parsing_rejects_malformed_dates() {
+        assert!(matches!(
+            "2024-1-01".parse::<Date>(),
+            Err(DateError::Format(_))
+        ));
+        assert!(matches!(
+            "2024-01-0a".parse::<Date>(),
+            Err(DateError::Format(_))
+        ));
+        assert!(matches!(
+            "+202-01-01".parse::<Date>(),
+            Err(DateError::Format(_))
+        ));
+        assert_eq!(
+            "2024-13-01".parse::<Date>(),
+            Err(DateError::InvalidMonth(13))
+        );
+        assert_eq!(
+            "2023-04-31".parse::<Date>(),
+            Err(DateError::InvalidDay {
+                year: 2023,
+                month: 4,
+                day: 31
+            })
+        );
+    }
+}
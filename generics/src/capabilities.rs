@@ -0,0 +1,354 @@
+// Capabilities on the idea of `Red`/`Blue` in `generic_empty_bounds`: `red()` only accepts a
+// `Cardinal` because only `Cardinal: Red`. Here the marker traits `CanRead`, `CanWrite` and
+// `CanAdmin` are implemented by capability *sets*, and a `Token<Caps>` proves its holder was given
+// that set. An operation simply asks for a `&Token<C>` with `C: CanWrite`
+// - tokens can't be forged: `Token` has a private field and the only constructor is `root()`,
+//   which hands out a single root token per process
+// - `attenuate` turns a token into one with fewer capabilities, never more
+use std::collections::BTreeMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Type-level booleans
+#[derive(Debug, Clone, Copy)]
+pub enum Yes {}
+#[derive(Debug, Clone, Copy)]
+pub enum No {}
+
+pub trait Flag {
+    const VALUE: bool;
+}
+
+impl Flag for Yes {
+    const VALUE: bool = true;
+}
+
+impl Flag for No {
+    const VALUE: bool = false;
+}
+
+// `Self` implies at most `Other`: the only missing impl is `Yes: AtMost<No>`
+pub trait AtMost<Other: Flag>: Flag {}
+
+impl AtMost<No> for No {}
+impl AtMost<Yes> for No {}
+impl AtMost<Yes> for Yes {}
+
+// A capability set, one flag per capability
+#[derive(Debug, Clone, Copy)]
+pub struct Caps<Read: Flag, Write: Flag, Admin: Flag>(PhantomData<(Read, Write, Admin)>);
+
+pub type ReadOnly = Caps<Yes, No, No>;
+pub type ReadWrite = Caps<Yes, Yes, No>;
+pub type Root = Caps<Yes, Yes, Yes>;
+
+pub trait CapSet {
+    const READ: bool;
+    const WRITE: bool;
+    const ADMIN: bool;
+}
+
+impl<R: Flag, W: Flag, A: Flag> CapSet for Caps<R, W, A> {
+    const READ: bool = R::VALUE;
+    const WRITE: bool = W::VALUE;
+    const ADMIN: bool = A::VALUE;
+}
+
+pub trait CanRead: CapSet {}
+pub trait CanWrite: CapSet {}
+pub trait CanAdmin: CapSet {}
+
+impl<W: Flag, A: Flag> CanRead for Caps<Yes, W, A> {}
+impl<R: Flag, A: Flag> CanWrite for Caps<R, Yes, A> {}
+impl<R: Flag, W: Flag> CanAdmin for Caps<R, W, Yes> {}
+
+// Every capability of `Self` is also in `Other`
+pub trait SubsetOf<Other> {}
+
+impl<R1, W1, A1, R2, W2, A2> SubsetOf<Caps<R2, W2, A2>> for Caps<R1, W1, A1>
+where
+    R1: AtMost<R2>,
+    W1: AtMost<W2>,
+    A1: AtMost<A2>,
+    R2: Flag,
+    W2: Flag,
+    A2: Flag,
+{
+}
+
+pub struct Token<C: CapSet> {
+    _caps: PhantomData<C>,
+}
+
+static ROOT_TAKEN: AtomicBool = AtomicBool::new(false);
+
+impl Token<Root> {
+    // The one place tokens come from: taken once at startup, then attenuated and handed out.
+    // `None` once the root token was taken
+    pub fn root() -> Option<Token<Root>> {
+        if ROOT_TAKEN.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            Some(Token { _caps: PhantomData })
+        }
+    }
+}
+
+impl<C: CapSet> Token<C> {
+    pub fn attenuate<Fewer>(&self) -> Token<Fewer>
+    where
+        Fewer: CapSet + SubsetOf<C>,
+    {
+        Token { _caps: PhantomData }
+    }
+}
+
+// A copy has exactly the same capabilities, so cloning doesn't amplify anything
+impl<C: CapSet> Clone for Token<C> {
+    fn clone(&self) -> Token<C> {
+        Token { _caps: PhantomData }
+    }
+}
+
+impl<C: CapSet> fmt::Debug for Token<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [(C::READ, "read"), (C::WRITE, "write"), (C::ADMIN, "admin")];
+        let caps: Vec<&str> = names.iter().filter(|c| c.0).map(|c| c.1).collect();
+        write!(f, "Token<{}>", caps.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountError {
+    Unknown(String),
+    AlreadyExists(String),
+    InsufficientFunds { account: String, balance: u64 },
+    NotEmpty(String),
+    Overflow { account: String, balance: u64 },
+}
+
+impl fmt::Display for AccountError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccountError::Unknown(id) => write!(f, "no account {:?}", id),
+            AccountError::AlreadyExists(id) => write!(f, "account {:?} already exists", id),
+            AccountError::InsufficientFunds { account, balance } => {
+                write!(f, "account {:?} only holds {}", account, balance)
+            }
+            AccountError::NotEmpty(id) => write!(f, "account {:?} still holds money", id),
+            AccountError::Overflow { account, balance } => {
+                write!(f, "account {:?} can't hold more than {}", account, balance)
+            }
+        }
+    }
+}
+
+// Reading needs `CanRead`, moving money `CanWrite`, opening and closing accounts `CanAdmin`
+#[derive(Debug, Default)]
+pub struct Accounts {
+    balances: BTreeMap<String, u64>,
+}
+
+impl Accounts {
+    pub fn new() -> Accounts {
+        Accounts::default()
+    }
+
+    pub fn balance<C: CanRead>(&self, _: &Token<C>, id: &str) -> Result<u64, AccountError> {
+        self.balances
+            .get(id)
+            .copied()
+            .ok_or_else(|| AccountError::Unknown(id.to_owned()))
+    }
+
+    pub fn ids<C: CanRead>(&self, _: &Token<C>) -> Vec<&str> {
+        self.balances.keys().map(String::as_str).collect()
+    }
+
+    pub fn deposit<C: CanWrite>(
+        &mut self,
+        _: &Token<C>,
+        id: &str,
+        amount: u64,
+    ) -> Result<u64, AccountError> {
+        let balance = self.account_mut(id)?;
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| AccountError::Overflow {
+                account: id.to_owned(),
+                balance: *balance,
+            })?;
+        Ok(*balance)
+    }
+
+    pub fn withdraw<C: CanWrite>(
+        &mut self,
+        _: &Token<C>,
+        id: &str,
+        amount: u64,
+    ) -> Result<u64, AccountError> {
+        let balance = self.account_mut(id)?;
+        if *balance < amount {
+            return Err(AccountError::InsufficientFunds {
+                account: id.to_owned(),
+                balance: *balance,
+            });
+        }
+        *balance -= amount;
+        Ok(*balance)
+    }
+
+    pub fn open<C: CanAdmin>(&mut self, _: &Token<C>, id: &str) -> Result<(), AccountError> {
+        if self.balances.contains_key(id) {
+            return Err(AccountError::AlreadyExists(id.to_owned()));
+        }
+        self.balances.insert(id.to_owned(), 0);
+        Ok(())
+    }
+
+    // Only empty accounts can be closed
+    pub fn close<C: CanAdmin>(&mut self, _: &Token<C>, id: &str) -> Result<(), AccountError> {
+        match self.balances.get(id) {
+            None => Err(AccountError::Unknown(id.to_owned())),
+            Some(0) => {
+                self.balances.remove(id);
+                Ok(())
+            }
+            Some(_) => Err(AccountError::NotEmpty(id.to_owned())),
+        }
+    }
+
+    fn account_mut(&mut self, id: &str) -> Result<&mut u64, AccountError> {
+        self.balances
+            .get_mut(id)
+            .ok_or_else(|| AccountError::Unknown(id.to_owned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::{assert_compile_fail, assert_compiles};
+    use std::sync::OnceLock;
+
+    const SRC: &str = include_str!("capabilities.rs");
+
+    const PRELUDE: &str = "use crate::capabilities::*;";
+
+    // The tests run in one process, so they share its only root token
+    fn root() -> Token<Root> {
+        static ROOT: OnceLock<Token<Root>> = OnceLock::new();
+        ROOT.get_or_init(|| Token::root().expect("nothing else takes the root token"))
+            .clone()
+    }
+
+    fn fail(body: &str, expected: &str) {
+        let snippet = format!("{}\nfn f() {{ {} }}", PRELUDE, body);
+        assert_compile_fail("capabilities", SRC, &snippet, expected);
+    }
+
+    #[test]
+    fn capabilities_gate_the_operations() {
+        let root = root();
+        let mut accounts = Accounts::new();
+        accounts.open(&root, "alice").unwrap();
+
+        let teller: Token<ReadWrite> = root.attenuate();
+        assert_eq!(accounts.deposit(&teller, "alice", 50), Ok(50));
+        assert_eq!(
+            accounts.withdraw(&teller, "alice", 80),
+            Err(AccountError::InsufficientFunds {
+                account: "alice".to_owned(),
+                balance: 50
+            })
+        );
+
+        let auditor: Token<ReadOnly> = teller.attenuate();
+        assert_eq!(accounts.balance(&auditor, "alice"), Ok(50));
+        assert_eq!(accounts.ids(&auditor), ["alice"]);
+
+        assert_eq!(
+            accounts.close(&root, "alice"),
+            Err(AccountError::NotEmpty("alice".to_owned()))
+        );
+    }
+
+    #[test]
+    fn there_is_one_root_token() {
+        let _ = root();
+        assert!(Token::root().is_none());
+    }
+
+    #[test]
+    fn deposits_refuse_to_overflow() {
+        let root = root();
+        let mut accounts = Accounts::new();
+        accounts.open(&root, "alice").unwrap();
+        assert_eq!(
+            accounts.deposit(&root, "alice", u64::MAX - 1),
+            Ok(u64::MAX - 1)
+        );
+        assert_eq!(
+            accounts.deposit(&root, "alice", 2),
+            Err(AccountError::Overflow {
+                account: "alice".to_owned(),
+                balance: u64::MAX - 1
+            })
+        );
+        assert_eq!(accounts.balance(&root, "alice"), Ok(u64::MAX - 1));
+    }
+
+    #[test]
+    fn debug_lists_the_capabilities() {
+        let root = root();
+        assert_eq!(format!("{:?}", root), "Token<read, write, admin>");
+        let write_only: Token<Caps<No, Yes, No>> = root.attenuate();
+        assert_eq!(format!("{:?}", write_only), "Token<write>");
+    }
+
+    #[test]
+    fn attenuation_compiles() {
+        let snippet = format!(
+            "{}\nfn f() {{ let t: Token<ReadOnly> = Token::root().unwrap().attenuate(); \
+             let same: Token<ReadOnly> = t.attenuate(); Accounts::new().balance(&same, \"a\").ok(); }}",
+            PRELUDE
+        );
+        assert_compiles("capabilities", SRC, &snippet);
+    }
+
+    #[test]
+    fn amplification_does_not_compile() {
+        fail(
+            "let t: Token<ReadOnly> = Token::root().unwrap().attenuate(); \
+             let _: Token<ReadWrite> = t.attenuate();",
+            "E0277",
+        );
+    }
+
+    #[test]
+    fn writing_with_a_read_only_token_does_not_compile() {
+        fail(
+            "let t: Token<ReadOnly> = Token::root().unwrap().attenuate(); \
+             Accounts::new().deposit(&t, \"a\", 1).ok();",
+            "E0277",
+        );
+    }
+
+    #[test]
+    fn admin_needs_the_admin_capability() {
+        fail(
+            "let t: Token<ReadWrite> = Token::root().unwrap().attenuate(); \
+             Accounts::new().open(&t, \"a\").ok();",
+            "E0277",
+        );
+    }
+
+    #[test]
+    fn tokens_cannot_be_forged() {
+        fail(
+            "let _: Token<Root> = Token { _caps: std::marker::PhantomData };",
+            "E0451",
+        );
+    }
+}
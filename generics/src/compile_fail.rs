@@ -0,0 +1,71 @@
+// A tiny compile-fail harness for the tests of the modules whose whole point is that misuse does
+// not compile (`typestate`, ...)
+// The module source is pasted into a scratch crate together with a snippet using it, and the
+// crate is checked with `rustc`. Without an external crate such as `trybuild` this is the only way
+// to assert that something is rejected by the type checker
+use std::env;
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+// Returns whether the crate compiled, and the compiler's diagnostics
+fn check(module: &str, module_src: &str, snippet: &str) -> (bool, String) {
+    let dir = env::temp_dir().join(format!(
+        "generics_compile_fail_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("snippet.rs");
+    let src = format!(
+        "#![allow(dead_code, unused)]\nmod {} {{\n{}\n}}\n{}\n",
+        module, module_src, snippet
+    );
+    fs::write(&file, src).unwrap();
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_owned());
+    let output = Command::new(rustc)
+        .args([
+            "--edition",
+            "2018",
+            "--crate-type",
+            "lib",
+            "--emit",
+            "metadata",
+        ])
+        .arg("--out-dir")
+        .arg(&dir)
+        .arg(&file)
+        .output()
+        .expect("failed to run rustc");
+    let _ = fs::remove_dir_all(&dir);
+
+    (
+        output.status.success(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    )
+}
+
+pub fn assert_compiles(module: &str, module_src: &str, snippet: &str) {
+    let (ok, stderr) = check(module, module_src, snippet);
+    assert!(
+        ok,
+        "expected the snippet to compile:\n{}\n{}",
+        snippet, stderr
+    );
+}
+
+// `expected` is a part of the diagnostics, typically an error code such as `E0599`
+pub fn assert_compile_fail(module: &str, module_src: &str, snippet: &str, expected: &str) {
+    let (ok, stderr) = check(module, module_src, snippet);
+    assert!(!ok, "expected the snippet NOT to compile:\n{}", snippet);
+    assert!(
+        stderr.contains(expected),
+        "expected {:?} in the diagnostics of:\n{}\n{}",
+        expected,
+        snippet,
+        stderr
+    );
+}
@@ -0,0 +1,411 @@
+// `PrintInOption` from `generic_where_clauses` grown into a dump facility for state snapshots
+// Like there, the blanket impl is bounded by what's actually formatted, `T: Debug`, so every
+// debuggable type gets `dump` for free. The pretty `{:#?}` output is regular enough (one field per
+// line, four spaces per level, strings escaped onto one line) to be post-processed line by line:
+// - levels deeper than `max_depth` are collapsed into `...`
+// - the values of fields and map keys named in `redact` become `<redacted>`
+// Two dumps of the same type can then be compared line by line with `Dump::diff`
+//
+// Redaction only sees the text: a field is found by its `name: ` at the start of a line. A custom
+// `Debug` which prints a secret on one line with other values, or under another name, leaks it.
+// Such a type has to keep the secret out of its own `Debug`
+use std::collections::HashSet;
+use std::fmt::{self, Debug};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+const INDENT: usize = 4;
+
+#[derive(Debug, Clone, Default)]
+pub struct DumpOptions {
+    max_depth: Option<usize>,
+    redact: HashSet<String>,
+}
+
+impl DumpOptions {
+    pub fn new() -> DumpOptions {
+        DumpOptions::default()
+    }
+
+    // `0` only keeps the outermost line(s) of the value
+    pub fn max_depth(mut self, depth: usize) -> DumpOptions {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    pub fn redact(mut self, field: &str) -> DumpOptions {
+        self.redact.insert(field.to_owned());
+        self
+    }
+}
+
+pub trait DebugDump {
+    fn dump(&self, options: &DumpOptions) -> Dump;
+
+    fn dump_into<W: Write>(&self, options: &DumpOptions, out: W) -> io::Result<()> {
+        self.dump(options).write_to(out)
+    }
+
+    fn dump_to_file<P: AsRef<Path>>(&self, options: &DumpOptions, path: P) -> io::Result<()> {
+        self.dump(options).write_to(File::create(path)?)
+    }
+}
+
+impl<T: ?Sized> DebugDump for T
+where
+    T: Debug,
+{
+    fn dump(&self, options: &DumpOptions) -> Dump {
+        let pretty = format!("{:#?}", self);
+        let lines = redact(pretty.lines(), &options.redact);
+        let lines = match options.max_depth {
+            Some(depth) => limit_depth(lines, depth),
+            None => lines,
+        };
+        Dump { lines }
+    }
+}
+
+fn depth_of(line: &str) -> usize {
+    (line.len() - line.trim_start().len()) / INDENT
+}
+
+// A line opening a nested value, closed later by a line at the same depth
+fn opens(line: &str) -> bool {
+    line.ends_with('{') || line.ends_with('[') || line.ends_with('(')
+}
+
+// `name` of a `name: value` line, struct fields are bare and map keys quoted
+fn field_name(line: &str) -> Option<&str> {
+    let (key, _) = line.trim_start().split_once(": ")?;
+    let name = if key.len() >= 2 && key.starts_with('"') && key.ends_with('"') {
+        &key[1..key.len() - 1]
+    } else {
+        key
+    };
+    let is_ident = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if is_ident {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+fn redact<'a, I: Iterator<Item = &'a str>>(lines: I, fields: &HashSet<String>) -> Vec<String> {
+    let mut out = Vec::new();
+    // depth of the redacted value being skipped
+    let mut skipping: Option<usize> = None;
+    for line in lines {
+        let depth = depth_of(line);
+        if let Some(d) = skipping {
+            if depth == d {
+                skipping = None;
+            }
+            continue;
+        }
+        if field_name(line).is_some_and(|name| fields.contains(name)) {
+            let (key, _) = line.split_once(": ").unwrap();
+            out.push(format!("{}: <redacted>,", key));
+            if opens(line) {
+                skipping = Some(depth);
+            }
+        } else {
+            out.push(line.to_owned());
+        }
+    }
+    out
+}
+
+fn limit_depth(lines: Vec<String>, max_depth: usize) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut hidden = false;
+    for line in lines {
+        if depth_of(&line) > max_depth {
+            if !hidden {
+                out.push(format!("{}...", " ".repeat((max_depth + 1) * INDENT)));
+                hidden = true;
+            }
+        } else {
+            hidden = false;
+            out.push(line);
+        }
+    }
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dump {
+    lines: Vec<String>,
+}
+
+impl Dump {
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        for line in &self.lines {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()
+    }
+
+    // Longest common subsequence of the lines, everything else was removed or added. Found with
+    // Hirschberg's algorithm: quadratic time, but memory linear in the number of lines
+    pub fn diff(&self, after: &Dump) -> Diff {
+        let mut lines = Vec::new();
+        diff_into(&self.lines, &after.lines, &mut lines);
+        Diff { lines }
+    }
+}
+
+// `row[j]`: length of the LCS of `a` and the first `j` lines of `b`, or with `reversed` of the
+// reversed `a` and the last `j` lines of `b`
+fn lcs_row(a: &[String], b: &[String], reversed: bool) -> Vec<usize> {
+    fn at(lines: &[String], i: usize, reversed: bool) -> &String {
+        if reversed {
+            &lines[lines.len() - 1 - i]
+        } else {
+            &lines[i]
+        }
+    }
+    let mut row = vec![0; b.len() + 1];
+    for i in 0..a.len() {
+        // `row[j - 1]` of the previous line of `a`
+        let mut diagonal = 0;
+        for j in 1..=b.len() {
+            let above = row[j];
+            row[j] = if at(a, i, reversed) == at(b, j - 1, reversed) {
+                diagonal + 1
+            } else {
+                above.max(row[j - 1])
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+fn diff_into(a: &[String], b: &[String], out: &mut Vec<DiffLine>) {
+    match a {
+        [] => out.extend(b.iter().cloned().map(DiffLine::Added)),
+        _ if b.is_empty() => out.extend(a.iter().cloned().map(DiffLine::Removed)),
+        [line] => match b.iter().position(|l| l == line) {
+            Some(j) => {
+                out.extend(b[..j].iter().cloned().map(DiffLine::Added));
+                out.push(DiffLine::Same(line.clone()));
+                out.extend(b[j + 1..].iter().cloned().map(DiffLine::Added));
+            }
+            None => {
+                out.push(DiffLine::Removed(line.clone()));
+                out.extend(b.iter().cloned().map(DiffLine::Added));
+            }
+        },
+        _ => {
+            // split `a` in half and `b` where the LCS of both halves together is longest
+            let (top, bottom) = a.split_at(a.len() / 2);
+            let forward = lcs_row(top, b, false);
+            let backward = lcs_row(bottom, b, true);
+            let split = (0..=b.len())
+                .max_by_key(|&k| (forward[k] + backward[b.len() - k], std::cmp::Reverse(k)))
+                .expect("there is always a split");
+            diff_into(top, &b[..split], out);
+            diff_into(bottom, &b[split..], out);
+        }
+    }
+}
+
+impl fmt::Display for Dump {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Removed(String),
+    Added(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    lines: Vec<DiffLine>,
+}
+
+impl Diff {
+    pub fn changes(&self) -> impl Iterator<Item = &DiffLine> {
+        self.lines
+            .iter()
+            .filter(|l| !matches!(l, DiffLine::Same(_)))
+    }
+
+    pub fn is_unchanged(&self) -> bool {
+        self.changes().next().is_none()
+    }
+}
+
+// Unified-diff style: `- ` before, `+ ` after, unchanged lines as context
+impl fmt::Display for Diff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                DiffLine::Same(l) => writeln!(f, "  {}", l)?,
+                DiffLine::Removed(l) => writeln!(f, "- {}", l)?,
+                DiffLine::Added(l) => writeln!(f, "+ {}", l)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    // the fields are only read through `Debug`
+    #[allow(dead_code)]
+    #[derive(Debug, Clone)]
+    struct Credentials {
+        user: String,
+        password: String,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, Clone)]
+    struct Session {
+        id: u32,
+        credentials: Credentials,
+        tags: Vec<&'static str>,
+    }
+
+    fn session() -> Session {
+        Session {
+            id: 7,
+            credentials: Credentials {
+                user: "ann".to_owned(),
+                password: "hunter2".to_owned(),
+            },
+            tags: vec!["a"],
+        }
+    }
+
+    #[test]
+    fn full_dump_is_the_pretty_debug_output() {
+        let s = session();
+        assert_eq!(
+            s.dump(&DumpOptions::new()).to_string(),
+            format!("{:#?}\n", s)
+        );
+    }
+
+    #[test]
+    fn depth_limit_collapses_nested_values() {
+        let dump = session().dump(&DumpOptions::new().max_depth(1));
+        assert_eq!(
+            dump.lines(),
+            [
+                "Session {",
+                "    id: 7,",
+                "    credentials: Credentials {",
+                "        ...",
+                "    },",
+                "    tags: [",
+                "        ...",
+                "    ],",
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn redaction_hides_fields_and_map_keys() {
+        let dump = session().dump(&DumpOptions::new().redact("password"));
+        assert!(dump
+            .lines()
+            .contains(&"        password: <redacted>,".to_owned()));
+        assert!(!dump.to_string().contains("hunter2"));
+
+        // a nested value is dropped whole
+        let dump = session().dump(&DumpOptions::new().redact("credentials"));
+        assert_eq!(dump.lines()[2], "    credentials: <redacted>,");
+        assert_eq!(dump.lines()[3], "    tags: [");
+
+        let mut env = BTreeMap::new();
+        env.insert("token", "abc");
+        env.insert("home", "/root");
+        let dump = env.dump(&DumpOptions::new().redact("token"));
+        assert_eq!(dump.lines()[2], "    \"token\": <redacted>,");
+    }
+
+    #[test]
+    fn diff_shows_changed_lines() {
+        let before = session();
+        let mut after = before.clone();
+        after.id = 8;
+        after.tags.push("b");
+
+        let options = DumpOptions::new();
+        let diff = before.dump(&options).diff(&after.dump(&options));
+        let changes: Vec<_> = diff.changes().cloned().collect();
+        assert_eq!(
+            changes,
+            [
+                DiffLine::Removed("    id: 7,".to_owned()),
+                DiffLine::Added("    id: 8,".to_owned()),
+                DiffLine::Added("        \"b\",".to_owned()),
+            ]
+        );
+        assert!(before
+            .dump(&options)
+            .diff(&before.dump(&options))
+            .is_unchanged());
+    }
+
+    #[test]
+    fn diff_of_long_dumps_is_minimal() {
+        let before: Vec<u32> = (0..3000).collect();
+        let after: Vec<u32> = before
+            .iter()
+            .map(|&n| if n % 100 == 0 { n + 1 } else { n })
+            .filter(|n| n % 250 != 7)
+            .collect();
+
+        let options = DumpOptions::new();
+        let diff = before.dump(&options).diff(&after.dump(&options));
+        let (mut removed, mut added) = (0, 0);
+        for change in diff.changes() {
+            match change {
+                DiffLine::Removed(_) => removed += 1,
+                DiffLine::Added(_) => added += 1,
+                DiffLine::Same(_) => unreachable!(),
+            }
+        }
+        // 30 lines changed, 12 removed
+        assert_eq!((removed, added), (42, 30));
+    }
+
+    #[test]
+    fn dumps_into_buffers_and_files() {
+        let options = DumpOptions::new().max_depth(0);
+        let mut buffer = Vec::new();
+        session().dump_into(&options, &mut buffer).unwrap();
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "Session {\n    ...\n}\n"
+        );
+
+        let path = std::env::temp_dir().join(format!("dump_{}.txt", std::process::id()));
+        session().dump_to_file(&options, &path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Session {\n    ...\n}\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
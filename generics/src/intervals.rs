@@ -0,0 +1,342 @@
+use std::fmt;
+use std::ops::Sub;
+
+// The `Contains` trait of `generic_new_associated_types` made generic over the element type
+// The associated type `Item` is chosen by each implementor, so generic code such as `difference`
+// only needs `C: Contains` instead of naming the element type as an extra parameter
+pub trait Contains {
+    type Item: Ord;
+
+    fn contains(&self, item: &Self::Item) -> bool;
+    fn first(&self) -> &Self::Item;
+    fn last(&self) -> &Self::Item;
+}
+
+// `last - first`, for any container whose items can be subtracted
+pub fn difference<C>(container: &C) -> <C::Item as Sub>::Output
+where
+    C: Contains,
+    C::Item: Sub + Clone,
+{
+    container.last().clone() - container.first().clone()
+}
+
+// The `Container(i32, i32)` pair generalised to N items of any ordered type
+// `contains` keeps its original meaning: the item is one of the stored values
+#[derive(Debug, Clone, PartialEq)]
+pub struct Container<T, const N: usize>(pub [T; N]);
+
+impl<T: Ord, const N: usize> Contains for Container<T, N> {
+    type Item = T;
+
+    fn contains(&self, item: &T) -> bool {
+        self.0.iter().any(|x| x == item)
+    }
+
+    // Panics for the empty `Container<T, 0>`, like indexing an empty array
+    fn first(&self) -> &T {
+        &self.0[0]
+    }
+
+    fn last(&self) -> &T {
+        &self.0[N - 1]
+    }
+}
+
+// Half-open range `[start, end)`: a window from 09:00 to 10:00 and one from 10:00 to 11:00 touch
+// but don't overlap. `start >= end` is the empty interval
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Ord + Clone> Interval<T> {
+    pub fn new(start: T, end: T) -> Interval<T> {
+        Interval { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        !self.is_empty() && !other.is_empty() && self.start < other.end && other.start < self.end
+    }
+
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        let start = self.start.clone().max(other.start.clone());
+        let end = self.end.clone().min(other.end.clone());
+        let i = Interval::new(start, end);
+        if i.is_empty() {
+            None
+        } else {
+            Some(i)
+        }
+    }
+
+    // The single interval covering both, when they overlap or touch
+    pub fn merge(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if self.is_empty() {
+            return Some(other.clone());
+        }
+        if other.is_empty() {
+            return Some(self.clone());
+        }
+        if self.start <= other.end && other.start <= self.end {
+            Some(Interval::new(
+                self.start.clone().min(other.start.clone()),
+                self.end.clone().max(other.end.clone()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    // `[start, at)` and `[at, end)`, either side is `None` when it would be empty
+    pub fn split_at(&self, at: T) -> (Option<Interval<T>>, Option<Interval<T>>) {
+        let at = at.max(self.start.clone()).min(self.end.clone());
+        let non_empty = |i: Interval<T>| if i.is_empty() { None } else { Some(i) };
+
+        (
+            non_empty(Interval::new(self.start.clone(), at.clone())),
+            non_empty(Interval::new(at, self.end.clone())),
+        )
+    }
+}
+
+// In-range semantics: `first` is the inclusive start, `last` the exclusive end
+impl<T: Ord> Contains for Interval<T> {
+    type Item = T;
+
+    fn contains(&self, item: &T) -> bool {
+        &self.start <= item && item < &self.end
+    }
+
+    fn first(&self) -> &T {
+        &self.start
+    }
+
+    fn last(&self) -> &T {
+        &self.end
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {})", self.start, self.end)
+    }
+}
+
+// A set of points stored as sorted, disjoint, non-touching, non-empty intervals
+// Keeping that invariant after every operation makes membership a binary search and lets the
+// set operations walk both sides in one linear pass
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Ord + Clone> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+
+    // Sorts and merges arbitrary intervals into the canonical form
+    fn normalize(mut intervals: Vec<Interval<T>>) -> IntervalSet<T> {
+        intervals.retain(|i| !i.is_empty());
+        intervals.sort_by(|a, b| a.start.cmp(&b.start));
+
+        let mut merged: Vec<Interval<T>> = Vec::with_capacity(intervals.len());
+        for interval in intervals {
+            match merged.last_mut() {
+                Some(last) if interval.start <= last.end => {
+                    if interval.end > last.end {
+                        last.end = interval.end;
+                    }
+                }
+                _ => merged.push(interval),
+            }
+        }
+
+        IntervalSet { intervals: merged }
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let mut intervals = std::mem::take(&mut self.intervals);
+        intervals.push(interval);
+        *self = IntervalSet::normalize(intervals);
+    }
+
+    pub fn remove(&mut self, interval: Interval<T>) {
+        *self = self.difference(&IntervalSet::from(vec![interval]));
+    }
+
+    pub fn contains(&self, point: &T) -> bool {
+        // index of the first interval starting after `point`, the one before it may hold it
+        let idx = self.intervals.partition_point(|i| &i.start <= point);
+        idx > 0 && self.intervals[idx - 1].contains(point)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let all = self.intervals.iter().chain(other.intervals.iter()).cloned();
+        IntervalSet::normalize(all.collect())
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let (mut i, mut j) = (0, 0);
+        let mut out = Vec::new();
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (&self.intervals[i], &other.intervals[j]);
+            if let Some(common) = a.intersection(b) {
+                out.push(common);
+            }
+            // the interval ending first can't overlap anything further on the other side
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+
+        IntervalSet { intervals: out }
+    }
+
+    // Points of `self` which are not in `other`
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut out = Vec::new();
+        let mut j = 0;
+        for a in &self.intervals {
+            let mut rest = Some(a.clone());
+            // skip the intervals of `other` entirely before `a`
+            while j < other.intervals.len() && other.intervals[j].end <= a.start {
+                j += 1;
+            }
+            let mut k = j;
+            while let (Some(current), Some(b)) = (rest.clone(), other.intervals.get(k)) {
+                if b.start >= current.end {
+                    break;
+                }
+                let (before, _) = current.split_at(b.start.clone());
+                let (_, after) = current.split_at(b.end.clone());
+                if let Some(before) = before {
+                    out.push(before);
+                }
+                rest = after;
+                k += 1;
+            }
+            if let Some(rest) = rest {
+                out.push(rest);
+            }
+        }
+
+        IntervalSet { intervals: out }
+    }
+}
+
+impl<T: Ord + Clone> From<Vec<Interval<T>>> for IntervalSet<T> {
+    fn from(intervals: Vec<Interval<T>>) -> IntervalSet<T> {
+        IntervalSet::normalize(intervals)
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{")?;
+        for (n, interval) in self.intervals.iter().enumerate() {
+            if n > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", interval)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(i32, i32)]) -> IntervalSet<i32> {
+        IntervalSet::from(
+            ranges
+                .iter()
+                .map(|&(s, e)| Interval::new(s, e))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn containers_share_the_trait() {
+        let pair = Container([3, 10]);
+        assert!(pair.contains(&10));
+        assert!(!pair.contains(&5));
+        assert_eq!(difference(&pair), 7);
+
+        let words = Container(["a", "b", "c"]);
+        assert_eq!(*words.last(), "c");
+
+        let window = Interval::new(3, 10);
+        assert!(window.contains(&5));
+        assert!(!window.contains(&10));
+        assert_eq!(difference(&window), 7);
+    }
+
+    #[test]
+    fn merge_and_split() {
+        let a = Interval::new(1, 5);
+        assert_eq!(a.merge(&Interval::new(5, 8)), Some(Interval::new(1, 8)));
+        assert_eq!(a.merge(&Interval::new(6, 8)), None);
+        assert!(!a.overlaps(&Interval::new(5, 8)));
+        assert_eq!(
+            a.split_at(3),
+            (Some(Interval::new(1, 3)), Some(Interval::new(3, 5)))
+        );
+        assert_eq!(a.split_at(0), (None, Some(a.clone())));
+        assert_eq!(a.split_at(9), (Some(a.clone()), None));
+    }
+
+    #[test]
+    fn sets_are_normalized() {
+        let s = set(&[(5, 7), (1, 3), (3, 4), (6, 9), (10, 10)]);
+        assert_eq!(s, set(&[(1, 4), (5, 9)]));
+        assert!(s.contains(&1));
+        assert!(!s.contains(&4));
+        assert!(s.contains(&8));
+        assert!(!s.contains(&9));
+        assert!(!s.contains(&0));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[(0, 5), (10, 15)]);
+        let b = set(&[(3, 12), (14, 20)]);
+
+        assert_eq!(a.union(&b), set(&[(0, 20)]));
+        assert_eq!(a.intersection(&b), set(&[(3, 5), (10, 12), (14, 15)]));
+        assert_eq!(a.difference(&b), set(&[(0, 3), (12, 14)]));
+        assert_eq!(b.difference(&a), set(&[(5, 10), (15, 20)]));
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut s = IntervalSet::new();
+        s.insert(Interval::new(0, 10));
+        s.remove(Interval::new(2, 4));
+        s.remove(Interval::new(6, 7));
+        assert_eq!(s, set(&[(0, 2), (4, 6), (7, 10)]));
+        s.insert(Interval::new(1, 8));
+        assert_eq!(s, set(&[(0, 10)]));
+        assert_eq!(s.to_string(), "{[0, 10)}");
+    }
+}
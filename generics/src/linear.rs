@@ -0,0 +1,409 @@
+// `DoubleDrop<T>` from `generic_traits` consumes `self` and a `T`, and the moved values can't be
+// used anymore. Here consuming is the *only* correct way to get rid of a handle: a `Linear<R>`
+// must be given back through `close(self)`, which releases the resource and reports the error.
+// Letting it fall out of scope is a bug, caught by its `Drop`:
+// - `OnLeak::Panic` (the default in debug builds) panics with the leaked resource
+// - `OnLeak::Log` (the default in release builds) prints it and releases it as a fallback
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// Something which needs an explicit, fallible release
+pub trait Resource: fmt::Debug {
+    type Error;
+
+    fn release(&mut self) -> Result<(), Self::Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OnLeak {
+    Panic,
+    Log,
+}
+
+impl Default for OnLeak {
+    fn default() -> OnLeak {
+        if cfg!(debug_assertions) {
+            OnLeak::Panic
+        } else {
+            OnLeak::Log
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Linear<R: Resource> {
+    // only `None` once `close` or `into_inner` took the resource out
+    resource: Option<R>,
+    on_leak: OnLeak,
+}
+
+impl<R: Resource> Linear<R> {
+    pub fn new(resource: R) -> Linear<R> {
+        Linear {
+            resource: Some(resource),
+            on_leak: OnLeak::default(),
+        }
+    }
+
+    pub fn with_leak_policy(mut self, on_leak: OnLeak) -> Linear<R> {
+        self.on_leak = on_leak;
+        self
+    }
+
+    pub fn get(&self) -> &R {
+        self.resource
+            .as_ref()
+            .expect("a live handle holds its resource")
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.resource
+            .as_mut()
+            .expect("a live handle holds its resource")
+    }
+
+    pub fn close(mut self) -> Result<(), R::Error> {
+        let mut resource = self
+            .resource
+            .take()
+            .expect("a live handle holds its resource");
+        resource.release()
+    }
+
+    // Gives up the protocol: the caller is now responsible for the resource
+    pub fn into_inner(mut self) -> R {
+        self.resource
+            .take()
+            .expect("a live handle holds its resource")
+    }
+
+    // Two handles which are only released together, see `Pair`
+    pub fn pair<S: Resource>(self, other: Linear<S>) -> Pair<R, S> {
+        Pair {
+            first: self,
+            second: other,
+        }
+    }
+}
+
+impl<R: Resource> Drop for Linear<R> {
+    fn drop(&mut self) {
+        let mut resource = match self.resource.take() {
+            Some(resource) => resource,
+            None => return,
+        };
+        // panicking while already unwinding would abort the whole process
+        if self.on_leak == OnLeak::Panic && !std::thread::panicking() {
+            panic!("{:?} was dropped without being closed", resource);
+        }
+        eprintln!("leak: {:?} was dropped without being closed", resource);
+        if resource.release().is_err() {
+            eprintln!("leak: releasing {:?} failed as well", resource);
+        }
+    }
+}
+
+// The trait of `generic_traits`, now with a result: both values are closed, whatever happens
+pub trait DoubleDrop<T> {
+    type Error;
+
+    fn double_drop(self, other: T) -> Result<(), Self::Error>;
+}
+
+impl<R: Resource, S: Resource> DoubleDrop<Linear<S>> for Linear<R> {
+    type Error = PairError<R::Error, S::Error>;
+
+    fn double_drop(self, other: Linear<S>) -> Result<(), Self::Error> {
+        self.pair(other).close()
+    }
+}
+
+// Both errors of a pair, when either release failed
+#[derive(Debug, PartialEq)]
+pub struct PairError<A, B> {
+    pub first: Option<A>,
+    pub second: Option<B>,
+}
+
+impl<A: fmt::Display, B: fmt::Display> fmt::Display for PairError<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.first, &self.second) {
+            (Some(a), Some(b)) => write!(f, "closing both handles failed: {}; {}", a, b),
+            (Some(a), None) => write!(f, "closing the first handle failed: {}", a),
+            (None, Some(b)) => write!(f, "closing the second handle failed: {}", b),
+            (None, None) => write!(f, "closing the pair failed"),
+        }
+    }
+}
+
+// Handles which can't be closed on their own, e.g. a lock and the file it protects
+#[derive(Debug)]
+pub struct Pair<A: Resource, B: Resource> {
+    first: Linear<A>,
+    second: Linear<B>,
+}
+
+impl<A: Resource, B: Resource> Pair<A, B> {
+    pub fn first(&mut self) -> &mut A {
+        self.first.get_mut()
+    }
+
+    pub fn second(&mut self) -> &mut B {
+        self.second.get_mut()
+    }
+
+    // Releases in reverse order of acquisition, and still releases `first` when `second` fails
+    pub fn close(self) -> Result<(), PairError<A::Error, B::Error>> {
+        let second = self.second.close().err();
+        let first = self.first.close().err();
+        if first.is_none() && second.is_none() {
+            Ok(())
+        } else {
+            Err(PairError { first, second })
+        }
+    }
+}
+
+// A file which is flushed and synced to disk on release, so write errors aren't lost in `Drop`
+#[derive(Debug)]
+pub struct FileHandle {
+    path: PathBuf,
+    file: File,
+}
+
+impl FileHandle {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Linear<FileHandle>> {
+        let path = path.as_ref().to_path_buf();
+        let file = File::create(&path)?;
+        Ok(Linear::new(FileHandle { path, file }))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Write for FileHandle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Resource for FileHandle {
+    type Error = io::Error;
+
+    fn release(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        self.file.sync_all()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LockError {
+    // The lock was released (and maybe taken by someone else) behind the guard's back
+    NotHeld(String),
+}
+
+impl fmt::Display for LockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LockError::NotHeld(name) => write!(f, "lock {:?} was not held", name),
+        }
+    }
+}
+
+// Owner tokens, unique over every lock of the process; 0 is "not held"
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+// A named advisory lock: at most one `LockGuard` exists at a time
+#[derive(Debug, Clone)]
+pub struct Lock {
+    name: String,
+    owner: Arc<AtomicU64>,
+}
+
+// Holds the token it acquired with, so a guard whose lock was broken and taken by someone else
+// can't release the new owner's lock
+#[derive(Debug)]
+pub struct LockGuard {
+    name: String,
+    owner: Arc<AtomicU64>,
+    token: u64,
+}
+
+impl Lock {
+    pub fn new(name: &str) -> Lock {
+        Lock {
+            name: name.to_owned(),
+            owner: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn try_acquire(&self) -> Option<Linear<LockGuard>> {
+        let token = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+        self.owner
+            .compare_exchange(0, token, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| {
+                Linear::new(LockGuard {
+                    name: self.name.clone(),
+                    owner: Arc::clone(&self.owner),
+                    token,
+                })
+            })
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.owner.load(Ordering::Acquire) != 0
+    }
+
+    // Breaks a lock, e.g. one left by a crashed owner
+    pub fn force_release(&self) {
+        self.owner.store(0, Ordering::Release);
+    }
+}
+
+impl Resource for LockGuard {
+    type Error = LockError;
+
+    fn release(&mut self) -> Result<(), LockError> {
+        self.owner
+            .compare_exchange(self.token, 0, Ordering::Release, Ordering::Relaxed)
+            .map(|_| ())
+            .map_err(|_| LockError::NotHeld(self.name.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // Records its releases, and fails them on demand
+    #[derive(Debug)]
+    struct Probe {
+        name: &'static str,
+        fail: bool,
+        log: Rc<RefCell<Vec<&'static str>>>,
+    }
+
+    impl Resource for Probe {
+        type Error = String;
+
+        fn release(&mut self) -> Result<(), String> {
+            self.log.borrow_mut().push(self.name);
+            if self.fail {
+                Err(format!("{} failed", self.name))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn probe(
+        name: &'static str,
+        fail: bool,
+        log: &Rc<RefCell<Vec<&'static str>>>,
+    ) -> Linear<Probe> {
+        Linear::new(Probe {
+            name,
+            fail,
+            log: Rc::clone(log),
+        })
+    }
+
+    #[test]
+    fn close_releases_once() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        assert_eq!(probe("a", false, &log).close(), Ok(()));
+        assert_eq!(probe("b", true, &log).close(), Err("b failed".to_owned()));
+        assert_eq!(*log.borrow(), ["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "dropped without being closed")]
+    fn forgetting_a_handle_panics() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let _handle = probe("a", false, &log).with_leak_policy(OnLeak::Panic);
+    }
+
+    #[test]
+    fn logged_leaks_are_still_released() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        drop(probe("a", false, &log).with_leak_policy(OnLeak::Log));
+        assert_eq!(*log.borrow(), ["a"]);
+    }
+
+    #[test]
+    fn into_inner_disarms_the_handle() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let inner = probe("a", false, &log).into_inner();
+        assert_eq!(inner.name, "a");
+        assert!(log.borrow().is_empty());
+    }
+
+    #[test]
+    fn pairs_release_both_in_reverse_order() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let pair = probe("first", true, &log).pair(probe("second", false, &log));
+        assert_eq!(
+            pair.close(),
+            Err(PairError {
+                first: Some("first failed".to_owned()),
+                second: None
+            })
+        );
+        assert_eq!(*log.borrow(), ["second", "first"]);
+
+        let result = probe("a", false, &log).double_drop(probe("b", true, &log));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "closing the second handle failed: b failed"
+        );
+    }
+
+    #[test]
+    fn lock_guards_are_exclusive() {
+        let lock = Lock::new("db");
+        let guard = lock.try_acquire().unwrap();
+        assert!(lock.try_acquire().is_none());
+        assert_eq!(guard.close(), Ok(()));
+        assert!(!lock.is_held());
+
+        let guard = lock.try_acquire().unwrap();
+        lock.force_release();
+        assert_eq!(guard.close(), Err(LockError::NotHeld("db".to_owned())));
+    }
+
+    #[test]
+    fn a_broken_guard_cant_release_the_next_owner() {
+        let lock = Lock::new("db");
+        let a = lock.try_acquire().unwrap();
+        lock.force_release();
+        let b = lock.try_acquire().unwrap();
+        assert_eq!(a.close(), Err(LockError::NotHeld("db".to_owned())));
+        assert!(lock.is_held());
+        assert!(lock.try_acquire().is_none());
+        assert_eq!(b.close(), Ok(()));
+        assert!(!lock.is_held());
+    }
+
+    #[test]
+    fn file_handles_sync_on_close() {
+        let path = std::env::temp_dir().join(format!("linear_{}.txt", std::process::id()));
+        let mut file = FileHandle::create(&path).unwrap();
+        writeln!(file.get_mut(), "hello").unwrap();
+        file.close().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
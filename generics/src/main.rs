@@ -1,6 +1,20 @@
 use std::marker::PhantomData;
 use std::ops::Add;
 
+mod calendar;
+mod capabilities;
+mod dump;
+mod intervals;
+mod linear;
+mod observed;
+mod shapes;
+mod typemap;
+mod typestate;
+mod units;
+
+#[cfg(test)]
+mod compile_fail;
+
 fn main() {
     generic_param();
     generic_function();
@@ -15,6 +29,566 @@ fn main() {
     generic_new_associated_types();
     generic_phantom_type_param();
     generic_phantom_unit_clarification_testcase();
+    generic_units_of_measure();
+    generic_calendar_newtypes();
+    generic_interval_containers();
+    generic_shape_hierarchy();
+    generic_typestate_builder();
+    generic_linear_handles();
+    generic_capability_tokens();
+    generic_debug_dumps();
+    generic_observed_values();
+    generic_type_map();
+}
+
+// Values of many types keyed by their type: a plugin context where each subsystem finds its own
+// state with `get::<T>()`, no downcasting by hand
+fn generic_type_map() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use typemap::{SendSyncTypeMap, TypeMap};
+
+    #[derive(Debug)]
+    struct Config {
+        plugin_dir: &'static str,
+    }
+
+    #[derive(Debug, Default)]
+    struct Metrics {
+        loaded: u32,
+    }
+
+    let mut context: TypeMap = TypeMap::new();
+    context.insert(Config {
+        plugin_dir: "/usr/lib/plugins",
+    });
+    context.insert(vec!["audio", "video"]);
+
+    for plugin in context.get::<Vec<&str>>().unwrap().clone() {
+        println!(
+            "loading {} from {}",
+            plugin,
+            context.get::<Config>().unwrap().plugin_dir
+        );
+        context.entry::<Metrics>().or_default().loaded += 1;
+    }
+    context.get_mut::<Vec<&str>>().unwrap().push("midi");
+    context
+        .entry::<Metrics>()
+        .and_modify(|m| m.loaded += 1)
+        .or_insert(Metrics { loaded: 0 });
+
+    let mut names: Vec<_> = context.type_names().collect();
+    names.sort_unstable();
+    println!("context holds {} types: {:?}", context.len(), names);
+    println!("metrics: {:?}", context.get::<Metrics>());
+    let config = context.remove::<Config>();
+    println!(
+        "removed {:?}, still has config: {}",
+        config,
+        context.contains::<Config>()
+    );
+    println!("empty: {}", context.is_empty());
+
+    // The thread-safe variant only accepts `Send + Sync` values
+    let shared = Arc::new(Mutex::new(SendSyncTypeMap::new()));
+    let handles: Vec<_> = (0..3)
+        .map(|_| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                shared
+                    .lock()
+                    .unwrap()
+                    .entry::<Metrics>()
+                    .or_default()
+                    .loaded += 1
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    println!("shared context: {:?}", shared.lock().unwrap());
+
+    // Compile-Error: `Rc<i32>` is not `Send + Sync`, so not `IntoBox<dyn Any + Send + Sync>`
+    // shared.lock().unwrap().insert(std::rc::Rc::new(1));
+}
+
+// `GenericVal<T>` as a reactive setting: subscribers, validation and history for every value,
+// `delta`/`rate` for numbers and `toggle` for `Observed<bool>`, like `GenVal<f32>`'s own impl
+fn generic_observed_values() {
+    use observed::Observed;
+    use std::cell::Cell;
+
+    let redraws = Cell::new(0);
+    let mut brightness = Observed::with_history(50u8, 3);
+    brightness.validate_with(|v| {
+        if *v <= 100 {
+            Ok(())
+        } else {
+            Err(format!("{}% is not a brightness", v))
+        }
+    });
+    brightness.subscribe(|old, new| println!("brightness {} -> {}", old, new));
+    let redraw = brightness.subscribe(|_, _| redraws.set(redraws.get() + 1));
+
+    brightness.set(70).unwrap();
+    brightness.set(65).unwrap();
+    if let Err(e) = brightness.set(140) {
+        println!("{}", e);
+    }
+    println!(
+        "brightness {} (delta {:?}), history {:?}",
+        brightness.value(),
+        brightness.delta(),
+        brightness.history().collect::<Vec<_>>()
+    );
+
+    brightness.unsubscribe(redraw);
+    brightness.revert().unwrap();
+    println!("{:?} after {} redraws", brightness, redraws.get());
+
+    let mut dark_mode = Observed::new(false);
+    dark_mode.subscribe(|_, on| println!("dark mode: {}", on));
+    dark_mode.toggle().unwrap();
+
+    let mut download = Observed::new(0.0f64);
+    let start = std::time::Instant::now();
+    download.set_at(12.0, start).unwrap();
+    download
+        .set_at(48.0, start + std::time::Duration::from_secs(4))
+        .unwrap();
+    println!(
+        "downloaded {} MB at {:?} MB/s, previous {:?}",
+        download.value(),
+        download.rate(),
+        download.previous().map(|c| c.value)
+    );
+}
+
+// `PrintInOption` for support snapshots: any `T: Debug` can be dumped with a depth limit and
+// redacted fields, and two dumps of the same struct diffed after an operation
+fn generic_debug_dumps() {
+    use dump::{DebugDump, DumpOptions};
+
+    // Snapshots only, the fields are read through `Debug`
+    #[allow(dead_code)]
+    #[derive(Debug, Clone)]
+    struct Owner {
+        name: String,
+        api_key: String,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Debug, Clone)]
+    struct Account {
+        id: u32,
+        owner: Owner,
+        balance: i64,
+        history: Vec<i64>,
+    }
+
+    let before = Account {
+        id: 42,
+        owner: Owner {
+            name: "Ann".to_owned(),
+            api_key: "sk-123".to_owned(),
+        },
+        balance: 100,
+        history: vec![100],
+    };
+    let mut after = before.clone();
+    after.balance -= 30;
+    after.history.push(-30);
+
+    let options = DumpOptions::new().redact("api_key");
+    print!("{}", before.dump(&options.clone().max_depth(1)));
+
+    let snapshot = before.dump(&options);
+    println!(
+        "{} lines, unchanged against itself: {}",
+        snapshot.lines().len(),
+        snapshot.diff(&snapshot).is_unchanged()
+    );
+
+    let diff = snapshot.diff(&after.dump(&options));
+    println!("withdrawal changed {} lines:", diff.changes().count());
+    print!("{}", diff);
+
+    let mut buffer = Vec::new();
+    after.dump_into(&options, &mut buffer).unwrap();
+    let path = std::env::temp_dir().join("generics_account_dump.txt");
+    after.dump_to_file(&options, &path).unwrap();
+    println!(
+        "file and buffer dumps agree: {}",
+        std::fs::read(&path).unwrap() == buffer
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+// `Red`/`Blue` as permissions: the account operations are bounded by `CanRead`, `CanWrite` and
+// `CanAdmin`, and each module only gets a token as strong as it needs
+fn generic_capability_tokens() {
+    use capabilities::{Accounts, CanRead, Caps, No, ReadOnly, ReadWrite, Token, Yes};
+
+    // A reporting module can only ever read, whatever token type it is called with
+    fn report<C: CanRead>(token: &Token<C>, accounts: &Accounts) {
+        for id in accounts.ids(token) {
+            println!("  {}: {}", id, accounts.balance(token, id).unwrap());
+        }
+    }
+
+    let root = Token::root().expect("the root token is taken once, here");
+    if Token::root().is_none() {
+        println!("a second root token was refused");
+    }
+    let teller: Token<ReadWrite> = root.attenuate();
+    let auditor: Token<ReadOnly> = teller.attenuate();
+    let deposit_box: Token<Caps<No, Yes, No>> = teller.attenuate();
+    println!("{:?}, {:?}, {:?}", root, teller, deposit_box);
+
+    let mut accounts = Accounts::new();
+    accounts.open(&root, "alice").unwrap();
+    accounts.open(&root, "bob").unwrap();
+    accounts.deposit(&deposit_box, "alice", 120).unwrap();
+    accounts.deposit(&teller, "bob", 30).unwrap();
+    if let Err(e) = accounts.withdraw(&teller, "bob", 50) {
+        println!("{}", e);
+    }
+    if let Err(e) = accounts.deposit(&teller, "bob", u64::MAX) {
+        println!("{}", e);
+    }
+    if let Err(e) = accounts.close(&root, "alice") {
+        println!("{}", e);
+    }
+
+    println!("report with {:?}:", auditor);
+    report(&auditor, &accounts);
+
+    // Compile-Error: the trait bound `Caps<Yes, No, No>: CanWrite` is not satisfied
+    // accounts.deposit(&auditor, "alice", 1_000_000).unwrap();
+
+    // Compile-Error: the trait bound `Yes: AtMost<No>` is not satisfied, no amplification
+    // let forged_admin: Token<capabilities::Root> = auditor.attenuate();
+}
+
+// `DoubleDrop` turned into a protocol: handles are given back with `close(self)`, which reports
+// the release error, and a lock is released together with the file it protects
+fn generic_linear_handles() {
+    use linear::{DoubleDrop, FileHandle, Lock, OnLeak, Resource};
+    use std::io::Write;
+
+    let lock = Lock::new("report");
+    let path = std::env::temp_dir().join("generics_linear_report.txt");
+
+    let guard = lock.try_acquire().expect("nobody else holds the lock");
+    println!(
+        "second acquire while held: {:?}",
+        lock.try_acquire().is_some()
+    );
+    let file = FileHandle::create(&path).expect("temp dir is writable");
+
+    println!("writing {:?}", file.get().path());
+
+    let mut pair = guard.pair(file);
+    writeln!(pair.second(), "written under the lock").unwrap();
+    println!("while holding {:?}", pair.first());
+    match pair.close() {
+        Ok(()) => println!("file synced and lock released: {}", !lock.is_held()),
+        Err(e) => println!("{}", e),
+    }
+
+    // A broken lock shows up as an error instead of vanishing in `Drop`
+    let guard = lock.try_acquire().unwrap();
+    let file = FileHandle::create(&path).unwrap();
+    lock.force_release();
+    if let Err(e) = guard.double_drop(file) {
+        println!("{}", e);
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    // Forgetting to close is reported, in debug builds by default with a panic
+    #[derive(Debug)]
+    struct Socket(u16);
+    impl Resource for Socket {
+        type Error = std::convert::Infallible;
+        fn release(&mut self) -> Result<(), Self::Error> {
+            println!("socket {} released by the leak fallback", self.0);
+            Ok(())
+        }
+    }
+    let _socket = linear::Linear::new(Socket(8080)).with_leak_policy(OnLeak::Log);
+
+    // `into_inner` opts out of the protocol explicitly
+    let raw = linear::Linear::new(Socket(22)).into_inner();
+    println!("{:?} handed over without being released", raw);
+
+    // Compile-Error: use of moved value: `guard`
+    // let guard = lock.try_acquire().unwrap();
+    // guard.close().unwrap();
+    // guard.close().unwrap();
+}
+
+// A builder whose `build()` only exists once every required field is set. The states are the
+// phantom parameters of `ServerBuilder<host, port>`, either `Unset` or `Set`
+fn generic_typestate_builder() {
+    use typestate::typestate_builder;
+
+    typestate_builder! {
+        struct Server => ServerBuilder {
+            required { host: String, port: u16 }
+            optional { workers: usize = 4, verbose: bool = false }
+        }
+    }
+
+    let server = Server::builder()
+        .port(8080)
+        .workers(16)
+        .verbose(true)
+        .host("localhost".to_owned())
+        .build();
+    println!(
+        "server {}:{} with {} workers, verbose: {}",
+        server.host, server.port, server.workers, server.verbose
+    );
+
+    // Defaults for everything optional, `verbose` is still `false`
+    let minimal = Server::builder()
+        .host("127.0.0.1".to_owned())
+        .port(80)
+        .build();
+    println!("minimal server: {:?}", minimal);
+
+    // Compile-Error: no method named `build` found for `ServerBuilder<Set, Unset>`
+    // let missing_port = Server::builder().host("localhost".to_owned()).build();
+
+    // Compile-Error: no method named `port` found for `ServerBuilder<Unset, Set>`
+    // let twice = Server::builder().port(80).port(8080);
+}
+
+// `HasArea` from `generic_trait_bounds`, implemented by a new `shapes::Triangle` too (the
+// `Triangle` of `generic_trait_bounds` still doesn't), next to perimeter, centroid and bounding
+// box traits. A floor plan mixes the shapes as `Box<dyn Shape>`
+fn generic_shape_hierarchy() {
+    use shapes::{
+        area, Circle, HasCentroid, HasPerimeter, Point, Polygon, Rectangle, Scene, Triangle,
+    };
+
+    let living = Rectangle::new(Point::new(0.0, 0.0), 6.0, 4.0);
+    let bay = Triangle::new(
+        Point::new(6.0, 0.0),
+        Point::new(8.0, 2.0),
+        Point::new(6.0, 4.0),
+    );
+    let kitchen = Polygon::new(vec![
+        Point::new(0.0, 4.0),
+        Point::new(4.0, 4.0),
+        Point::new(4.0, 6.0),
+        Point::new(2.0, 7.0),
+        Point::new(0.0, 6.0),
+    ]);
+    let column = Circle::new(Point::new(3.0, 2.0), 0.25);
+
+    // `area` is generic over `T: HasArea`, and `shapes::Triangle` qualifies
+    println!("bay area: {}", area(&bay));
+    println!(
+        "kitchen perimeter {:.2}, centroid {:?}",
+        kitchen.perimeter(),
+        kitchen.centroid()
+    );
+
+    let mut plan = Scene::new();
+    plan.add(living);
+    plan.add(bay);
+    plan.add(kitchen);
+    plan.add(column);
+
+    println!(
+        "{} shapes, total area: {:.2}",
+        plan.shapes().len(),
+        plan.total_area()
+    );
+    println!("plan fits in {:?}", plan.bounding_box());
+
+    for shape in plan.by_area() {
+        println!(
+            "{:>9}: area {:6.2}, box {:?}",
+            shape.name(),
+            shape.area(),
+            shape.bounding_box()
+        );
+    }
+
+    for p in &[
+        Point::new(3.0, 2.0),
+        Point::new(7.0, 2.0),
+        Point::new(3.5, 6.5),
+    ] {
+        match plan.hit_test(*p) {
+            Some(shape) => println!("{:?} hits the {}", p, shape.name()),
+            None => println!("{:?} is outside the plan", p),
+        }
+    }
+}
+
+// `Contains` with an associated `Item` type, implemented by a tuple-like container and by
+// intervals, plus a set of intervals used as time windows (minutes since midnight)
+fn generic_interval_containers() {
+    use intervals::{difference, Container, Contains, Interval, IntervalSet};
+
+    let my_container = Container([3, 10]);
+    println!(
+        "Does my_container contain 10: {}, first {}, last {}, difference {}",
+        my_container.contains(&10),
+        my_container.first(),
+        my_container.last(),
+        difference(&my_container)
+    );
+
+    let triple = Container(['x', 'y', 'z']);
+    println!("Does triple contain 'y': {}", triple.contains(&'y'));
+
+    let meeting = Interval::new(9 * 60, 10 * 60 + 30);
+    println!(
+        "meeting {} lasts {} minutes, 10:00 inside: {}",
+        meeting,
+        difference(&meeting),
+        meeting.contains(&600)
+    );
+    println!(
+        "overlaps [630, 700): {}, overlaps [600, 700): {}",
+        meeting.overlaps(&Interval::new(630, 700)),
+        meeting.overlaps(&Interval::new(600, 700))
+    );
+    println!("split at 10:00: {:?}", meeting.split_at(600));
+    println!(
+        "merged with [630, 700): {:?}",
+        meeting.merge(&Interval::new(630, 700))
+    );
+
+    let office_hours = IntervalSet::from(vec![Interval::new(480, 720), Interval::new(780, 1020)]);
+    let mut busy = IntervalSet::new();
+    busy.insert(meeting);
+    busy.insert(Interval::new(840, 900));
+    busy.insert(Interval::new(700, 800));
+
+    println!("office hours: {}", office_hours);
+    println!("busy: {}", busy);
+    println!("free: {}", office_hours.difference(&busy));
+    println!("busy in office: {}", office_hours.intersection(&busy));
+    println!("either: {}", office_hours.union(&busy));
+    println!(
+        "nothing left of office hours minus itself: {}",
+        office_hours.difference(&office_hours).is_empty()
+    );
+
+    busy.remove(Interval::new(840, 900));
+    println!(
+        "after cancelling: {}, busy at 14:30: {}",
+        busy,
+        busy.contains(&870)
+    );
+    for window in busy.iter() {
+        println!("  busy {}", window);
+    }
+}
+
+// `Years`/`Days` from `generic_new_type_idiom` without the 365-day shortcut
+fn generic_calendar_newtypes() {
+    use calendar::{old_enough, Date, Days, Years};
+
+    let birth: Date = "2006-02-28".parse().unwrap();
+    for today in &["2024-02-27", "2024-02-28"] {
+        let today: Date = today.parse().unwrap();
+        println!(
+            "born {}, on {} age {:?}, old enough: {}",
+            birth,
+            today,
+            birth.age_on(today),
+            old_enough(birth, today, Years(18))
+        );
+    }
+
+    let leap_day = Date::new(2024, 2, 29).unwrap();
+    let between = leap_day - birth;
+    println!("{} is {:?} after {}", leap_day, between, birth);
+    println!(
+        "one more day: {:?}, one less day: {:?}",
+        between.checked_add(Days(1)),
+        between.checked_sub(Days(1))
+    );
+
+    let show = |d: Option<Date>| d.map_or("out of range".to_owned(), |d| d.to_string());
+    println!("one day later: {}", show(leap_day.checked_add(Days(1))));
+    println!(
+        "one leap year earlier: {}",
+        show(leap_day.checked_sub(Days(366)))
+    );
+    println!("one year later: {}", show(leap_day.checked_add_years(1)));
+    println!(
+        "far future: {}",
+        show(leap_day.checked_add(Days(3_000_000)))
+    );
+    println!(
+        "{}, {}-{}-{} days since epoch: {}",
+        leap_day,
+        leap_day.year(),
+        leap_day.month(),
+        leap_day.day(),
+        leap_day.days_since_epoch()
+    );
+
+    for input in &["2023-02-29", "2024-1-1"] {
+        if let Err(e) = input.parse::<Date>() {
+            println!("{}", e);
+        }
+    }
+}
+
+// `Length<Unit>` grown into units which know their dimension and exact ratio to each other
+fn generic_units_of_measure() {
+    use units::{
+        Celsius, Foot, Hour, Inch, Kelvin, Km, KmPerHour, MeterPerSecond, MilePerHour, Mm, Quantity,
+    };
+
+    let one_foot: Quantity<Inch> = Quantity::new(12.0);
+    let one_meter: Quantity<Mm> = Quantity::new(1000.0);
+
+    // let mixed = one_foot + one_meter;
+    // Compile-Error: still a type mismatch, convert first
+    let total = one_foot + one_meter.to::<Inch>();
+    println!(
+        "one foot plus one meter is {:.3} ({:.4})",
+        total,
+        total.to::<Foot>()
+    );
+
+    // Length / Time = Velocity, checked at compile time
+    let speed = Quantity::<Km>::new(42.195) / Quantity::<Hour>::new(2.0);
+    println!(
+        "marathon pace: {:.2} = {:.2}",
+        speed,
+        speed.to::<MeterPerSecond>()
+    );
+    // let nonsense = Quantity::<Km>::new(1.0) / Quantity::<units::Kg>::new(1.0);
+    // Compile-Error: there is no `DivDim<Mass>` for `Length`
+
+    // Temperatures convert, offset and all, but Celsius and Fahrenheit don't add up: the sum
+    // would depend on the scale it was taken in
+    let body: Quantity<Celsius> = "98.6 F".parse().unwrap();
+    println!("body temperature: {:.1} = {:.2}", body, body.to::<Kelvin>());
+    // let fever = body + Quantity::<Celsius>::new(2.0);
+    // Compile-Error: cannot add `Quantity<Celsius>` to `Quantity<Celsius>`, it isn't `ZeroBased`
+
+    for input in &["12 in", "3 ft", "0.5 mi", "2 kg", "many in"] {
+        match input.parse::<Quantity<Inch>>() {
+            Ok(len) => println!("{:?} is {}", input, len),
+            Err(e) => println!("{:?}: {}", input, e),
+        }
+    }
+
+    let limit: Quantity<KmPerHour> = "65 mi/h".parse().unwrap();
+    println!("65 mph is {:.1} ({})", limit, limit.to::<MilePerHour>());
 }
 
 fn generic_phantom_unit_clarification_testcase() {
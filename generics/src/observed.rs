@@ -0,0 +1,320 @@
+// `GenericVal<T>` with its `value()` getter, turned into a reactive cell for settings
+// Changing the value runs the validators, then notifies every subscriber with the old and the
+// new value, and keeps the replaced value in a bounded history
+// As with `GenVal<f32>`, some behaviour only exists for some `T`: the blanket impl serves every
+// value, numbers (`T: Number`) get `delta`/`rate`, and `Observed<bool>` gets `toggle`
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub reason: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid value: {}", self.reason)
+    }
+}
+
+// A value and the moment it was set
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<T> {
+    pub value: T,
+    pub at: Instant,
+}
+
+// The primitive numbers, all of them: `Into<f64>` leaves out `i64`, `u64`, `usize`...
+pub trait Number: Copy {
+    // Nearest `f64`, large 64 and 128-bit integers lose their low bits
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! number {
+    ($($t:ty),+) => {$(
+        impl Number for $t {
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    )+};
+}
+
+number!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
+
+type Subscriber<'a, T> = Box<dyn FnMut(&T, &T) + 'a>;
+type Validator<'a, T> = Box<dyn Fn(&T) -> Result<(), String> + 'a>;
+
+pub struct Observed<'a, T> {
+    current: Change<T>,
+    // most recent first, at most `depth` entries
+    history: VecDeque<Change<T>>,
+    depth: usize,
+    subscribers: Vec<(SubscriptionId, Subscriber<'a, T>)>,
+    validators: Vec<Validator<'a, T>>,
+    next_id: u64,
+}
+
+impl<'a, T: Clone + PartialEq> Observed<'a, T> {
+    // Keeps the previous value only
+    pub fn new(value: T) -> Observed<'a, T> {
+        Observed::with_history(value, 1)
+    }
+
+    pub fn with_history(value: T, depth: usize) -> Observed<'a, T> {
+        Observed {
+            current: Change {
+                value,
+                at: Instant::now(),
+            },
+            history: VecDeque::with_capacity(depth),
+            depth,
+            subscribers: Vec::new(),
+            validators: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn value(&self) -> &T {
+        &self.current.value
+    }
+
+    // Subscribers are called as `f(old, new)` after every actual change
+    pub fn subscribe<F: FnMut(&T, &T) + 'a>(&mut self, f: F) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id);
+        self.next_id += 1;
+        self.subscribers.push((id, Box::new(f)));
+        id
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let len = self.subscribers.len();
+        self.subscribers.retain(|(i, _)| *i != id);
+        self.subscribers.len() != len
+    }
+
+    // Every validator has to accept a new value, the first rejection is reported
+    pub fn validate_with<F: Fn(&T) -> Result<(), String> + 'a>(&mut self, f: F) {
+        self.validators.push(Box::new(f));
+    }
+
+    // `Ok(false)` when the value is equal to the current one: nothing is notified or recorded
+    pub fn set(&mut self, value: T) -> Result<bool, ValidationError> {
+        self.set_at(value, Instant::now())
+    }
+
+    pub fn set_at(&mut self, value: T, at: Instant) -> Result<bool, ValidationError> {
+        if value == self.current.value {
+            return Ok(false);
+        }
+        self.validate(&value)?;
+
+        let old = std::mem::replace(&mut self.current, Change { value, at });
+        for (_, subscriber) in &mut self.subscribers {
+            subscriber(&old.value, &self.current.value);
+        }
+        if self.depth > 0 {
+            if self.history.len() == self.depth {
+                self.history.pop_back();
+            }
+            self.history.push_front(old);
+        }
+        Ok(true)
+    }
+
+    fn validate(&self, value: &T) -> Result<(), ValidationError> {
+        for validator in &self.validators {
+            validator(value).map_err(|reason| ValidationError { reason })?;
+        }
+        Ok(())
+    }
+
+    // Previous values, most recent first
+    pub fn history(&self) -> impl Iterator<Item = &T> {
+        self.history.iter().map(|c| &c.value)
+    }
+
+    pub fn previous(&self) -> Option<&Change<T>> {
+        self.history.front()
+    }
+
+    // Goes back to the previous value, validated and notified like any other change. `Ok(false)`
+    // without a history, a rejected value stays in it
+    pub fn revert(&mut self) -> Result<bool, ValidationError> {
+        match self.history.front() {
+            Some(previous) => self.validate(&previous.value)?,
+            None => return Ok(false),
+        }
+        let previous = self.history.pop_front().expect("the history isn't empty");
+        let old = std::mem::replace(&mut self.current, previous);
+        for (_, subscriber) in &mut self.subscribers {
+            subscriber(&old.value, &self.current.value);
+        }
+        Ok(true)
+    }
+}
+
+impl<'a, T> Observed<'a, T>
+where
+    T: Clone + PartialEq + Number,
+{
+    // `current - previous`, `None` before the first change
+    pub fn delta(&self) -> Option<f64> {
+        let previous = self.history.front()?;
+        Some(self.current.value.to_f64() - previous.value.to_f64())
+    }
+
+    // Change per second between the previous and the current value
+    pub fn rate(&self) -> Option<f64> {
+        let previous = self.history.front()?;
+        let secs = self.current.at.duration_since(previous.at).as_secs_f64();
+        if secs > 0.0 {
+            Some(self.delta()? / secs)
+        } else {
+            None
+        }
+    }
+}
+
+impl<'a> Observed<'a, bool> {
+    pub fn toggle(&mut self) -> Result<bool, ValidationError> {
+        let flipped = !self.current.value;
+        self.set(flipped)
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Observed<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Observed")
+            .field("value", &self.current.value)
+            .field("history", &self.history.len())
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    #[test]
+    fn subscribers_see_old_and_new() {
+        let seen = RefCell::new(Vec::new());
+        let mut volume = Observed::new(5);
+        let id = volume.subscribe(|old: &i32, new: &i32| seen.borrow_mut().push((*old, *new)));
+
+        assert_eq!(volume.set(7), Ok(true));
+        assert_eq!(volume.set(7), Ok(false));
+        assert!(volume.unsubscribe(id));
+        assert!(!volume.unsubscribe(id));
+        volume.set(9).unwrap();
+
+        drop(volume);
+        assert_eq!(seen.into_inner(), [(5, 7)]);
+    }
+
+    #[test]
+    fn validators_reject_without_side_effects() {
+        let mut volume = Observed::new(5u8);
+        volume.validate_with(|v| {
+            if *v <= 10 {
+                Ok(())
+            } else {
+                Err(format!("{} is above 10", v))
+            }
+        });
+
+        assert_eq!(
+            volume.set(11),
+            Err(ValidationError {
+                reason: "11 is above 10".to_owned()
+            })
+        );
+        assert_eq!(*volume.value(), 5);
+        assert!(volume.previous().is_none());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut name = Observed::with_history("a".to_owned(), 2);
+        for s in ["b", "c", "d"] {
+            name.set(s.to_owned()).unwrap();
+        }
+        assert_eq!(name.history().collect::<Vec<_>>(), ["c", "b"]);
+
+        assert_eq!(name.revert(), Ok(true));
+        assert_eq!(name.value(), "c");
+        assert_eq!(name.revert(), Ok(true));
+        assert_eq!(name.revert(), Ok(false));
+        assert_eq!(name.value(), "b");
+
+        let mut no_history = Observed::with_history(1, 0);
+        no_history.set(2).unwrap();
+        assert_eq!(no_history.history().count(), 0);
+    }
+
+    #[test]
+    fn numbers_have_delta_and_rate() {
+        let start = Instant::now();
+        let mut temperature = Observed::new(20.0f32);
+        assert_eq!(temperature.delta(), None);
+
+        temperature.set_at(21.0, start).unwrap();
+        temperature
+            .set_at(25.0, start + Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(temperature.delta(), Some(4.0));
+        assert_eq!(temperature.rate(), Some(2.0));
+
+        let mut count = Observed::new(10u32);
+        count.set(4).unwrap();
+        assert_eq!(count.delta(), Some(-6.0));
+
+        // not `Into<f64>`
+        let mut bytes = Observed::new(1_000u64);
+        bytes.set(4_096).unwrap();
+        assert_eq!(bytes.delta(), Some(3_096.0));
+        let mut offset = Observed::new(-5i128);
+        offset.set(5).unwrap();
+        assert_eq!(offset.delta(), Some(10.0));
+    }
+
+    #[test]
+    fn reverts_are_validated() {
+        let limit = std::cell::Cell::new(100);
+        let mut volume = Observed::new(80);
+        volume.validate_with(|v| {
+            if *v <= limit.get() {
+                Ok(())
+            } else {
+                Err(format!("{} is above {}", v, limit.get()))
+            }
+        });
+        volume.set(30).unwrap();
+
+        limit.set(50);
+        assert_eq!(
+            volume.revert(),
+            Err(ValidationError {
+                reason: "80 is above 50".to_owned()
+            })
+        );
+        assert_eq!(*volume.value(), 30);
+        assert_eq!(volume.history().collect::<Vec<_>>(), [&80]);
+    }
+
+    #[test]
+    fn booleans_toggle() {
+        let mut dark_mode = Observed::new(false);
+        dark_mode.toggle().unwrap();
+        dark_mode.toggle().unwrap();
+        assert!(!*dark_mode.value());
+        assert_eq!(dark_mode.history().collect::<Vec<_>>(), [&true]);
+    }
+}
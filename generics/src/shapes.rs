@@ -0,0 +1,508 @@
+use std::f64::consts::PI;
+use std::fmt::Debug;
+
+// The `HasArea` bound of `generic_trait_bounds` split into small traits, each usable on its own
+// as a generic bound, and bundled by `Shape` so that differently typed shapes can live in one
+// `Vec<Box<dyn Shape>>`
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Point {
+        Point { x, y }
+    }
+
+    fn distance(&self, other: &Point) -> f64 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+}
+
+// Axis aligned box, `min` is the bottom-left corner and `max` the top-right one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn contains(&self, p: Point) -> bool {
+        self.min.x <= p.x && p.x <= self.max.x && self.min.y <= p.y && p.y <= self.max.y
+    }
+
+    // The smallest box holding both
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Point::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Point::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
+}
+
+pub trait HasArea {
+    fn area(&self) -> f64;
+}
+
+pub trait HasPerimeter {
+    fn perimeter(&self) -> f64;
+}
+
+pub trait HasCentroid {
+    fn centroid(&self) -> Point;
+}
+
+pub trait BoundingBox {
+    fn bounding_box(&self) -> Bounds;
+}
+
+// Everything a floor plan needs from a shape. `contains` treats the boundary as inside
+pub trait Shape: HasArea + HasPerimeter + HasCentroid + BoundingBox + Debug {
+    fn name(&self) -> &'static str;
+    fn contains(&self, p: Point) -> bool;
+}
+
+// Generic functions only ask for the capability they use
+pub fn area<T: HasArea + ?Sized>(t: &T) -> f64 {
+    t.area()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rectangle {
+    // bottom-left corner
+    pub corner: Point,
+    pub length: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Triangle {
+    pub a: Point,
+    pub b: Point,
+    pub c: Point,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Circle {
+    pub center: Point,
+    pub radius: f64,
+}
+
+// Simple (non self-intersecting) polygon, vertices in either winding order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub vertices: Vec<Point>,
+}
+
+impl Rectangle {
+    pub fn new(corner: Point, length: f64, height: f64) -> Rectangle {
+        Rectangle {
+            corner,
+            length,
+            height,
+        }
+    }
+}
+
+impl Triangle {
+    pub fn new(a: Point, b: Point, c: Point) -> Triangle {
+        Triangle { a, b, c }
+    }
+
+    fn vertices(&self) -> [Point; 3] {
+        [self.a, self.b, self.c]
+    }
+}
+
+impl Circle {
+    pub fn new(center: Point, radius: f64) -> Circle {
+        Circle { center, radius }
+    }
+}
+
+impl Polygon {
+    pub fn new(vertices: Vec<Point>) -> Polygon {
+        Polygon { vertices }
+    }
+}
+
+// Polygon helpers shared by `Triangle` and `Polygon`
+fn edges(vertices: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .map(|(&p, &q)| (p, q))
+}
+
+// Shoelace formula: twice the signed area is the sum of the cross products of consecutive
+// vertices, positive for counterclockwise vertices
+fn signed_area(vertices: &[Point]) -> f64 {
+    edges(vertices)
+        .map(|(p, q)| p.x * q.y - q.x * p.y)
+        .sum::<f64>()
+        / 2.0
+}
+
+fn polygon_perimeter(vertices: &[Point]) -> f64 {
+    edges(vertices).map(|(p, q)| p.distance(&q)).sum()
+}
+
+fn polygon_centroid(vertices: &[Point]) -> Point {
+    let a = signed_area(vertices);
+    if a == 0.0 {
+        // degenerate (all points on a line): fall back to the average vertex
+        let n = vertices.len().max(1) as f64;
+        let (x, y) = vertices
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+        return Point::new(x / n, y / n);
+    }
+
+    let (cx, cy) = edges(vertices).fold((0.0, 0.0), |(cx, cy), (p, q)| {
+        let cross = p.x * q.y - q.x * p.y;
+        (cx + (p.x + q.x) * cross, cy + (p.y + q.y) * cross)
+    });
+    Point::new(cx / (6.0 * a), cy / (6.0 * a))
+}
+
+fn polygon_bounds(vertices: &[Point]) -> Bounds {
+    let first = vertices.first().copied().unwrap_or(Point::new(0.0, 0.0));
+    let start = Bounds {
+        min: first,
+        max: first,
+    };
+    vertices
+        .iter()
+        .fold(start, |b, &p| b.union(&Bounds { min: p, max: p }))
+}
+
+fn on_segment(p: Point, (a, b): (Point, Point)) -> bool {
+    let cross = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+    let eps = 1e-9 * (1.0 + a.distance(&b));
+    cross.abs() <= eps
+        && p.x >= a.x.min(b.x) - eps
+        && p.x <= a.x.max(b.x) + eps
+        && p.y >= a.y.min(b.y) - eps
+        && p.y <= a.y.max(b.y) + eps
+}
+
+// Even-odd ray casting: a ray from `p` to the right crosses the boundary an odd number of times
+// iff `p` is inside. Points exactly on an edge are checked first, the ray test is ambiguous there
+fn polygon_contains(vertices: &[Point], p: Point) -> bool {
+    if edges(vertices).any(|edge| on_segment(p, edge)) {
+        return true;
+    }
+
+    edges(vertices)
+        .filter(|(a, b)| (a.y > p.y) != (b.y > p.y))
+        .filter(|(a, b)| p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y))
+        .count()
+        % 2
+        == 1
+}
+
+impl HasArea for Rectangle {
+    fn area(&self) -> f64 {
+        self.length * self.height
+    }
+}
+
+impl HasPerimeter for Rectangle {
+    fn perimeter(&self) -> f64 {
+        2.0 * (self.length + self.height)
+    }
+}
+
+impl HasCentroid for Rectangle {
+    fn centroid(&self) -> Point {
+        Point::new(
+            self.corner.x + self.length / 2.0,
+            self.corner.y + self.height / 2.0,
+        )
+    }
+}
+
+impl BoundingBox for Rectangle {
+    fn bounding_box(&self) -> Bounds {
+        Bounds {
+            min: self.corner,
+            max: Point::new(self.corner.x + self.length, self.corner.y + self.height),
+        }
+    }
+}
+
+impl Shape for Rectangle {
+    fn name(&self) -> &'static str {
+        "rectangle"
+    }
+
+    fn contains(&self, p: Point) -> bool {
+        self.bounding_box().contains(p)
+    }
+}
+
+impl HasArea for Triangle {
+    fn area(&self) -> f64 {
+        signed_area(&self.vertices()).abs()
+    }
+}
+
+impl HasPerimeter for Triangle {
+    fn perimeter(&self) -> f64 {
+        polygon_perimeter(&self.vertices())
+    }
+}
+
+impl HasCentroid for Triangle {
+    // For a triangle the centroid is simply the average of the vertices
+    fn centroid(&self) -> Point {
+        Point::new(
+            (self.a.x + self.b.x + self.c.x) / 3.0,
+            (self.a.y + self.b.y + self.c.y) / 3.0,
+        )
+    }
+}
+
+impl BoundingBox for Triangle {
+    fn bounding_box(&self) -> Bounds {
+        polygon_bounds(&self.vertices())
+    }
+}
+
+impl Shape for Triangle {
+    fn name(&self) -> &'static str {
+        "triangle"
+    }
+
+    fn contains(&self, p: Point) -> bool {
+        polygon_contains(&self.vertices(), p)
+    }
+}
+
+impl HasArea for Circle {
+    fn area(&self) -> f64 {
+        PI * self.radius * self.radius
+    }
+}
+
+impl HasPerimeter for Circle {
+    fn perimeter(&self) -> f64 {
+        2.0 * PI * self.radius
+    }
+}
+
+impl HasCentroid for Circle {
+    fn centroid(&self) -> Point {
+        self.center
+    }
+}
+
+impl BoundingBox for Circle {
+    fn bounding_box(&self) -> Bounds {
+        let r = self.radius;
+        Bounds {
+            min: Point::new(self.center.x - r, self.center.y - r),
+            max: Point::new(self.center.x + r, self.center.y + r),
+        }
+    }
+}
+
+impl Shape for Circle {
+    fn name(&self) -> &'static str {
+        "circle"
+    }
+
+    fn contains(&self, p: Point) -> bool {
+        self.center.distance(&p) <= self.radius
+    }
+}
+
+impl HasArea for Polygon {
+    fn area(&self) -> f64 {
+        signed_area(&self.vertices).abs()
+    }
+}
+
+impl HasPerimeter for Polygon {
+    fn perimeter(&self) -> f64 {
+        polygon_perimeter(&self.vertices)
+    }
+}
+
+impl HasCentroid for Polygon {
+    fn centroid(&self) -> Point {
+        polygon_centroid(&self.vertices)
+    }
+}
+
+impl BoundingBox for Polygon {
+    fn bounding_box(&self) -> Bounds {
+        polygon_bounds(&self.vertices)
+    }
+}
+
+impl Shape for Polygon {
+    fn name(&self) -> &'static str {
+        "polygon"
+    }
+
+    fn contains(&self, p: Point) -> bool {
+        polygon_contains(&self.vertices, p)
+    }
+}
+
+// Shapes of different types behind trait objects, drawn in insertion order: the last one added
+// is on top
+#[derive(Debug, Default)]
+pub struct Scene {
+    shapes: Vec<Box<dyn Shape>>,
+}
+
+impl Scene {
+    pub fn new() -> Scene {
+        Scene { shapes: Vec::new() }
+    }
+
+    pub fn add<S: Shape + 'static>(&mut self, shape: S) {
+        self.shapes.push(Box::new(shape));
+    }
+
+    pub fn shapes(&self) -> &[Box<dyn Shape>] {
+        &self.shapes
+    }
+
+    // Overlapping shapes are counted once per shape
+    pub fn total_area(&self) -> f64 {
+        self.shapes.iter().map(|s| area(s.as_ref())).sum()
+    }
+
+    // Largest first, as a view: the scene's own order is the z-order `hit_test` relies on.
+    // `total_cmp` gives a total order even for NaN areas
+    pub fn by_area(&self) -> Vec<&dyn Shape> {
+        let mut sorted: Vec<&dyn Shape> = self.shapes.iter().map(|s| s.as_ref()).collect();
+        sorted.sort_by(|a, b| b.area().total_cmp(&a.area()));
+        sorted
+    }
+
+    // The topmost shape under `p`
+    pub fn hit_test(&self, p: Point) -> Option<&dyn Shape> {
+        self.shapes
+            .iter()
+            .rev()
+            .find(|s| s.bounding_box().contains(p) && s.contains(p))
+            .map(|s| s.as_ref())
+    }
+
+    pub fn bounding_box(&self) -> Option<Bounds> {
+        let mut boxes = self.shapes.iter().map(|s| s.bounding_box());
+        let first = boxes.next()?;
+        Some(boxes.fold(first, |acc, b| acc.union(&b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    fn l_shape() -> Polygon {
+        // 2x2 square with its top-right 1x1 quarter cut out
+        Polygon::new(vec![
+            Point::new(0.0, 0.0),
+            Point::new(2.0, 0.0),
+            Point::new(2.0, 1.0),
+            Point::new(1.0, 1.0),
+            Point::new(1.0, 2.0),
+            Point::new(0.0, 2.0),
+        ])
+    }
+
+    #[test]
+    fn measurements() {
+        let rect = Rectangle::new(Point::new(1.0, 1.0), 3.0, 4.0);
+        assert_eq!((rect.area(), rect.perimeter()), (12.0, 14.0));
+        assert_eq!(rect.centroid(), Point::new(2.5, 3.0));
+
+        let tri = Triangle::new(
+            Point::new(0.0, 0.0),
+            Point::new(4.0, 0.0),
+            Point::new(0.0, 3.0),
+        );
+        assert!(close(tri.area(), 6.0));
+        assert!(close(tri.perimeter(), 12.0));
+
+        let circle = Circle::new(Point::new(0.0, 0.0), 2.0);
+        assert!(close(circle.area(), 4.0 * PI));
+
+        let l = l_shape();
+        assert!(close(l.area(), 3.0));
+        assert!(close(l.perimeter(), 8.0));
+        let c = l.centroid();
+        assert!(close(c.x, 5.0 / 6.0) && close(c.y, 5.0 / 6.0));
+        assert_eq!(
+            l.bounding_box(),
+            Bounds {
+                min: Point::new(0.0, 0.0),
+                max: Point::new(2.0, 2.0)
+            }
+        );
+    }
+
+    #[test]
+    fn winding_order_does_not_matter() {
+        let mut reversed = l_shape();
+        reversed.vertices.reverse();
+        assert!(close(reversed.area(), 3.0));
+        assert_eq!(reversed.centroid(), l_shape().centroid());
+    }
+
+    #[test]
+    fn point_in_shape() {
+        let l = l_shape();
+        assert!(l.contains(Point::new(0.5, 1.5)));
+        assert!(!l.contains(Point::new(1.5, 1.5)));
+        // boundary, including the inner corner
+        assert!(l.contains(Point::new(1.0, 1.5)));
+        assert!(l.contains(Point::new(1.0, 1.0)));
+
+        let circle = Circle::new(Point::new(0.0, 0.0), 1.0);
+        assert!(circle.contains(Point::new(0.0, 1.0)));
+        assert!(!circle.contains(Point::new(0.8, 0.8)));
+    }
+
+    #[test]
+    fn scene_queries() {
+        let mut scene = Scene::new();
+        scene.add(Rectangle::new(Point::new(0.0, 0.0), 10.0, 10.0));
+        scene.add(Circle::new(Point::new(5.0, 5.0), 1.0));
+        scene.add(l_shape());
+
+        assert!(close(scene.total_area(), 100.0 + PI + 3.0));
+        assert_eq!(
+            scene.hit_test(Point::new(5.0, 5.0)).unwrap().name(),
+            "circle"
+        );
+        assert_eq!(
+            scene.hit_test(Point::new(0.5, 0.5)).unwrap().name(),
+            "polygon"
+        );
+        assert_eq!(
+            scene.hit_test(Point::new(9.0, 9.0)).unwrap().name(),
+            "rectangle"
+        );
+        assert!(scene.hit_test(Point::new(11.0, 0.0)).is_none());
+
+        let names: Vec<_> = scene.by_area().iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["rectangle", "circle", "polygon"]);
+        // the z-order is untouched, the circle is still on top of the rectangle
+        assert_eq!(
+            scene.hit_test(Point::new(5.0, 5.0)).unwrap().name(),
+            "circle"
+        );
+    }
+}
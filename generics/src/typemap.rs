@@ -0,0 +1,280 @@
+// A map holding at most one value per type, e.g. a plugin context where every subsystem stores its
+// own state under its own type. `SGen<T>` picks one `T` at compile time; here the key is the
+// runtime `TypeId` of `T` and the value is type-erased as `Box<dyn Any>`, so the typed methods can
+// downcast it back safely: a value is only ever stored under its own `TypeId`
+// `TypeMap<A>` is generic over the erased box: `TypeMap` (`dyn Any`) accepts any `'static` type,
+// `SendSyncTypeMap` (`dyn Any + Send + Sync`) only thread-safe ones and is itself `Send + Sync`
+use std::any::{type_name, Any, TypeId};
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+// The erased value types a `TypeMap` can be built on
+pub trait Downcast {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl Downcast for dyn Any {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Downcast for dyn Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+// The types which can be stored in a `TypeMap<A>`
+pub trait IntoBox<A: ?Sized + Downcast>: Any {
+    fn into_box(self) -> Box<A>;
+}
+
+impl<T: Any> IntoBox<dyn Any> for T {
+    fn into_box(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+}
+
+impl<T: Any + Send + Sync> IntoBox<dyn Any + Send + Sync> for T {
+    fn into_box(self) -> Box<dyn Any + Send + Sync> {
+        Box::new(self)
+    }
+}
+
+struct Slot<A: ?Sized> {
+    // `TypeId` has no readable form, the name is kept for `type_names` and `Debug`
+    name: &'static str,
+    value: Box<A>,
+}
+
+pub struct TypeMap<A: ?Sized + Downcast = dyn Any> {
+    slots: HashMap<TypeId, Slot<A>>,
+}
+
+pub type SendSyncTypeMap = TypeMap<dyn Any + Send + Sync>;
+
+impl<A: ?Sized + Downcast> TypeMap<A> {
+    pub fn new() -> TypeMap<A> {
+        TypeMap {
+            slots: HashMap::new(),
+        }
+    }
+
+    // Returns the value of type `T` which was replaced
+    pub fn insert<T: IntoBox<A>>(&mut self, value: T) -> Option<T> {
+        let slot = Slot {
+            name: type_name::<T>(),
+            value: value.into_box(),
+        };
+        self.slots
+            .insert(TypeId::of::<T>(), slot)
+            .map(|old| downcast_owned(old.value))
+    }
+
+    pub fn get<T: IntoBox<A>>(&self) -> Option<&T> {
+        self.slots
+            .get(&TypeId::of::<T>())
+            .map(|slot| downcast_ref(&*slot.value))
+    }
+
+    pub fn get_mut<T: IntoBox<A>>(&mut self) -> Option<&mut T> {
+        self.slots
+            .get_mut(&TypeId::of::<T>())
+            .map(|slot| downcast_mut(&mut *slot.value))
+    }
+
+    pub fn remove<T: IntoBox<A>>(&mut self) -> Option<T> {
+        self.slots
+            .remove(&TypeId::of::<T>())
+            .map(|slot| downcast_owned(slot.value))
+    }
+
+    pub fn contains<T: IntoBox<A>>(&self) -> bool {
+        self.slots.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn entry<T: IntoBox<A>>(&mut self) -> Entry<'_, A, T> {
+        Entry {
+            inner: self.slots.entry(TypeId::of::<T>()),
+            _type: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    // In no particular order, like the keys of a `HashMap`
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.slots.values().map(|slot| slot.name)
+    }
+}
+
+impl<A: ?Sized + Downcast> Default for TypeMap<A> {
+    fn default() -> TypeMap<A> {
+        TypeMap::new()
+    }
+}
+
+impl<A: ?Sized + Downcast> fmt::Debug for TypeMap<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut names: Vec<_> = self.type_names().collect();
+        names.sort_unstable();
+        f.debug_set().entries(names).finish()
+    }
+}
+
+// The slot of `T` is only ever filled with a `T`, so the downcasts can't fail
+fn downcast_ref<A: ?Sized + Downcast, T: Any>(value: &A) -> &T {
+    value
+        .as_any()
+        .downcast_ref()
+        .expect("slot holds its own type")
+}
+
+fn downcast_mut<A: ?Sized + Downcast, T: Any>(value: &mut A) -> &mut T {
+    value
+        .as_any_mut()
+        .downcast_mut()
+        .expect("slot holds its own type")
+}
+
+fn downcast_owned<A: ?Sized + Downcast, T: Any>(value: Box<A>) -> T {
+    *value
+        .into_any()
+        .downcast()
+        .expect("slot holds its own type")
+}
+
+// The typed view of `HashMap`'s entry for the slot of `T`
+pub struct Entry<'a, A: ?Sized + Downcast, T> {
+    inner: hash_map::Entry<'a, TypeId, Slot<A>>,
+    _type: PhantomData<T>,
+}
+
+impl<'a, A: ?Sized + Downcast, T: IntoBox<A>> Entry<'a, A, T> {
+    pub fn or_insert(self, default: T) -> &'a mut T {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> T>(self, default: F) -> &'a mut T {
+        let slot = self.inner.or_insert_with(|| Slot {
+            name: type_name::<T>(),
+            value: default().into_box(),
+        });
+        downcast_mut(&mut *slot.value)
+    }
+
+    pub fn or_default(self) -> &'a mut T
+    where
+        T: Default,
+    {
+        self.or_insert_with(T::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut T)>(self, f: F) -> Entry<'a, A, T> {
+        Entry {
+            inner: self
+                .inner
+                .and_modify(|slot| f(downcast_mut(&mut *slot.value))),
+            _type: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+    use std::sync::{Arc, RwLock};
+    use std::thread;
+
+    #[derive(Debug, Default, PartialEq)]
+    struct Hits(u32);
+
+    #[derive(Debug, PartialEq)]
+    struct Config {
+        verbose: bool,
+    }
+
+    #[test]
+    fn one_value_per_type() {
+        let mut map: TypeMap = TypeMap::new();
+        assert_eq!(map.insert(Hits(1)), None);
+        assert_eq!(map.insert(Config { verbose: true }), None);
+        assert_eq!(map.insert(Hits(2)), Some(Hits(1)));
+
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get::<Hits>(), Some(&Hits(2)));
+        map.get_mut::<Config>().unwrap().verbose = false;
+        assert_eq!(map.remove::<Config>(), Some(Config { verbose: false }));
+        assert!(!map.contains::<Config>());
+        assert_eq!(map.get::<u8>(), None);
+    }
+
+    #[test]
+    fn entry_api() {
+        let mut map: TypeMap = TypeMap::new();
+        map.entry::<Hits>().or_default().0 += 1;
+        map.entry::<Hits>()
+            .and_modify(|h| h.0 *= 10)
+            .or_insert(Hits(0));
+        *map.entry().or_insert_with(|| String::from("a")) += "b";
+
+        assert_eq!(map.get::<Hits>(), Some(&Hits(10)));
+        assert_eq!(map.get::<String>().unwrap(), "ab");
+    }
+
+    #[test]
+    fn names_and_non_send_values() {
+        let mut map: TypeMap = TypeMap::new();
+        map.insert(Rc::new(5));
+        map.insert(Hits(0));
+        // `type_name` is meant for diagnostics, its exact output isn't guaranteed
+        let names: Vec<_> = map.type_names().collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().any(|n| n.ends_with("Rc<i32>")));
+        assert!(names.iter().any(|n| n.ends_with("Hits")));
+        assert!(format!("{:?}", map).contains("Hits"));
+    }
+
+    #[test]
+    fn send_sync_maps_cross_threads() {
+        let shared = Arc::new(RwLock::new(SendSyncTypeMap::new()));
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || shared.write().unwrap().entry::<Hits>().or_default().0 += 1)
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        assert_eq!(shared.read().unwrap().get::<Hits>(), Some(&Hits(4)));
+    }
+}
@@ -0,0 +1,186 @@
+// Typestate builders: hidden type parameters, as in `generic_phantom_type_param`, track at
+// compile time which required fields of a builder have been set
+// Every required field gets its own type parameter, either `Unset` or `Set`. The setter of a
+// required field only exists while its parameter is `Unset` and turns it into `Set`, and
+// `build()` only exists once every parameter is `Set`. Forgetting a field or setting it twice is
+// therefore a compile error ("no method named `build` found"), not a runtime `None`
+//
+// typestate_builder! {
+//     pub struct Config => ConfigBuilder {
+//         required { host: String, port: u16 }
+//         optional { timeout_secs: u64 = 30 }
+//     }
+// }
+//
+// let config = Config::builder().port(8080).host("localhost".to_owned()).build();
+
+// Markers, never instantiated
+pub enum Set {}
+pub enum Unset {}
+
+// The path the macro expands to, so callers don't have to import `PhantomData`
+pub type Marker<T> = std::marker::PhantomData<T>;
+
+macro_rules! typestate_builder {
+    (
+        $vis:vis struct $name:ident => $builder:ident {
+            required { $($req:ident : $req_ty:ty),* $(,)? }
+            optional { $($opt:ident : $opt_ty:ty = $default:expr),* $(,)? }
+        }
+    ) => {
+        #[derive(Debug, Clone, PartialEq)]
+        $vis struct $name {
+            $(pub $req: $req_ty,)*
+            $(pub $opt: $opt_ty,)*
+        }
+
+        // The type parameters are named after the required fields, they live in the type
+        // namespace so they don't clash with the fields themselves
+        #[allow(non_camel_case_types)]
+        $vis struct $builder<$($req),*> {
+            $($req: Option<$req_ty>,)*
+            $($opt: $opt_ty,)*
+            _state: $crate::typestate::Marker<($($req,)*)>,
+        }
+
+        impl $name {
+            $vis fn builder() -> $builder<$(typestate_builder!(@unset $req)),*> {
+                $builder {
+                    $($req: None,)*
+                    $($opt: $default,)*
+                    _state: $crate::typestate::Marker::default(),
+                }
+            }
+        }
+
+        // Optional fields can be set (and overwritten) in any state
+        #[allow(non_camel_case_types)]
+        impl<$($req),*> $builder<$($req),*> {
+            $(
+                $vis fn $opt(mut self, value: $opt_ty) -> Self {
+                    self.$opt = value;
+                    self
+                }
+            )*
+        }
+
+        impl $builder<$(typestate_builder!(@set $req)),*> {
+            $vis fn build(self) -> $name {
+                $name {
+                    $($req: self.$req.expect("the typestate guarantees the field is set"),)*
+                    $($opt: self.$opt,)*
+                }
+            }
+        }
+
+        typestate_builder!(@setters $vis $builder [$($req),*] [$($opt),*] [] [$($req : $req_ty),*]);
+    };
+
+    (@unset $req:ident) => { $crate::typestate::Unset };
+    (@set $req:ident) => { $crate::typestate::Set };
+
+    // One setter per required field: walk the fields keeping those already handled (`before`)
+    // and those still to come (`after`), so the impl can name every other parameter
+    (@setters $vis:vis $builder:ident [$($all:ident),*] [$($opt:ident),*] [$($before:ident),*] []) => {};
+    (@setters $vis:vis $builder:ident [$($all:ident),*] [$($opt:ident),*] [$($before:ident),*]
+        [$field:ident : $field_ty:ty $(, $after:ident : $after_ty:ty)*]) => {
+        #[allow(non_camel_case_types)]
+        impl<$($before,)* $($after),*> $builder<$($before,)* $crate::typestate::Unset, $($after),*> {
+            $vis fn $field(self, value: $field_ty)
+                -> $builder<$($before,)* $crate::typestate::Set, $($after),*>
+            {
+                let mut this = self;
+                this.$field = Some(value);
+                $builder {
+                    $($all: this.$all,)*
+                    $($opt: this.$opt,)*
+                    _state: $crate::typestate::Marker::default(),
+                }
+            }
+        }
+
+        typestate_builder!(@setters $vis $builder [$($all),*] [$($opt),*] [$($before,)* $field]
+            [$($after : $after_ty),*]);
+    };
+}
+
+pub(crate) use typestate_builder;
+
+#[cfg(test)]
+mod tests {
+    use crate::compile_fail::{assert_compile_fail, assert_compiles};
+
+    typestate_builder! {
+        pub struct Server => ServerBuilder {
+            required { host: String, port: u16 }
+            optional { workers: usize = 4, verbose: bool = false }
+        }
+    }
+
+    typestate_builder! {
+        struct Flags => FlagsBuilder {
+            required {}
+            optional { debug: bool = true }
+        }
+    }
+
+    const SRC: &str = include_str!("typestate.rs");
+
+    const DECL: &str = "
+        use crate::typestate::typestate_builder;
+        typestate_builder! {
+            pub struct Server => ServerBuilder {
+                required { host: String, port: u16 }
+                optional { workers: usize = 4 }
+            }
+        }
+    ";
+
+    #[test]
+    fn builds_in_any_order_with_defaults() {
+        let a = Server::builder()
+            .host("localhost".to_owned())
+            .port(80)
+            .build();
+        let b = Server::builder()
+            .verbose(true)
+            .port(80)
+            .workers(8)
+            .host("localhost".to_owned())
+            .build();
+
+        assert_eq!((a.workers, a.verbose), (4, false));
+        assert_eq!((b.workers, b.verbose), (8, true));
+        assert_eq!(a.host, b.host);
+    }
+
+    #[test]
+    fn no_required_fields() {
+        assert!(Flags::builder().build().debug);
+        assert!(!Flags::builder().debug(false).build().debug);
+    }
+
+    #[test]
+    fn complete_builder_compiles() {
+        let snippet = format!(
+            "{}\nfn f() -> Server {{ Server::builder().port(1).host(String::new()).build() }}",
+            DECL
+        );
+        assert_compiles("typestate", SRC, &snippet);
+    }
+
+    #[test]
+    fn missing_required_field_does_not_compile() {
+        let snippet = format!(
+            "{}\nfn f() -> Server {{ Server::builder().port(1).build() }}",
+            DECL
+        );
+        assert_compile_fail("typestate", SRC, &snippet, "E0599");
+    }
+
+    #[test]
+    fn setting_a_required_field_twice_does_not_compile() {
+        let snippet = format!("{}\nfn f() {{ Server::builder().port(1).port(2); }}", DECL);
+        assert_compile_fail("typestate", SRC, &snippet, "E0599");
+    }
+}
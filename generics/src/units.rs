@@ -0,0 +1,598 @@
+// A catalogue of units, `main` only uses a few of them
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Sub};
+use std::str::FromStr;
+
+// Units of measure on top of the phantom type idea of `Length<Unit>`
+// A `Quantity<U>` is a plain `f64` tagged with a unit type `U`. Each unit belongs to a dimension
+// (length, mass, ...) and knows its exact ratio to the coherent SI unit of that dimension, so
+// - quantities of the same unit add and subtract, different units don't compile (as before).
+//   Only for `ZeroBased` units: 20 °C + 30 °C is 50 °C, but the same two temperatures added in
+//   kelvin are 323.15 °C, so Celsius and Fahrenheit quantities only convert
+// - `to::<V>()` converts between units of the SAME dimension, anything else doesn't compile
+// - `*` and `/` build derived units (`Per<Meter, Second>`) whose dimension is looked up in the
+//   `MulDim`/`DivDim` tables at compile time, e.g. length / mass has no entry and doesn't compile
+
+// Dimensions are never instantiated, they only exist as type parameters
+pub trait Dimension {
+    const NAME: &'static str;
+    // Units accepted when parsing a quantity of this dimension
+    const UNITS: &'static [UnitDef];
+}
+
+// The value in the SI unit of the dimension is `(value + offset) * num / den`
+// `offset` is only non-zero for temperatures (Celsius, Fahrenheit), whose scales don't start at 0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnitDef {
+    pub suffix: &'static str,
+    pub num: u64,
+    pub den: u64,
+    pub offset: f64,
+}
+
+pub trait Unit {
+    type Dim: Dimension;
+    const NUM: u64;
+    const DEN: u64;
+    const OFFSET: f64 = 0.0;
+    fn suffix() -> String;
+}
+
+// Units whose 0 is the 0 of the dimension, every unit without an offset. Sums, differences and
+// multiples of their quantities mean the same in every such unit of the dimension
+pub trait ZeroBased: Unit {}
+
+// `Self * Rhs` has dimension `Output`
+pub trait MulDim<Rhs> {
+    type Output: Dimension;
+}
+
+// `Self / Rhs` has dimension `Output`
+pub trait DivDim<Rhs> {
+    type Output: Dimension;
+}
+
+macro_rules! dimension {
+    ($(#[$meta:meta])* $dim:ident, $name:expr, [$($unit:ident),* $(,)?]) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum $dim {}
+
+        impl Dimension for $dim {
+            const NAME: &'static str = $name;
+            const UNITS: &'static [UnitDef] = &[$($unit::DEF),*];
+        }
+    };
+}
+
+macro_rules! unit {
+    ($(#[$meta:meta])* $unit:ident, $dim:ident, $suffix:expr, $num:expr, $den:expr) => {
+        unit!($(#[$meta])* $unit, $dim, $suffix, $num, $den, 0.0);
+
+        impl ZeroBased for $unit {}
+    };
+    ($(#[$meta:meta])* $unit:ident, $dim:ident, $suffix:expr, $num:expr, $den:expr, $offset:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub enum $unit {}
+
+        $(#[$meta])*
+        impl $unit {
+            pub const DEF: UnitDef = UnitDef {
+                suffix: $suffix,
+                num: $num,
+                den: $den,
+                offset: $offset,
+            };
+        }
+
+        impl Unit for $unit {
+            type Dim = $dim;
+            const NUM: u64 = $num;
+            const DEN: u64 = $den;
+            const OFFSET: f64 = $offset;
+            fn suffix() -> String {
+                $suffix.to_owned()
+            }
+        }
+    };
+}
+
+// Aliases only help parsing, they format with the unit's own suffix
+macro_rules! alias {
+    ($unit:ident, $suffix:expr) => {
+        UnitDef {
+            suffix: $suffix,
+            ..$unit::DEF
+        }
+    };
+}
+
+// Base dimensions. The ratios are exact by definition (1 in = 25.4 mm, 1 lb = 0.45359237 kg)
+unit!(Mm, Length, "mm", 1, 1_000);
+unit!(Cm, Length, "cm", 1, 100);
+unit!(Meter, Length, "m", 1, 1);
+unit!(Km, Length, "km", 1_000, 1);
+unit!(Inch, Length, "in", 254, 10_000);
+unit!(Foot, Length, "ft", 3_048, 10_000);
+unit!(Yard, Length, "yd", 9_144, 10_000);
+unit!(Mile, Length, "mi", 1_609_344, 1_000);
+dimension!(
+    Length,
+    "length",
+    [Mm, Cm, Meter, Km, Inch, Foot, Yard, Mile]
+);
+
+// Only in the tests so far
+unit!(
+    #[allow(dead_code)]
+    Gram,
+    Mass,
+    "g",
+    1,
+    1_000
+);
+unit!(
+    #[allow(dead_code)]
+    Kg,
+    Mass,
+    "kg",
+    1,
+    1
+);
+unit!(
+    #[allow(dead_code)]
+    Pound,
+    Mass,
+    "lb",
+    45_359_237,
+    100_000_000
+);
+unit!(
+    #[allow(dead_code)]
+    Ounce,
+    Mass,
+    "oz",
+    45_359_237,
+    1_600_000_000
+);
+dimension!(
+    #[allow(dead_code)]
+    Mass,
+    "mass",
+    [Gram, Kg, Pound, Ounce]
+);
+
+unit!(Second, Time, "s", 1, 1);
+unit!(Minute, Time, "min", 60, 1);
+unit!(Hour, Time, "h", 3_600, 1);
+dimension!(Time, "time", [Second, Minute, Hour]);
+
+unit!(Kelvin, Temperature, "K", 1, 1);
+unit!(Celsius, Temperature, "°C", 1, 1, 273.15);
+unit!(Fahrenheit, Temperature, "°F", 5, 9, 459.67);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Temperature {}
+
+impl Dimension for Temperature {
+    const NAME: &'static str = "temperature";
+    const UNITS: &'static [UnitDef] = &[
+        Kelvin::DEF,
+        Celsius::DEF,
+        Fahrenheit::DEF,
+        alias!(Celsius, "C"),
+        alias!(Fahrenheit, "F"),
+    ];
+}
+
+// Derived dimensions. Parsing accepts the suffixes produced by `Times`/`Per` for common units
+#[allow(dead_code)]
+pub type SquareMeter = Times<Meter, Meter>;
+pub type MeterPerSecond = Per<Meter, Second>;
+pub type KmPerHour = Per<Km, Hour>;
+pub type MilePerHour = Per<Mile, Hour>;
+#[allow(dead_code)]
+pub type MeterPerSecondSquared = Per<MeterPerSecond, Second>;
+#[allow(dead_code)]
+pub type Newton = Times<Kg, MeterPerSecondSquared>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Area {}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Velocity {}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Acceleration {}
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Force {}
+
+const fn derived(suffix: &'static str, num: u64, den: u64) -> UnitDef {
+    UnitDef {
+        suffix,
+        num,
+        den,
+        offset: 0.0,
+    }
+}
+
+impl Dimension for Area {
+    const NAME: &'static str = "area";
+    const UNITS: &'static [UnitDef] = &[
+        derived("m*m", 1, 1),
+        derived("mm*mm", 1, 1_000_000),
+        derived("cm*cm", 1, 10_000),
+        derived("km*km", 1_000_000, 1),
+        derived("in*in", 254 * 254, 10_000 * 10_000),
+        derived("ft*ft", 3_048 * 3_048, 10_000 * 10_000),
+    ];
+}
+
+impl Dimension for Velocity {
+    const NAME: &'static str = "velocity";
+    const UNITS: &'static [UnitDef] = &[
+        derived("m/s", 1, 1),
+        derived("km/h", 1_000, 3_600),
+        derived("mi/h", 1_609_344, 3_600_000),
+        derived("ft/s", 3_048, 10_000),
+    ];
+}
+
+impl Dimension for Acceleration {
+    const NAME: &'static str = "acceleration";
+    const UNITS: &'static [UnitDef] = &[derived("m/s/s", 1, 1)];
+}
+
+impl Dimension for Force {
+    const NAME: &'static str = "force";
+    const UNITS: &'static [UnitDef] = &[derived("kg*m/s/s", 1, 1)];
+}
+
+// The dimension tables: only the combinations listed here compile
+macro_rules! dim_table {
+    ($($lhs:ident * $rhs:ident = $out:ident),* $(,)?) => {
+        $(
+            impl MulDim<$rhs> for $lhs {
+                type Output = $out;
+            }
+            impl DivDim<$rhs> for $out {
+                type Output = $lhs;
+            }
+        )*
+    };
+}
+
+dim_table! {
+    Length * Length = Area,
+    Velocity * Time = Length,
+    Acceleration * Time = Velocity,
+    Mass * Acceleration = Force,
+}
+
+// The commuted products, `DivDim` for these comes from the table above
+impl MulDim<Velocity> for Time {
+    type Output = Length;
+}
+impl MulDim<Acceleration> for Time {
+    type Output = Velocity;
+}
+impl DivDim<Velocity> for Length {
+    type Output = Time;
+}
+impl DivDim<Acceleration> for Velocity {
+    type Output = Time;
+}
+
+// Unit of `A * B`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Times<A, B>(PhantomData<(A, B)>);
+
+// Unit of `A / B`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Per<A, B>(PhantomData<(A, B)>);
+
+impl<A: Unit, B: Unit> Unit for Times<A, B>
+where
+    A::Dim: MulDim<B::Dim>,
+{
+    type Dim = <A::Dim as MulDim<B::Dim>>::Output;
+    const NUM: u64 = A::NUM * B::NUM;
+    const DEN: u64 = A::DEN * B::DEN;
+
+    fn suffix() -> String {
+        format!("{}*{}", A::suffix(), B::suffix())
+    }
+}
+
+impl<A: ZeroBased, B: ZeroBased> ZeroBased for Times<A, B> where A::Dim: MulDim<B::Dim> {}
+
+impl<A: Unit, B: Unit> Unit for Per<A, B>
+where
+    A::Dim: DivDim<B::Dim>,
+{
+    type Dim = <A::Dim as DivDim<B::Dim>>::Output;
+    const NUM: u64 = A::NUM * B::DEN;
+    const DEN: u64 = A::DEN * B::NUM;
+
+    fn suffix() -> String {
+        format!("{}/{}", A::suffix(), B::suffix())
+    }
+}
+
+impl<A: ZeroBased, B: ZeroBased> ZeroBased for Per<A, B> where A::Dim: DivDim<B::Dim> {}
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Quantity<U>(f64, PhantomData<U>);
+
+impl<U: Unit> Quantity<U> {
+    pub fn new(value: f64) -> Quantity<U> {
+        Quantity(value, PhantomData)
+    }
+
+    #[allow(dead_code)]
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    // Only compiles for a unit `V` of the same dimension
+    pub fn to<V: Unit<Dim = U::Dim>>(self) -> Quantity<V> {
+        let from = UnitDef {
+            suffix: "",
+            num: U::NUM,
+            den: U::DEN,
+            offset: U::OFFSET,
+        };
+        let to = UnitDef {
+            suffix: "",
+            num: V::NUM,
+            den: V::DEN,
+            offset: V::OFFSET,
+        };
+        Quantity::new(convert(self.0, &from, &to))
+    }
+}
+
+fn gcd(a: u128, b: u128) -> u128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// The ratio between the two units is reduced in integers first, so 12 in -> ft is `12 * 1 / 12`
+// and not `12 * 0.0254 / 0.3048`, which would be off by rounding
+fn convert(value: f64, from: &UnitDef, to: &UnitDef) -> f64 {
+    let num = from.num as u128 * to.den as u128;
+    let den = from.den as u128 * to.num as u128;
+    let g = gcd(num, den);
+
+    (value + from.offset) * (num / g) as f64 / (den / g) as f64 - to.offset
+}
+
+impl<U: ZeroBased> Add for Quantity<U> {
+    type Output = Quantity<U>;
+
+    fn add(self, rhs: Quantity<U>) -> Quantity<U> {
+        Quantity::new(self.0 + rhs.0)
+    }
+}
+
+impl<U: ZeroBased> Sub for Quantity<U> {
+    type Output = Quantity<U>;
+
+    fn sub(self, rhs: Quantity<U>) -> Quantity<U> {
+        Quantity::new(self.0 - rhs.0)
+    }
+}
+
+// Scaling by a plain number keeps the unit, again only where 0 is 0
+impl<U: ZeroBased> Mul<f64> for Quantity<U> {
+    type Output = Quantity<U>;
+
+    fn mul(self, rhs: f64) -> Quantity<U> {
+        Quantity::new(self.0 * rhs)
+    }
+}
+
+impl<U: ZeroBased> Div<f64> for Quantity<U> {
+    type Output = Quantity<U>;
+
+    fn div(self, rhs: f64) -> Quantity<U> {
+        Quantity::new(self.0 / rhs)
+    }
+}
+
+impl<A: Unit, B: Unit> Mul<Quantity<B>> for Quantity<A>
+where
+    A::Dim: MulDim<B::Dim>,
+{
+    type Output = Quantity<Times<A, B>>;
+
+    fn mul(self, rhs: Quantity<B>) -> Quantity<Times<A, B>> {
+        Quantity::new(self.0 * rhs.0)
+    }
+}
+
+impl<A: Unit, B: Unit> Div<Quantity<B>> for Quantity<A>
+where
+    A::Dim: DivDim<B::Dim>,
+{
+    type Output = Quantity<Per<A, B>>;
+
+    fn div(self, rhs: Quantity<B>) -> Quantity<Per<A, B>> {
+        Quantity::new(self.0 / rhs.0)
+    }
+}
+
+// `12 in`, `3.5 km/h`. Precision flags are passed on to the number: `{:.2}` gives `0.30 m`
+impl<U: Unit> fmt::Display for Quantity<U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)?;
+        write!(f, " {}", U::suffix())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ParseQuantityError {
+    InvalidNumber(String),
+    // The suffix is not a known unit of the expected dimension
+    UnknownUnit {
+        dimension: &'static str,
+        suffix: String,
+    },
+}
+
+impl fmt::Display for ParseQuantityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseQuantityError::InvalidNumber(s) => write!(f, "invalid number {:?}", s),
+            ParseQuantityError::UnknownUnit { dimension, suffix } => {
+                write!(f, "{:?} is not a unit of {}", suffix, dimension)
+            }
+        }
+    }
+}
+
+// Parses `<number> <suffix>` in ANY unit of the dimension and converts it, so
+// `"1 ft".parse::<Quantity<Inch>>()` gives 12 in. The space is optional: `"12in"`
+impl<U: Unit> FromStr for Quantity<U> {
+    type Err = ParseQuantityError;
+
+    fn from_str(s: &str) -> Result<Quantity<U>, ParseQuantityError> {
+        let s = s.trim();
+        let split = s
+            .find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c)))
+            .unwrap_or(s.len());
+        let (number, suffix) = s.split_at(split);
+        let suffix = suffix.trim();
+
+        let value = number
+            .parse::<f64>()
+            .map_err(|_| ParseQuantityError::InvalidNumber(number.to_owned()))?;
+
+        let from = <U::Dim as Dimension>::UNITS
+            .iter()
+            .find(|def| def.suffix == suffix)
+            .ok_or_else(|| ParseQuantityError::UnknownUnit {
+                dimension: <U::Dim as Dimension>::NAME,
+                suffix: suffix.to_owned(),
+            })?;
+        let to = UnitDef {
+            suffix: "",
+            num: U::NUM,
+            den: U::DEN,
+            offset: U::OFFSET,
+        };
+
+        Ok(Quantity::new(convert(value, from, &to)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_fail::{assert_compile_fail, assert_compiles};
+
+    const SRC: &str = include_str!("units.rs");
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn conversions_use_exact_ratios() {
+        assert_eq!(Quantity::<Inch>::new(12.0).to::<Foot>().value(), 1.0);
+        assert_eq!(Quantity::<Inch>::new(1.0).to::<Mm>().value(), 25.4);
+        assert_eq!(Quantity::<Mile>::new(1.0).to::<Foot>().value(), 5280.0);
+        assert_eq!(Quantity::<Pound>::new(1.0).to::<Ounce>().value(), 16.0);
+        assert_eq!(Quantity::<Hour>::new(1.5).to::<Minute>().value(), 90.0);
+    }
+
+    #[test]
+    fn temperatures_convert_with_offsets() {
+        assert!(close(
+            Quantity::<Celsius>::new(100.0).to::<Fahrenheit>().value(),
+            212.0
+        ));
+        assert!(close(
+            Quantity::<Fahrenheit>::new(32.0).to::<Celsius>().value(),
+            0.0
+        ));
+        assert!(close(
+            Quantity::<Kelvin>::new(0.0).to::<Celsius>().value(),
+            -273.15
+        ));
+    }
+
+    #[test]
+    fn only_zero_based_units_add() {
+        let sum = Quantity::<Kelvin>::new(293.15) + Quantity::new(303.15);
+        assert!(close(sum.value(), 596.3));
+        let snippet = "use crate::units::*;\n\
+             fn f() { let _ = Quantity::<Kelvin>::new(1.0) * 2.0 - Quantity::new(1.0); \
+             let _ = Quantity::<KmPerHour>::new(1.0) + Quantity::new(1.0); }";
+        assert_compiles("units", SRC, snippet);
+
+        for op in ["+", "-"] {
+            let snippet = format!(
+                "use crate::units::*;\n\
+                 fn f() {{ let _ = Quantity::<Celsius>::new(20.0) {} Quantity::new(30.0); }}",
+                op
+            );
+            assert_compile_fail("units", SRC, &snippet, "E0369");
+        }
+        let snippet = "use crate::units::*;\n\
+             fn f() { let _ = Quantity::<Fahrenheit>::new(20.0) * 2.0; }";
+        assert_compile_fail("units", SRC, snippet, "E0369");
+    }
+
+    #[test]
+    fn derived_dimensions() {
+        let distance = Quantity::<Km>::new(90.0);
+        let time = Quantity::<Hour>::new(1.0);
+        let speed: Quantity<KmPerHour> = distance / time;
+        assert!(close(speed.to::<MeterPerSecond>().value(), 25.0));
+
+        let area: Quantity<Times<Foot, Foot>> = Quantity::<Foot>::new(10.0) * Quantity::new(10.0);
+        assert!(close(area.to::<SquareMeter>().value(), 9.290304));
+
+        let back: Quantity<Km> = (speed * Quantity::<Hour>::new(2.0)).to();
+        assert!(close(back.value(), 180.0));
+
+        let accel: Quantity<MeterPerSecondSquared> =
+            Quantity::<MeterPerSecond>::new(10.0) / Quantity::<Second>::new(2.0);
+        let force: Quantity<Newton> = Quantity::<Kg>::new(3.0) * accel;
+        assert!(close(force.value(), 15.0));
+    }
+
+    #[test]
+    fn display_has_unit_suffix() {
+        assert_eq!(Quantity::<Inch>::new(12.0).to_string(), "12 in");
+        assert_eq!(
+            format!("{:.1}", Quantity::<KmPerHour>::new(3.25)),
+            "3.2 km/h"
+        );
+    }
+
+    #[test]
+    fn parsing_converts_from_any_unit_of_the_dimension() {
+        let len: Quantity<Inch> = "12 in".parse().unwrap();
+        assert_eq!(len.value(), 12.0);
+        let len: Quantity<Inch> = "1ft".parse().unwrap();
+        assert_eq!(len.value(), 12.0);
+        let speed: Quantity<MeterPerSecond> = "36 km/h".parse().unwrap();
+        assert!(close(speed.value(), 10.0));
+        let temp: Quantity<Celsius> = "212 F".parse().unwrap();
+        assert!(close(temp.value(), 100.0));
+
+        assert_eq!(
+            "12 kg".parse::<Quantity<Inch>>(),
+            Err(ParseQuantityError::UnknownUnit {
+                dimension: "length",
+                suffix: "kg".to_owned()
+            })
+        );
+        assert!("twelve in".parse::<Quantity<Inch>>().is_err());
+    }
+}
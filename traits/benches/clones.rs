@@ -0,0 +1,102 @@
+// Shallow, copy-on-write and deep clones of the config tree of `traits_shared_clones`, run with
+// `cargo bench`. `traits` is a binary crate and `#[bench]` needs nightly, so this is a plain
+// `main` on stable: every case is run in samples of a fixed number of iterations, after a
+// warm-up, and the median time per iteration is reported
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// The module itself, the binary has no library to link against. Only part of it is used, and its
+// tests don't run here
+#[allow(dead_code, unused_imports)]
+#[path = "../src/shared.rs"]
+mod shared;
+
+use shared::{DeepClone, Shared, Tracker};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Config {
+    Value(String),
+    Section(BTreeMap<String, Shared<Config>>),
+}
+
+impl DeepClone for Config {
+    fn deep_clone(&self) -> Config {
+        match self {
+            Config::Value(v) => Config::Value(v.deep_clone()),
+            Config::Section(s) => Config::Section(s.deep_clone()),
+        }
+    }
+}
+
+// `width` children per section, values at `depth` 0
+fn build(width: usize, depth: u32, tracker: &Tracker) -> Shared<Config> {
+    let node = if depth == 0 {
+        Config::Value(String::from("some configured value"))
+    } else {
+        Config::Section(
+            (0..width)
+                .map(|i| (format!("key{}", i), build(width, depth - 1, tracker)))
+                .collect(),
+        )
+    };
+    Shared::tracked(node, tracker)
+}
+
+fn set_first_leaf(node: &mut Shared<Config>, value: &str) {
+    match node.make_mut() {
+        Config::Section(children) => set_first_leaf(children.values_mut().next().unwrap(), value),
+        Config::Value(v) => *v = value.to_owned(),
+    }
+}
+
+const SAMPLES: usize = 21;
+const SAMPLE_TIME: Duration = Duration::from_millis(50);
+
+// Median time of one call of `f`
+fn bench<R, F: FnMut() -> R>(mut f: F) -> Duration {
+    // warm up, and find how many iterations fill a sample
+    let mut iterations = 1u32;
+    loop {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(f());
+        }
+        if start.elapsed() >= SAMPLE_TIME || iterations >= 1 << 24 {
+            break;
+        }
+        iterations *= 2;
+    }
+
+    let mut samples: Vec<Duration> = (0..SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            for _ in 0..iterations {
+                black_box(f());
+            }
+            start.elapsed() / iterations
+        })
+        .collect();
+    samples.sort();
+    samples[SAMPLES / 2]
+}
+
+fn main() {
+    for depth in [2, 3, 4] {
+        let tracker = Tracker::new();
+        let config = build(8, depth, &tracker);
+        let nodes: usize = (0..=depth).map(|d| 8usize.pow(d)).sum();
+        println!("config tree of {} nodes", nodes);
+
+        let shallow = bench(|| config.shallow_clone());
+        let on_write = bench(|| {
+            let mut copy = config.clone();
+            set_first_leaf(&mut copy, "overridden");
+            copy
+        });
+        let deep = bench(|| config.deep_clone());
+        println!("  shallow clone:         {:>12?}", shallow);
+        println!("  clone + one write:     {:>12?}", on_write);
+        println!("  deep clone:            {:>12?}", deep);
+    }
+}
@@ -0,0 +1,388 @@
+// `Droppable` of `traits_drop` (and `ToDrop` of scope_rules) print when they are dropped, which a
+// person can read but a test can't check. A `DropTracer` records its construction and its drop
+// into a shared `DropLog` instead, so a test can assert the exact order of cleanup
+// - every event gets the next number of one sequence, also across threads
+// - `DropLog::scope` names a region of code, events inside it carry the path of the open scopes
+//   of their thread, e.g. "request/db"
+// - `DropLog::mark` records a point in the code, to tell *when* something was dropped, e.g.
+//   before or after the end of a statement
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, ThreadId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Created,
+    Dropped,
+    Mark,
+    Enter,
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub seq: u64,
+    pub kind: EventKind,
+    pub name: String,
+    // Open scopes of the thread, outermost first, joined by '/'. Empty outside any scope
+    pub scope: String,
+    pub thread: ThreadId,
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            EventKind::Created => "new",
+            EventKind::Dropped => "drop",
+            EventKind::Mark => "mark",
+            EventKind::Enter => "enter",
+            EventKind::Exit => "exit",
+        };
+        write!(f, "#{} [{}] {} {}", self.seq, self.scope, kind, self.name)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    next_seq: u64,
+    events: Vec<TraceEvent>,
+    scopes: HashMap<ThreadId, Vec<String>>,
+}
+
+impl Inner {
+    fn record(&mut self, kind: EventKind, name: &str) {
+        let thread = thread::current().id();
+        let scope = self
+            .scopes
+            .get(&thread)
+            .map(|open| open.join("/"))
+            .unwrap_or_default();
+        self.events.push(TraceEvent {
+            seq: self.next_seq,
+            kind,
+            name: name.to_owned(),
+            scope,
+            thread,
+        });
+        self.next_seq += 1;
+    }
+}
+
+// Cheap to clone, every clone appends to the same log
+#[derive(Debug, Clone, Default)]
+pub struct DropLog {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl DropLog {
+    pub fn new() -> DropLog {
+        DropLog::default()
+    }
+
+    // Drops also happen while a panic unwinds, when the lock may have been poisoned by another
+    // tracer. The log is only ever appended to, so it stays usable
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn tracer(&self, name: &str) -> DropTracer {
+        self.lock().record(EventKind::Created, name);
+        DropTracer {
+            name: name.to_owned(),
+            log: self.clone(),
+        }
+    }
+
+    pub fn mark(&self, name: &str) {
+        self.lock().record(EventKind::Mark, name);
+    }
+
+    // Open until the returned guard is dropped, scopes nest per thread
+    pub fn scope(&self, name: &str) -> ScopeGuard {
+        let thread = thread::current().id();
+        let mut inner = self.lock();
+        inner.record(EventKind::Enter, name);
+        inner
+            .scopes
+            .entry(thread)
+            .or_default()
+            .push(name.to_owned());
+        ScopeGuard {
+            name: name.to_owned(),
+            thread,
+            log: self.clone(),
+        }
+    }
+
+    pub fn events(&self) -> Vec<TraceEvent> {
+        self.lock().events.clone()
+    }
+
+    fn names(&self, kind: EventKind) -> Vec<String> {
+        self.lock()
+            .events
+            .iter()
+            .filter(|e| e.kind == kind)
+            .map(|e| e.name.clone())
+            .collect()
+    }
+
+    pub fn created(&self) -> Vec<String> {
+        self.names(EventKind::Created)
+    }
+
+    pub fn dropped(&self) -> Vec<String> {
+        self.names(EventKind::Dropped)
+    }
+
+    // Names of drops and marks in the order they happened, the shape most order tests want
+    pub fn timeline(&self) -> Vec<String> {
+        self.lock()
+            .events
+            .iter()
+            .filter(|e| e.kind == EventKind::Dropped || e.kind == EventKind::Mark)
+            .map(|e| e.name.clone())
+            .collect()
+    }
+
+    // Tracers created but not (yet) dropped: still in scope, leaked or `mem::forget`-ed
+    pub fn alive(&self) -> Vec<String> {
+        let mut alive = self.created();
+        for name in self.dropped() {
+            if let Some(i) = alive.iter().position(|n| *n == name) {
+                alive.remove(i);
+            }
+        }
+        alive
+    }
+
+    // The sequence keeps counting, numbers stay unique over the life of the log
+    pub fn clear(&self) {
+        self.lock().events.clear();
+    }
+}
+
+impl fmt::Display for DropLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.lock().events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+// Put one into a struct, a `Vec`, a closure... and its drop shows up in the log
+#[derive(Debug)]
+pub struct DropTracer {
+    name: String,
+    log: DropLog,
+}
+
+impl DropTracer {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Drop for DropTracer {
+    fn drop(&mut self) {
+        self.log.lock().record(EventKind::Dropped, &self.name);
+    }
+}
+
+// Closes its own scope, on the thread which opened it, even when dropped out of order or on
+// another thread
+pub struct ScopeGuard {
+    name: String,
+    thread: ThreadId,
+    log: DropLog,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let mut inner = self.log.lock();
+        if let Some(open) = inner.scopes.get_mut(&self.thread) {
+            if let Some(i) = open.iter().rposition(|name| *name == self.name) {
+                open.remove(i);
+            }
+            if open.is_empty() {
+                inner.scopes.remove(&self.thread);
+            }
+        }
+        inner.record(EventKind::Exit, &self.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn locals_drop_in_reverse_order() {
+        let log = DropLog::new();
+        {
+            let _a = log.tracer("a");
+            let _b = log.tracer("b");
+            // shadowing hides `_b`, it doesn't drop it
+            let _b = log.tracer("b2");
+            log.mark("end of block");
+        }
+        assert_eq!(log.timeline(), ["end of block", "b2", "b", "a"]);
+        assert_eq!(log.created(), ["a", "b", "b2"]);
+    }
+
+    #[test]
+    fn fields_and_elements_drop_in_declaration_order() {
+        struct Service {
+            _db: DropTracer,
+            _cache: DropTracer,
+            _metrics: DropTracer,
+        }
+
+        let log = DropLog::new();
+        // created out of declaration order on purpose
+        let metrics = log.tracer("metrics");
+        let service = Service {
+            _cache: log.tracer("cache"),
+            _db: log.tracer("db"),
+            _metrics: metrics,
+        };
+        let workers = vec![log.tracer("w0"), log.tracer("w1")];
+        drop(service);
+        drop(workers);
+        assert_eq!(log.dropped(), ["db", "cache", "metrics", "w0", "w1"]);
+    }
+
+    #[test]
+    fn temporaries_drop_at_the_end_of_the_statement() {
+        let log = DropLog::new();
+        let len = log.tracer("temporary").name().len();
+        log.mark("next statement");
+        let _kept = log.tracer("bound");
+        let _ = log.tracer("ignored");
+        log.mark("after let _");
+        assert_eq!(len, 9);
+        assert_eq!(
+            log.timeline(),
+            ["temporary", "next statement", "ignored", "after let _"]
+        );
+        assert_eq!(log.alive(), ["bound"]);
+    }
+
+    #[test]
+    fn moves_and_forget() {
+        let log = DropLog::new();
+        let moved = log.tracer("moved");
+        let closure = move || moved.name().len();
+        log.mark("closure made");
+        assert_eq!(closure(), 5);
+        drop(closure);
+        mem::forget(log.tracer("forgotten"));
+        assert_eq!(log.timeline(), ["closure made", "moved"]);
+        assert_eq!(log.alive(), ["forgotten"]);
+    }
+
+    #[test]
+    fn scopes_nest_per_thread() {
+        let log = DropLog::new();
+        {
+            let _request = log.scope("request");
+            let _conn = log.tracer("conn");
+            let _db = log.scope("db");
+            let _row = log.tracer("row");
+        }
+        let events = log.events();
+        let got: Vec<(EventKind, &str, &str)> = events
+            .iter()
+            .map(|e| (e.kind, e.name.as_str(), e.scope.as_str()))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                (EventKind::Enter, "request", ""),
+                (EventKind::Created, "conn", "request"),
+                (EventKind::Enter, "db", "request"),
+                (EventKind::Created, "row", "request/db"),
+                (EventKind::Dropped, "row", "request/db"),
+                (EventKind::Exit, "db", "request"),
+                (EventKind::Dropped, "conn", "request"),
+                (EventKind::Exit, "request", ""),
+            ]
+        );
+        assert_eq!(events[3].to_string(), "#3 [request/db] new row");
+    }
+
+    #[test]
+    fn scopes_close_out_of_order_and_across_threads() {
+        let log = DropLog::new();
+        let outer = log.scope("outer");
+        let inner = log.scope("inner");
+        drop(outer);
+        log.mark("outer closed");
+        drop(inner);
+        log.mark("all closed");
+
+        let request = log.scope("request");
+        let _db = log.scope("db");
+        thread::spawn(move || drop(request)).join().unwrap();
+        log.mark("request closed elsewhere");
+
+        let events = log.events();
+        let marks: Vec<(&str, &str)> = events
+            .iter()
+            .filter(|e| e.kind == EventKind::Mark)
+            .map(|e| (e.name.as_str(), e.scope.as_str()))
+            .collect();
+        assert_eq!(
+            marks,
+            [
+                ("outer closed", "inner"),
+                ("all closed", ""),
+                ("request closed elsewhere", "db"),
+            ]
+        );
+    }
+
+    #[test]
+    fn one_sequence_across_threads() {
+        let log = DropLog::new();
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let log = log.clone();
+                thread::spawn(move || {
+                    let _scope = log.scope(&format!("worker{}", i));
+                    let _job = log.tracer(&format!("job{}", i));
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let events = log.events();
+        assert_eq!(events.len(), 16);
+        assert!(events.iter().enumerate().all(|(i, e)| e.seq == i as u64));
+        assert!(log.alive().is_empty());
+        // other threads' scopes never leak into a worker's events
+        for e in events.iter().filter(|e| e.kind == EventKind::Dropped) {
+            assert_eq!(e.scope, e.name.replace("job", "worker"));
+        }
+    }
+
+    #[test]
+    fn drops_during_a_panic_are_recorded() {
+        let log = DropLog::new();
+        let inner = log.clone();
+        let result = thread::spawn(move || {
+            let _guard = inner.tracer("guard");
+            panic!("request failed");
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(log.dropped(), ["guard"]);
+    }
+}
@@ -0,0 +1,478 @@
+// A tick-based farm with many animals, from the single `Sheep` of `traits_basic`
+// Every tick the scheduler visits the animals in the order they were added:
+// 1. `Animal::tick` lets the animal live, it gets hungrier and, if fed, grows wool or produces
+//    milk or eggs
+// 2. the farmer feeds every animal at or above `FEED_AT` hunger, while there is feed left
+// 3. the farmer collects whatever `Animal::harvest` is ready to give, a sheep only once its wool
+//    has fully grown back
+// Chance only comes from the farm's seeded `Pcg32`, so a seed replays the same log
+use crate::rng::{Pcg32, Rng};
+use std::fmt;
+
+// From this hunger on an animal stops producing
+pub const HUNGRY: u32 = 6;
+// The farmer feeds an animal from this hunger on
+pub const FEED_AT: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Produce {
+    Wool(u32),
+    Milk(u32),
+    Eggs(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Talked(&'static str),
+    GrewWool { wool: u32 },
+    Produced(Produce),
+    Hungry { hunger: u32 },
+    Ate { amount: u32 },
+    Harvested(Produce),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub tick: u64,
+    pub animal: String,
+    pub action: Action,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tick {:>3}: {} ", self.tick, self.animal)?;
+        match &self.action {
+            Action::Talked(noise) => write!(f, "says {}", noise),
+            Action::GrewWool { wool } => write!(f, "grew wool ({}/{})", wool, Sheep::FULL_WOOL),
+            Action::Produced(p) => write!(f, "produced {:?}", p),
+            Action::Hungry { hunger } => write!(f, "is hungry ({})", hunger),
+            Action::Ate { amount } => write!(f, "ate {} feed", amount),
+            Action::Harvested(p) => write!(f, "was harvested for {:?}", p),
+        }
+    }
+}
+
+pub trait Animal {
+    fn new(name: &str) -> Self
+    where
+        Self: Sized;
+
+    fn name(&self) -> &str;
+    fn species(&self) -> &'static str;
+    fn noise(&self) -> &'static str;
+
+    fn talk(&self) -> String {
+        format!("{} says {}", self.name(), self.noise())
+    }
+
+    fn hunger(&self) -> u32;
+    fn eat(&mut self, amount: u32);
+
+    // One tick of the animal's life, `None` when nothing worth logging happened
+    fn tick(&mut self, rng: &mut dyn Rng) -> Option<Action>;
+
+    // Takes what's ready to be collected
+    fn harvest(&mut self) -> Option<Produce>;
+}
+
+// What every species does first in a tick: get hungrier, and say so past `HUNGRY`
+fn get_hungrier(hunger: &mut u32) -> Option<Action> {
+    *hunger += 1;
+    if *hunger >= HUNGRY {
+        Some(Action::Hungry { hunger: *hunger })
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheep {
+    name: String,
+    wool: u32,
+    hunger: u32,
+}
+
+impl Sheep {
+    pub const FULL_WOOL: u32 = 5;
+
+    pub fn is_naked(&self) -> bool {
+        self.wool == 0
+    }
+
+    // All the wool there is, shearing a naked sheep gives nothing
+    pub fn shear(&mut self) -> Option<u32> {
+        if self.is_naked() {
+            None
+        } else {
+            Some(std::mem::replace(&mut self.wool, 0))
+        }
+    }
+}
+
+impl Animal for Sheep {
+    fn new(name: &str) -> Sheep {
+        Sheep {
+            name: name.to_owned(),
+            wool: Sheep::FULL_WOOL,
+            hunger: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn species(&self) -> &'static str {
+        "sheep"
+    }
+
+    fn noise(&self) -> &'static str {
+        if self.is_naked() {
+            "brrr"
+        } else {
+            "baaah"
+        }
+    }
+
+    fn hunger(&self) -> u32 {
+        self.hunger
+    }
+
+    fn eat(&mut self, amount: u32) {
+        self.hunger = self.hunger.saturating_sub(amount);
+    }
+
+    fn tick(&mut self, rng: &mut dyn Rng) -> Option<Action> {
+        if let Some(hungry) = get_hungrier(&mut self.hunger) {
+            return Some(hungry);
+        }
+        if self.wool < Sheep::FULL_WOOL {
+            self.wool += 1;
+            Some(Action::GrewWool { wool: self.wool })
+        } else if rng.gen_bool(0.3) {
+            Some(Action::Talked(self.noise()))
+        } else {
+            None
+        }
+    }
+
+    fn harvest(&mut self) -> Option<Produce> {
+        if self.wool == Sheep::FULL_WOOL {
+            self.shear().map(Produce::Wool)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cow {
+    name: String,
+    milk: u32,
+    hunger: u32,
+}
+
+impl Animal for Cow {
+    fn new(name: &str) -> Cow {
+        Cow {
+            name: name.to_owned(),
+            milk: 0,
+            hunger: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn species(&self) -> &'static str {
+        "cow"
+    }
+
+    fn noise(&self) -> &'static str {
+        "moooo"
+    }
+
+    fn hunger(&self) -> u32 {
+        self.hunger
+    }
+
+    fn eat(&mut self, amount: u32) {
+        self.hunger = self.hunger.saturating_sub(amount);
+    }
+
+    // 1 to 3 litres per tick
+    fn tick(&mut self, rng: &mut dyn Rng) -> Option<Action> {
+        if let Some(hungry) = get_hungrier(&mut self.hunger) {
+            return Some(hungry);
+        }
+        let litres = rng.gen_range(1..4) as u32;
+        self.milk += litres;
+        Some(Action::Produced(Produce::Milk(litres)))
+    }
+
+    // Milked once there's a bucket full
+    fn harvest(&mut self) -> Option<Produce> {
+        if self.milk >= 5 {
+            Some(Produce::Milk(std::mem::replace(&mut self.milk, 0)))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chicken {
+    name: String,
+    eggs: u32,
+    hunger: u32,
+}
+
+impl Animal for Chicken {
+    fn new(name: &str) -> Chicken {
+        Chicken {
+            name: name.to_owned(),
+            eggs: 0,
+            hunger: 0,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn species(&self) -> &'static str {
+        "chicken"
+    }
+
+    fn noise(&self) -> &'static str {
+        "cluck"
+    }
+
+    fn hunger(&self) -> u32 {
+        self.hunger
+    }
+
+    fn eat(&mut self, amount: u32) {
+        self.hunger = self.hunger.saturating_sub(amount);
+    }
+
+    // Lays on one tick out of two, on average
+    fn tick(&mut self, rng: &mut dyn Rng) -> Option<Action> {
+        if let Some(hungry) = get_hungrier(&mut self.hunger) {
+            return Some(hungry);
+        }
+        if rng.gen_bool(0.5) {
+            self.eggs += 1;
+            Some(Action::Produced(Produce::Eggs(1)))
+        } else {
+            Some(Action::Talked(self.noise()))
+        }
+    }
+
+    fn harvest(&mut self) -> Option<Produce> {
+        if self.eggs > 0 {
+            Some(Produce::Eggs(std::mem::replace(&mut self.eggs, 0)))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Summary {
+    pub ticks: u64,
+    pub wool: u32,
+    pub milk: u32,
+    pub eggs: u32,
+    pub feed_used: u32,
+    // animal-ticks spent at or above `HUNGRY`
+    pub hungry_ticks: u32,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ticks: {} wool, {} l milk, {} eggs, {} feed used, {} hungry animal-ticks",
+            self.ticks, self.wool, self.milk, self.eggs, self.feed_used, self.hungry_ticks
+        )
+    }
+}
+
+pub struct Farm {
+    animals: Vec<Box<dyn Animal>>,
+    rng: Pcg32,
+    feed: u32,
+    tick: u64,
+    log: Vec<Event>,
+    summary: Summary,
+}
+
+impl Farm {
+    pub fn new(seed: u64, feed: u32) -> Farm {
+        Farm {
+            animals: Vec::new(),
+            rng: Pcg32::new(seed, 0),
+            feed,
+            tick: 0,
+            log: Vec::new(),
+            summary: Summary::default(),
+        }
+    }
+
+    pub fn add<A: Animal + 'static>(&mut self, animal: A) {
+        self.animals.push(Box::new(animal));
+    }
+
+    pub fn animals(&self) -> impl Iterator<Item = &dyn Animal> {
+        self.animals.iter().map(|a| a.as_ref())
+    }
+
+    pub fn restock(&mut self, feed: u32) {
+        self.feed += feed;
+    }
+
+    pub fn feed_left(&self) -> u32 {
+        self.feed
+    }
+
+    pub fn step(&mut self) {
+        self.tick += 1;
+        self.summary.ticks += 1;
+        let tick = self.tick;
+        let mut events = Vec::new();
+
+        for animal in &mut self.animals {
+            if let Some(action) = animal.tick(&mut self.rng) {
+                events.push((animal.name().to_owned(), action));
+            }
+        }
+
+        for animal in &mut self.animals {
+            let hunger = animal.hunger();
+            if hunger >= FEED_AT && self.feed > 0 {
+                let amount = hunger.min(self.feed);
+                self.feed -= amount;
+                animal.eat(amount);
+                events.push((animal.name().to_owned(), Action::Ate { amount }));
+            }
+            if let Some(produce) = animal.harvest() {
+                events.push((animal.name().to_owned(), Action::Harvested(produce)));
+            }
+        }
+
+        for (animal, action) in events {
+            match action {
+                Action::Hungry { .. } => self.summary.hungry_ticks += 1,
+                Action::Ate { amount } => self.summary.feed_used += amount,
+                Action::Harvested(Produce::Wool(n)) => self.summary.wool += n,
+                Action::Harvested(Produce::Milk(n)) => self.summary.milk += n,
+                Action::Harvested(Produce::Eggs(n)) => self.summary.eggs += n,
+                _ => {}
+            }
+            self.log.push(Event {
+                tick,
+                animal,
+                action,
+            });
+        }
+    }
+
+    pub fn run(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.step();
+        }
+    }
+
+    pub fn log(&self) -> &[Event] {
+        &self.log
+    }
+
+    // Everything one animal did, in order
+    pub fn history<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Event> + 'a {
+        self.log.iter().filter(move |e| e.animal == name)
+    }
+
+    pub fn summary(&self) -> &Summary {
+        &self.summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Replay;
+
+    fn farm(seed: u64, feed: u32) -> Farm {
+        let mut farm = Farm::new(seed, feed);
+        farm.add(Sheep::new("Dolly"));
+        farm.add(Sheep::new("Shaun"));
+        farm.add(Cow::new("Daisy"));
+        farm.add(Chicken::new("Ginger"));
+        farm
+    }
+
+    #[test]
+    fn sheep_regrow_after_shearing() {
+        let mut dolly: Sheep = Animal::new("Dolly");
+        assert_eq!(dolly.harvest(), Some(Produce::Wool(5)));
+        assert!(dolly.is_naked());
+        assert_eq!(dolly.shear(), None);
+        assert_eq!(dolly.talk(), "Dolly says brrr");
+
+        let mut rng = Replay::new(Vec::new());
+        for wool in 1..=5 {
+            assert_eq!(dolly.tick(&mut rng), Some(Action::GrewWool { wool }));
+        }
+        assert_eq!(dolly.hunger(), 5);
+        assert_eq!(dolly.tick(&mut rng), Some(Action::Hungry { hunger: 6 }));
+        dolly.eat(10);
+        assert_eq!(dolly.hunger(), 0);
+    }
+
+    #[test]
+    fn same_seed_same_log() {
+        let (mut a, mut b) = (farm(11, 100), farm(11, 100));
+        a.run(30);
+        b.run(30);
+        assert_eq!(a.log(), b.log());
+        assert_eq!(a.summary(), b.summary());
+
+        let mut c = farm(12, 100);
+        c.run(30);
+        assert_ne!(a.log(), c.log());
+    }
+
+    #[test]
+    fn well_fed_farm_never_starves() {
+        let mut farm = farm(1, 1_000);
+        farm.run(50);
+        let summary = farm.summary();
+        assert_eq!(summary.hungry_ticks, 0);
+        // both sheep start full: sheared on tick 1, then every 5 ticks of regrowth
+        assert_eq!(summary.wool, 2 * 5 * 10);
+        assert!(summary.milk > 0 && summary.eggs > 0);
+        assert_eq!(summary.feed_used, 1_000 - farm.feed_left());
+    }
+
+    #[test]
+    fn no_feed_means_no_produce() {
+        let mut farm = farm(1, 0);
+        farm.run(20);
+        let dolly: Vec<&Event> = farm.history("Dolly").collect();
+        assert!(dolly
+            .iter()
+            .any(|e| e.action == Action::Hungry { hunger: HUNGRY }));
+        assert_eq!(dolly.last().unwrap().action, Action::Hungry { hunger: 20 });
+        // the initial shearing and 5 ticks of regrowth, then nothing
+        assert_eq!(farm.summary().wool, 10);
+        assert_eq!(farm.summary().hungry_ticks, 4 * (20 - 5));
+
+        farm.restock(100);
+        farm.step();
+        assert!(farm
+            .history("Daisy")
+            .any(|e| e.tick == 21 && matches!(e.action, Action::Ate { amount: 21 })));
+    }
+}
@@ -0,0 +1,668 @@
+// A small form framework grown from `UsernameWidget`/`AgeWidget` of
+// `traits_disambiguating_overlapping`
+// - `TextWidget`, `NumberWidget` and `ChoiceWidget` are the typed APIs, each with its own
+//   `get`/`set`/`validate`. A field implementing two of them needs the fully qualified
+//   `<Field as NumberWidget>::get(&field)` syntax, just like `Form` did there
+// - `Widget` is the untyped, object-safe side: parse an answer, render a prompt. `Form` keeps its
+//   named fields as `Box<dyn Widget>` and gets the typed values back by downcasting
+// - `Form::prompt` asks every field in turn on any `BufRead`, repeating a question until the
+//   answer is valid
+use std::any::Any;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldError {
+    Required,
+    TooShort { min: usize },
+    TooLong { max: usize },
+    NotANumber(String),
+    OutOfRange { min: i64, max: i64 },
+    UnknownChoice(String),
+    Invalid(String),
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FieldError::Required => write!(f, "a value is required"),
+            FieldError::TooShort { min } => write!(f, "needs at least {} characters", min),
+            FieldError::TooLong { max } => write!(f, "takes at most {} characters", max),
+            FieldError::NotANumber(s) => write!(f, "{:?} is not a number", s),
+            FieldError::OutOfRange { min, max } => write!(f, "must be within {}..={}", min, max),
+            FieldError::UnknownChoice(s) => write!(f, "{:?} is not one of the choices", s),
+            FieldError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+// A `FieldError` with the name of its field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormError {
+    pub field: String,
+    pub error: FieldError,
+}
+
+impl fmt::Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.error)
+    }
+}
+
+#[derive(Debug)]
+pub enum PromptError {
+    Io(io::Error),
+    // The input ended before this field got a valid answer
+    Eof(String),
+}
+
+impl fmt::Display for PromptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PromptError::Io(e) => write!(f, "prompt failed: {}", e),
+            PromptError::Eof(field) => write!(f, "input ended before {:?} was answered", field),
+        }
+    }
+}
+
+impl From<io::Error> for PromptError {
+    fn from(e: io::Error) -> PromptError {
+        PromptError::Io(e)
+    }
+}
+
+// What `Form` needs from any field
+pub trait Widget {
+    fn label(&self) -> &str;
+    // e.g. `[18-120]`, shown after the label
+    fn hint(&self) -> String;
+    fn is_required(&self) -> bool;
+    fn is_set(&self) -> bool;
+    // Parses and validates a typed-in answer, the value is only changed when it is valid
+    fn set_input(&mut self, input: &str) -> Result<(), FieldError>;
+    fn display_value(&self) -> Option<String>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+pub trait TextWidget {
+    fn get(&self) -> Option<&str>;
+    fn set(&mut self, value: &str) -> Result<(), FieldError>;
+    fn validate(&self, value: &str) -> Result<(), FieldError>;
+}
+
+pub trait NumberWidget {
+    fn get(&self) -> Option<i64>;
+    fn set(&mut self, value: i64) -> Result<(), FieldError>;
+    fn validate(&self, value: i64) -> Result<(), FieldError>;
+}
+
+pub trait ChoiceWidget {
+    fn get(&self) -> Option<&str>;
+    fn set(&mut self, choice: &str) -> Result<(), FieldError>;
+    fn validate(&self, choice: &str) -> Result<(), FieldError>;
+}
+
+type Check = Box<dyn Fn(&str) -> Result<(), String>>;
+
+pub struct TextField {
+    label: String,
+    value: Option<String>,
+    required: bool,
+    min_len: usize,
+    max_len: usize,
+    checks: Vec<Check>,
+}
+
+impl TextField {
+    pub fn new(label: &str) -> TextField {
+        TextField {
+            label: label.to_owned(),
+            value: None,
+            required: true,
+            min_len: 0,
+            max_len: usize::MAX,
+            checks: Vec::new(),
+        }
+    }
+
+    pub fn optional(mut self) -> TextField {
+        self.required = false;
+        self
+    }
+
+    pub fn length(mut self, min: usize, max: usize) -> TextField {
+        self.min_len = min;
+        self.max_len = max;
+        self
+    }
+
+    // Extra rules, e.g. "no spaces", reported as `FieldError::Invalid`
+    pub fn check<F: Fn(&str) -> Result<(), String> + 'static>(mut self, f: F) -> TextField {
+        self.checks.push(Box::new(f));
+        self
+    }
+}
+
+impl TextWidget for TextField {
+    fn get(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    fn set(&mut self, value: &str) -> Result<(), FieldError> {
+        TextWidget::validate(self, value)?;
+        self.value = Some(value.to_owned());
+        Ok(())
+    }
+
+    fn validate(&self, value: &str) -> Result<(), FieldError> {
+        let len = value.chars().count();
+        if len < self.min_len {
+            return Err(FieldError::TooShort { min: self.min_len });
+        }
+        if len > self.max_len {
+            return Err(FieldError::TooLong { max: self.max_len });
+        }
+        for check in &self.checks {
+            check(value).map_err(FieldError::Invalid)?;
+        }
+        Ok(())
+    }
+}
+
+impl Widget for TextField {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn hint(&self) -> String {
+        match (self.min_len, self.max_len) {
+            (0, usize::MAX) => String::new(),
+            (min, usize::MAX) => format!("[{}+ chars]", min),
+            (min, max) => format!("[{}-{} chars]", min, max),
+        }
+    }
+
+    fn is_required(&self) -> bool {
+        self.required
+    }
+
+    fn is_set(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn set_input(&mut self, input: &str) -> Result<(), FieldError> {
+        TextWidget::set(self, input)
+    }
+
+    fn display_value(&self) -> Option<String> {
+        self.value.clone()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+pub struct NumberField {
+    label: String,
+    value: Option<i64>,
+    required: bool,
+    min: i64,
+    max: i64,
+}
+
+impl NumberField {
+    pub fn new(label: &str) -> NumberField {
+        NumberField {
+            label: label.to_owned(),
+            value: None,
+            required: true,
+            min: i64::MIN,
+            max: i64::MAX,
+        }
+    }
+
+    pub fn range(mut self, min: i64, max: i64) -> NumberField {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    // Optional, with a value used when the answer is left empty
+    pub fn default_value(mut self, value: i64) -> NumberField {
+        self.value = Some(value);
+        self.required = false;
+        self
+    }
+}
+
+impl NumberWidget for NumberField {
+    fn get(&self) -> Option<i64> {
+        self.value
+    }
+
+    fn set(&mut self, value: i64) -> Result<(), FieldError> {
+        NumberWidget::validate(self, value)?;
+        self.value = Some(value);
+        Ok(())
+    }
+
+    fn validate(&self, value: i64) -> Result<(), FieldError> {
+        if (self.min..=self.max).contains(&value) {
+            Ok(())
+        } else {
+            Err(FieldError::OutOfRange {
+                min: self.min,
+                max: self.max,
+            })
+        }
+    }
+}
+
+impl Widget for NumberField {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn hint(&self) -> String {
+        match (self.min, self.max, self.value) {
+            (i64::MIN, i64::MAX, None) => String::new(),
+            (i64::MIN, i64::MAX, Some(default)) => format!("[{}]", default),
+            (min, max, None) => format!("[{}-{}]", min, max),
+            (min, max, Some(default)) => format!("[{}-{}, default {}]", min, max, default),
+        }
+    }
+
+    fn is_required(&self) -> bool {
+        self.required
+    }
+
+    fn is_set(&self) -> bool {
+        self.value.is_some()
+    }
+
+    fn set_input(&mut self, input: &str) -> Result<(), FieldError> {
+        let value = input
+            .parse()
+            .map_err(|_| FieldError::NotANumber(input.to_owned()))?;
+        NumberWidget::set(self, value)
+    }
+
+    fn display_value(&self) -> Option<String> {
+        self.value.map(|v| v.to_string())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// One of a fixed list. An answer is either the choice itself (any case) or its 1-based number
+pub struct ChoiceField {
+    label: String,
+    choices: Vec<String>,
+    selected: Option<usize>,
+    required: bool,
+}
+
+impl ChoiceField {
+    pub fn new(label: &str, choices: &[&str]) -> ChoiceField {
+        ChoiceField {
+            label: label.to_owned(),
+            choices: choices.iter().map(|c| c.to_string()).collect(),
+            selected: None,
+            required: true,
+        }
+    }
+
+    pub fn optional(mut self) -> ChoiceField {
+        self.required = false;
+        self
+    }
+
+    fn position(&self, choice: &str) -> Option<usize> {
+        let by_name = self
+            .choices
+            .iter()
+            .position(|c| c.eq_ignore_ascii_case(choice));
+        let by_number = choice
+            .parse::<usize>()
+            .ok()
+            .filter(|n| (1..=self.choices.len()).contains(n))
+            .map(|n| n - 1);
+        by_name.or(by_number)
+    }
+}
+
+impl ChoiceWidget for ChoiceField {
+    fn get(&self) -> Option<&str> {
+        self.selected.map(|i| self.choices[i].as_str())
+    }
+
+    fn set(&mut self, choice: &str) -> Result<(), FieldError> {
+        ChoiceWidget::validate(self, choice)?;
+        self.selected = self.position(choice);
+        Ok(())
+    }
+
+    fn validate(&self, choice: &str) -> Result<(), FieldError> {
+        match self.position(choice) {
+            Some(_) => Ok(()),
+            None => Err(FieldError::UnknownChoice(choice.to_owned())),
+        }
+    }
+}
+
+// The index of the selection is a number as well, so `get` needs disambiguating
+impl NumberWidget for ChoiceField {
+    fn get(&self) -> Option<i64> {
+        self.selected.map(|i| i as i64 + 1)
+    }
+
+    fn set(&mut self, value: i64) -> Result<(), FieldError> {
+        NumberWidget::validate(self, value)?;
+        self.selected = Some(value as usize - 1);
+        Ok(())
+    }
+
+    fn validate(&self, value: i64) -> Result<(), FieldError> {
+        if (1..=self.choices.len() as i64).contains(&value) {
+            Ok(())
+        } else {
+            Err(FieldError::OutOfRange {
+                min: 1,
+                max: self.choices.len() as i64,
+            })
+        }
+    }
+}
+
+impl Widget for ChoiceField {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn hint(&self) -> String {
+        let numbered: Vec<String> = self
+            .choices
+            .iter()
+            .enumerate()
+            .map(|(i, c)| format!("{}) {}", i + 1, c))
+            .collect();
+        format!("[{}]", numbered.join(", "))
+    }
+
+    fn is_required(&self) -> bool {
+        self.required
+    }
+
+    fn is_set(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    fn set_input(&mut self, input: &str) -> Result<(), FieldError> {
+        ChoiceWidget::set(self, input)
+    }
+
+    fn display_value(&self) -> Option<String> {
+        ChoiceWidget::get(self).map(str::to_owned)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+// Named fields, asked and rendered in insertion order
+#[derive(Default)]
+pub struct Form {
+    fields: Vec<(String, Box<dyn Widget>)>,
+}
+
+impl Form {
+    pub fn new() -> Form {
+        Form::default()
+    }
+
+    pub fn field<W: Widget + 'static>(mut self, name: &str, widget: W) -> Form {
+        self.fields.push((name.to_owned(), Box::new(widget)));
+        self
+    }
+
+    fn widget(&self, name: &str) -> Option<&dyn Widget> {
+        self.fields
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, w)| w.as_ref())
+    }
+
+    // `None` when there's no such field, or it isn't of that type
+    fn typed<W: 'static>(&self, name: &str) -> Option<&W> {
+        self.widget(name)?.as_any().downcast_ref()
+    }
+
+    pub fn text(&self, name: &str) -> Option<&str> {
+        TextWidget::get(self.typed::<TextField>(name)?)
+    }
+
+    pub fn number(&self, name: &str) -> Option<i64> {
+        NumberWidget::get(self.typed::<NumberField>(name)?)
+    }
+
+    pub fn choice(&self, name: &str) -> Option<&str> {
+        ChoiceWidget::get(self.typed::<ChoiceField>(name)?)
+    }
+
+    pub fn set(&mut self, name: &str, input: &str) -> Result<(), FormError> {
+        let widget = self
+            .fields
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, w)| w)
+            .ok_or_else(|| FormError {
+                field: name.to_owned(),
+                error: FieldError::Invalid("no such field".to_owned()),
+            })?;
+        widget.set_input(input).map_err(|error| FormError {
+            field: name.to_owned(),
+            error,
+        })
+    }
+
+    // Every missing required field, in form order
+    pub fn validate(&self) -> Result<(), Vec<FormError>> {
+        let errors: Vec<FormError> = self
+            .fields
+            .iter()
+            .filter(|(_, w)| w.is_required() && !w.is_set())
+            .map(|(name, _)| FormError {
+                field: name.clone(),
+                error: FieldError::Required,
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // Asks every field on `output`, reading one answer per line from `input`. An invalid answer
+    // prints the error and asks again, an empty one keeps the current value of optional fields
+    pub fn prompt<R: BufRead, W: Write>(
+        &mut self,
+        mut input: R,
+        mut output: W,
+    ) -> Result<(), PromptError> {
+        for (name, widget) in &mut self.fields {
+            loop {
+                let hint = widget.hint();
+                let marker = if widget.is_required() {
+                    ""
+                } else {
+                    " (optional)"
+                };
+                if hint.is_empty() {
+                    write!(output, "{}{}: ", widget.label(), marker)?;
+                } else {
+                    write!(output, "{} {}{}: ", widget.label(), hint, marker)?;
+                }
+                output.flush()?;
+
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    return Err(PromptError::Eof(name.clone()));
+                }
+                let answer = line.trim();
+                let result = if answer.is_empty() {
+                    if widget.is_required() && !widget.is_set() {
+                        Err(FieldError::Required)
+                    } else {
+                        Ok(())
+                    }
+                } else {
+                    widget.set_input(answer)
+                };
+                match result {
+                    Ok(()) => break,
+                    Err(e) => writeln!(output, "  {}", e)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Plain-text summary, `-` for unset fields
+impl fmt::Display for Form {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .fields
+            .iter()
+            .map(|(_, w)| w.label().len())
+            .max()
+            .unwrap_or(0);
+        for (_, widget) in &self.fields {
+            let value = widget.display_value().unwrap_or_else(|| "-".to_owned());
+            writeln!(f, "{:width$} : {}", widget.label(), value, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signup() -> Form {
+        Form::new()
+            .field(
+                "username",
+                TextField::new("Username")
+                    .length(3, 12)
+                    .check(|s| match s.contains(' ') {
+                        true => Err("no spaces allowed".to_owned()),
+                        false => Ok(()),
+                    }),
+            )
+            .field("age", NumberField::new("Age").range(18, 120))
+            .field(
+                "editor",
+                ChoiceField::new("Editor", &["vim", "emacs", "other"]),
+            )
+            .field("retries", NumberField::new("Retries").default_value(3))
+    }
+
+    #[test]
+    fn per_field_errors() {
+        let mut form = signup();
+        assert_eq!(
+            form.set("username", "a b c"),
+            Err(FormError {
+                field: "username".to_owned(),
+                error: FieldError::Invalid("no spaces allowed".to_owned())
+            })
+        );
+        assert_eq!(
+            form.set("age", "12").unwrap_err().error,
+            FieldError::OutOfRange { min: 18, max: 120 }
+        );
+        assert_eq!(
+            form.set("age", "old").unwrap_err().error,
+            FieldError::NotANumber("old".to_owned())
+        );
+        assert_eq!(
+            form.set("editor", "nano").unwrap_err().error,
+            FieldError::UnknownChoice("nano".to_owned())
+        );
+
+        let missing: Vec<String> = form
+            .validate()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        assert_eq!(missing, ["username", "age", "editor"]);
+    }
+
+    #[test]
+    fn typed_access() {
+        let mut form = signup();
+        form.set("username", "ferris").unwrap();
+        form.set("age", "28").unwrap();
+        form.set("editor", "EMACS").unwrap();
+
+        assert_eq!(form.validate(), Ok(()));
+        assert_eq!(form.text("username"), Some("ferris"));
+        assert_eq!(form.number("age"), Some(28));
+        assert_eq!(form.number("retries"), Some(3));
+        assert_eq!(form.choice("editor"), Some("emacs"));
+        // wrong type or unknown name
+        assert_eq!(form.number("username"), None);
+        assert_eq!(form.text("nope"), None);
+    }
+
+    #[test]
+    fn overlapping_get_needs_qualification() {
+        let mut editor = ChoiceField::new("Editor", &["vim", "emacs"]);
+        ChoiceWidget::set(&mut editor, "2").unwrap();
+        assert_eq!(<ChoiceField as ChoiceWidget>::get(&editor), Some("emacs"));
+        assert_eq!(<ChoiceField as NumberWidget>::get(&editor), Some(2));
+        assert!(NumberWidget::set(&mut editor, 3).is_err());
+    }
+
+    #[test]
+    fn prompt_repeats_until_valid() {
+        let mut form = signup();
+        let input = "x\nferris\n\n17\n28\n3\n\n";
+        let mut output = Vec::new();
+        form.prompt(input.as_bytes(), &mut output).unwrap();
+
+        let transcript = String::from_utf8(output).unwrap();
+        assert_eq!(
+            transcript,
+            "Username [3-12 chars]:   needs at least 3 characters\n\
+             Username [3-12 chars]: \
+             Age [18-120]:   a value is required\n\
+             Age [18-120]:   must be within 18..=120\n\
+             Age [18-120]: \
+             Editor [1) vim, 2) emacs, 3) other]: \
+             Retries [3] (optional): "
+        );
+        assert_eq!(form.choice("editor"), Some("other"));
+        assert_eq!(form.number("retries"), Some(3));
+        assert_eq!(
+            form.to_string(),
+            "Username : ferris\nAge      : 28\nEditor   : other\nRetries  : 3\n"
+        );
+    }
+
+    #[test]
+    fn prompt_reports_early_eof() {
+        let mut form = signup();
+        match form.prompt("ferris\n".as_bytes(), io::sink()) {
+            Err(PromptError::Eof(field)) => assert_eq!(field, "age"),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -1,6 +1,16 @@
-use random::Source;
 use std::ops;
 
+mod droptrace;
+mod farm;
+mod forms;
+mod merge;
+mod newtype;
+mod profiles;
+mod rng;
+mod sequences;
+mod shared;
+mod vector;
+
 fn main() {
     traits_basic();
     traits_derive();
@@ -12,6 +22,677 @@ fn main() {
     traits_clone();
     traits_supertraits();
     traits_disambiguating_overlapping();
+    traits_form_widgets();
+    traits_profile_registry();
+    traits_reproducible_rng();
+    traits_farm_simulation();
+    traits_vector_algebra();
+    traits_drop_tracing();
+    traits_number_sequences();
+    traits_streaming_merge();
+    traits_shared_clones();
+    traits_newtype_units();
+}
+
+// `traits_derive` again, with `newtype!` writing the impls `Seconds` lacked and the conversion
+// `Inches::to_centimeters` did by hand
+fn traits_newtype_units() {
+    use newtype::newtype;
+
+    newtype! {
+        struct Centimeters(f64) unit "cm";
+        with add, sub, scale, sum, ord;
+    }
+
+    newtype! {
+        struct Inches(f64) unit "in";
+        with add, neg, ord;
+        convert Centimeters = 2.54;
+    }
+
+    newtype! {
+        struct Seconds(i32) unit "s";
+        with add, sub, scale, total;
+    }
+
+    newtype! {
+        struct Minutes(i32) unit "min";
+        with total;
+        convert Seconds = 60;
+    }
+
+    newtype! {
+        // Nothing opted into
+        struct Hours(i32) unit "h";
+        convert Minutes = 60;
+    }
+
+    let one_second = Seconds(1);
+    println!("One second looks like: {:?}, or {}", one_second, one_second);
+    println!("and equals itself: {}", one_second == Seconds(1));
+    let lap = Seconds::new(75) - one_second * 15;
+    println!(
+        "a lap takes {} ({} laps per {})",
+        lap,
+        Seconds(240) / lap,
+        Minutes(4)
+    );
+    println!("{} is {}", Minutes(2), Minutes(2).to::<Seconds>());
+    println!("{} is {} (truncated)", lap, Minutes::from(lap));
+    println!("laps sorted: {:?}", {
+        let mut laps = vec![Seconds(70), Seconds(61), lap];
+        laps.sort();
+        laps
+    });
+
+    let foot = Inches(12.0);
+    let meter = Centimeters(100.0);
+    let cmp = if foot.to::<Centimeters>() < meter {
+        "smaller"
+    } else {
+        "bigger"
+    };
+    println!(
+        "One foot ({:.2}) is {} than one meter",
+        Centimeters::from(foot),
+        cmp
+    );
+    println!("{} back in inches: {:.3}", meter, Inches::from(meter));
+    let pieces = [
+        Centimeters(10.0),
+        Centimeters(2.5),
+        (Inches(1.0) + -Inches(0.5)).into(),
+    ];
+    let total: Centimeters = pieces.iter().copied().sum();
+    println!(
+        "together {:.2}, {:.2} left of the meter",
+        total,
+        meter - total
+    );
+
+    let shift = Hours::from(8);
+    let raw: i32 = shift.into();
+    println!(
+        "a shift of {} {} is {}, none yet is {}",
+        raw,
+        Hours::UNIT,
+        shift.to::<Minutes>(),
+        Hours::default()
+    );
+    println!(
+        "{} in minutes: {:?}",
+        Hours(i32::MAX),
+        Hours(i32::MAX).try_to::<Minutes>()
+    );
+    // Compile-Error: `Hours` didn't opt into `add`, nor `Seconds` into `+` with `Minutes`
+    // let _ = Hours(1) + Hours(2);
+    // let _ = Seconds(1) + Minutes(1);
+}
+
+// `Pair`'s boxes of `traits_clone` are copied on every clone; a config tree cloned per request
+// is better shared until it's written to. The counts compare the three ways to clone one, `cargo
+// bench` times them (benches/clones.rs)
+fn traits_shared_clones() {
+    use shared::{DeepClone, Shared, Tracker};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Config {
+        Value(String),
+        Section(BTreeMap<String, Shared<Config>>),
+    }
+
+    impl DeepClone for Config {
+        fn deep_clone(&self) -> Config {
+            match self {
+                Config::Value(v) => Config::Value(v.deep_clone()),
+                Config::Section(s) => Config::Section(s.deep_clone()),
+            }
+        }
+    }
+
+    // `width` children per section, values at `depth` 0
+    fn build(width: usize, depth: u32, tracker: &Tracker) -> Shared<Config> {
+        let node = if depth == 0 {
+            Config::Value(String::from("some configured value"))
+        } else {
+            Config::Section(
+                (0..width)
+                    .map(|i| (format!("key{}", i), build(width, depth - 1, tracker)))
+                    .collect(),
+            )
+        };
+        Shared::tracked(node, tracker)
+    }
+
+    // A request overriding one value: copies the sections on the way down
+    fn set_first_leaf(node: &mut Shared<Config>, value: &str) {
+        match node.make_mut() {
+            Config::Section(children) => {
+                set_first_leaf(children.values_mut().next().unwrap(), value)
+            }
+            Config::Value(v) => *v = value.to_owned(),
+        }
+    }
+
+    let tracker = Tracker::new();
+    let config = build(8, 4, &tracker);
+    let nodes = (0..=4).map(|d| 8usize.pow(d)).sum::<usize>();
+    println!("config tree of {} nodes", nodes);
+
+    let copy = config.shallow_clone();
+    assert!(copy.ptr_eq(&config));
+    drop(copy);
+    println!("shallow: {}", tracker.report());
+
+    tracker.reset();
+    let mut copy = config.clone();
+    set_first_leaf(&mut copy, "overridden");
+    drop(copy);
+    println!("copy on write: {}", tracker.report());
+
+    tracker.reset();
+    let copy = config.deep_clone();
+    assert!(copy == config);
+    println!("deep: {}", tracker.report());
+
+    let mut request = config.clone();
+    set_first_leaf(&mut request, "mine");
+    println!(
+        "after a write: shared with the original {}, {} containers share the root, unchanged: {}",
+        request.ptr_eq(&config),
+        config.share_count(),
+        request == config
+    );
+    println!("root still shared: {}", config.is_shared());
+    let plain = Shared::new(vec![1, 2, 3]);
+    println!(
+        "into_inner: {:?}, {}",
+        plain.clone().into_inner(),
+        plain.tracker().report()
+    );
+}
+
+// `combine_vecs` of `traits_impl_trait` concatenates with `chain`; merging log files needs the
+// streams interleaved in order, and sorting more lines than fit in memory
+fn traits_streaming_merge() {
+    use merge::{dedup_sorted, external_sort, merge, merge_by, round_robin};
+    use rng::{Pcg32, Rng};
+
+    let v1 = vec![1, 3, 5];
+    let v2 = vec![2, 4, 6];
+    let merged: Vec<i32> = merge(vec![v1.clone(), v2.clone()]).collect();
+    let turns: Vec<i32> = round_robin(vec![v2, v1]).collect();
+    println!("merged {:?}, round robin {:?}", merged, turns);
+
+    // Log lines of three services, each file in time order, merged by their timestamp
+    let api = vec![
+        "10:00:01 api start",
+        "10:00:04 api GET /",
+        "10:00:09 api stop",
+    ];
+    let db = vec!["10:00:00 db start", "10:00:04 db query", "10:00:08 db stop"];
+    let cache = vec!["10:00:02 cache miss", "10:00:04 cache fill"];
+    let timestamp = |line: &&str| line.split(' ').next().unwrap_or_default().to_owned();
+    for line in merge_by(vec![api, db, cache], |a, b| timestamp(a).cmp(&timestamp(b))) {
+        println!("  {}", line);
+    }
+
+    let seen: Vec<u32> = dedup_sorted(merge(vec![vec![1, 1, 2, 8], vec![2, 3, 8]])).collect();
+    println!("distinct: {:?}", seen);
+
+    // 20000 lines sorted 2000 at a time, through temporary files
+    let mut rng = Pcg32::new(48, 0);
+    let lines = (0..20_000).map(|_| format!("{:05}", rng.gen_range(0..50_000)));
+    match external_sort(lines, 2_000, &std::env::temp_dir()) {
+        Ok(sorted) => {
+            let runs = sorted.runs().len();
+            let distinct: Vec<String> = dedup_sorted(sorted.map_while(Result::ok)).collect();
+            println!(
+                "external sort: {} runs, {} distinct values, smallest {:?}, largest {:?}",
+                runs,
+                distinct.len(),
+                distinct.first(),
+                distinct.last()
+            );
+        }
+        Err(e) => println!("external sort failed: {}", e),
+    }
+}
+
+// `Fibonacci` of `traits_iterators` overflows after 47 terms of `u32`; these sequences are generic
+// over the integer type, end with `None` instead, and `nth` jumps in O(log n)
+fn traits_number_sequences() {
+    use sequences::{fibonacci, lucas, Collatz, Fibonacci, LinearRecurrence, Primes};
+
+    println!(
+        "Fibonacci numbers per type: u8 {}, u32 {}, i64 {}, u128 {}",
+        Fibonacci::<u8>::new().count(),
+        Fibonacci::<u32>::new().count(),
+        Fibonacci::<i64>::new().count(),
+        Fibonacci::<u128>::new().count()
+    );
+    let mut fib = Fibonacci::<u64>::new();
+    println!(
+        "F(10) = {:?}, then F({}) = {:?}, F(93) = {:?}, F(94) = {:?}",
+        fib.nth(10),
+        fib.index(),
+        fib.next(),
+        fibonacci::<u64>(93),
+        fibonacci::<u64>(94)
+    );
+    let lucas_numbers: Vec<u32> = Fibonacci::lucas().take(10).collect();
+    println!(
+        "Lucas: {:?}, L(100) = {:?}",
+        lucas_numbers,
+        lucas::<u128>(100)
+    );
+
+    let mut tribonacci = LinearRecurrence::new(&[1u64, 1, 1], &[0, 0, 1]);
+    println!(
+        "Tribonacci: {:?}, T(70) = {:?}",
+        tribonacci.clone().take(10).collect::<Vec<_>>(),
+        tribonacci.nth(70)
+    );
+    let pell = LinearRecurrence::new(&[1i32, 2], &[0, 1]);
+    println!("Pell numbers in an i32: {}", pell.count());
+
+    let primes: Vec<u32> = Primes::new().take(15).collect();
+    println!("primes: {:?}", primes);
+    println!(
+        "the 10000th prime: {:?}, after 10^12: {:?}, largest u16: {:?}",
+        Primes::<u32>::new().nth(9_999),
+        Primes::<u64>::starting_at(1_000_000_000_000).next(),
+        Primes::<u16>::starting_at(65_000).last()
+    );
+
+    let path: Vec<u32> = Collatz::new(7).collect();
+    println!("Collatz(7): {:?}", path);
+    let longest = (1u32..10_000)
+        .max_by_key(|&n| Collatz::new(n as u64).count())
+        .unwrap();
+    println!(
+        "longest Collatz path below 10000 starts at {} ({} terms), in u16 it's cut at {:?}",
+        longest,
+        Collatz::new(longest as u64).count(),
+        Collatz::new(longest as u16).last()
+    );
+}
+
+// The blocks of `traits_drop` again, with `DropTracer`s recording into a log instead of printing,
+// so the order can be checked rather than read
+fn traits_drop_tracing() {
+    use droptrace::{DropLog, DropTracer};
+
+    // Fields drop in declaration order, after the struct's own `drop` if it had one
+    struct Connection {
+        _socket: DropTracer,
+        _buffer: DropTracer,
+    }
+
+    let log = DropLog::new();
+    let _a = log.tracer("AAA");
+    {
+        let _block_a = log.scope("A");
+        let _b = log.tracer("BBB");
+        {
+            let _block_b = log.scope("B");
+            let _c = log.tracer("CCC");
+            let _d = log.tracer("DDD");
+            log.mark("exiting B");
+        }
+        let _conn = Connection {
+            _socket: log.tracer("socket"),
+            _buffer: log.tracer("buffer"),
+        };
+        println!("temporary {} lives until the ';'", log.tracer("tmp").name());
+        log.mark("exiting A");
+    }
+    drop(_a);
+    print!("{}", log);
+    println!("drops: {}", log.dropped().join(", "));
+    println!("order with marks: {}", log.timeline().join(" < "));
+    println!(
+        "alive: {:?}, {} created, {} events",
+        log.alive(),
+        log.created().len(),
+        log.events().len()
+    );
+    log.clear();
+    println!("cleared: {}", log.events().is_empty());
+}
+
+// `Foo + Bar` of `traits_operator_overloading` doesn't scale past a toy, `Vec2`/`Vec3` implement
+// the whole operator set for values and references, here driving a small projectile simulation
+fn traits_vector_algebra() {
+    use vector::{ApproxEq, Vec2, Vec3};
+
+    let a = Vec3::new(1.0, 2.0, 3.0);
+    let b = Vec3::new(-2.0, 0.5, 1.0);
+    println!("a + b = {}, a - b = {}, -a = {}", a + b, a - b, -a);
+    println!("2a = {}, a / 2 = {}", 2.0 * a, a / 2.0);
+    println!("a . b = {}, a x b = {}", a.dot(&b), a.cross(&b));
+    println!("|a| = {:.3}, unit a = {:?}", a.length(), a.normalize());
+
+    let mut c = a;
+    c[1] = 0.0;
+    c += &b;
+    c *= 3.0;
+    println!("c = {}, c.z = {}, in the plane {}", c, c[2], c.truncate());
+
+    // Code holding references doesn't have to copy the operands out first
+    fn reflect(v: &Vec2, normal: &Vec2) -> Vec2 {
+        v - normal * (2.0 * v.dot(normal))
+    }
+
+    // Euler steps of a ball thrown at 45 degrees, with gravity and linear drag
+    let gravity = Vec2::new(0.0, -9.81);
+    let drag = 0.1;
+    let dt = 0.01;
+    let mut position = Vec2::zero();
+    let mut velocity = Vec2::new(1.0, 1.0).normalize().unwrap() * 20.0;
+    let mut path = vec![position];
+    while position.y >= 0.0 {
+        let acceleration = gravity - velocity * drag;
+        velocity += acceleration * dt;
+        position += velocity * dt;
+        path.push(position);
+    }
+    let bounce = reflect(&velocity, &Vec2::new(0.0, 1.0));
+    let apex = path
+        .iter()
+        .fold(Vec2::zero(), |top, p| if p.y > top.y { *p } else { top });
+    let centroid = path.iter().sum::<Vec2>() / path.len() as f64;
+    println!(
+        "landed at x = {:.2} after {} steps, apex {:.2} high, path centroid ({:.2}, {:.2})",
+        position.x,
+        path.len() - 1,
+        apex.y,
+        centroid.x,
+        centroid.y
+    );
+    println!("bounces off at ({:.2}, {:.2})", bounce.x, bounce.y);
+
+    // The rounding of many small steps makes `==` useless, an epsilon isn't
+    let step = Vec2::new(0.1, 0.2);
+    let walked: Vec2 = (0..10).map(|_| step).sum();
+    let target = Vec2::new(1.0, 2.0);
+    println!(
+        "10 steps == {}: {}, approx: {}, {:e} apart",
+        target,
+        walked == target,
+        walked.approx_eq(&target),
+        walked.distance(&target)
+    );
+    println!(
+        "halfway {}, left of it {}, turn {}",
+        Vec2::zero().lerp(&target, 0.5),
+        target.perp(),
+        step.cross(&target.perp()) > 0.0
+    );
+    println!("extended {}", target.extend(1.0).to_array().len());
+}
+
+// The `Sheep` of `traits_basic` on a whole farm: every tick each animal gets hungrier, regrows
+// wool or produces, and the farmer feeds and harvests; a seed replays the same season
+fn traits_farm_simulation() {
+    use farm::{Animal, Chicken, Cow, Farm, Sheep};
+
+    let mut farm = Farm::new(2024, 40);
+    farm.add(Sheep::new("Dolly"));
+    farm.add(Sheep::new("Shaun"));
+    farm.add(Cow::new("Daisy"));
+    farm.add(Chicken::new("Ginger"));
+    for animal in farm.animals() {
+        println!(
+            "{} the {}: {}",
+            animal.name(),
+            animal.species(),
+            animal.talk()
+        );
+    }
+
+    farm.run(12);
+    println!("Dolly's season:");
+    for event in farm.history("Dolly") {
+        println!("  {}", event);
+    }
+    println!(
+        "{} events, {} feed left",
+        farm.log().len(),
+        farm.feed_left()
+    );
+    println!("{}", farm.summary());
+
+    // Out of feed, the animals stop producing until the barn is restocked
+    farm.run(8);
+    farm.restock(100);
+    farm.run(10);
+    println!("{}", farm.summary());
+    for animal in farm.animals() {
+        println!("  {} hunger {}", animal.name(), animal.hunger());
+    }
+}
+
+// `random_animal` on the project's own `Rng`: a seed replays the same herd, the animals are
+// picked from weighted factories, and a recorded run can be replayed value for value
+fn traits_reproducible_rng() {
+    use rng::{Pcg32, Recorder, Replay, Rng, XorShift64Star};
+
+    trait Animal {
+        fn noise(&self) -> &'static str;
+    }
+
+    struct Sheep;
+    struct Cow;
+    struct Goat;
+
+    impl Animal for Sheep {
+        fn noise(&self) -> &'static str {
+            "baaah"
+        }
+    }
+
+    impl Animal for Cow {
+        fn noise(&self) -> &'static str {
+            "moooo"
+        }
+    }
+
+    impl Animal for Goat {
+        fn noise(&self) -> &'static str {
+            "meeeh"
+        }
+    }
+
+    type Factory = fn() -> Box<dyn Animal>;
+    let factories: [(u32, Factory); 3] = [
+        (5, || Box::new(Sheep)),
+        (3, || Box::new(Cow)),
+        (1, || Box::new(Goat)),
+    ];
+
+    fn herd<R: Rng>(rng: &mut R, factories: &[(u32, Factory)], size: usize) -> Vec<&'static str> {
+        (0..size)
+            .map(|_| rng.choose_weighted(factories).unwrap()().noise())
+            .collect()
+    }
+
+    let first = herd(&mut Pcg32::new(2024, 7), &factories, 6);
+    let second = herd(&mut Pcg32::new(2024, 7), &factories, 6);
+    println!("{:?}, same seed same herd: {}", first, first == second);
+    println!(
+        "another generator: {:?}",
+        herd(&mut XorShift64Star::new(2024), &factories, 6)
+    );
+
+    let mut rng = Pcg32::new(1, 1);
+    let mut order = vec!["feed", "milk", "shear", "sleep"];
+    rng.shuffle(&mut order);
+    println!(
+        "chores: {:?}, dice: {}, coin: {}, pick: {:?}",
+        order,
+        rng.gen_range(1..7),
+        rng.gen_bool(0.5),
+        rng.choose(&order)
+    );
+
+    // Record a run, then replay it without the original generator
+    let mut recorder = Recorder::new(Pcg32::new(42, 54));
+    let recorded = herd(&mut recorder, &factories, 3);
+    println!(
+        "recorded {} raw values: {:x?}",
+        recorder.tape().len(),
+        recorder.tape()
+    );
+    let mut replay = recorder.into_replay();
+    println!(
+        "replay gives the same herd: {}, tape used up: {}",
+        herd(&mut replay, &factories, 3) == recorded,
+        replay.remaining() == 0
+    );
+
+    // `gen_f64` values chosen by hand, for the 0.5 threshold of `random_animal`
+    let mut scripted = Replay::from_unit_floats(&[0.1, 0.9]);
+    for _ in 0..2 {
+        let x = scripted.gen_f64();
+        println!("{:.2} -> {}", x, if x < 0.5 { "sheep" } else { "cow" });
+    }
+}
+
+// The `Person` chain of `traits_supertraits` as a directory: heterogeneous `Box<dyn Person>`
+// entries, queried by role through the `as_student()`-style upcasts, and stored as lines
+fn traits_profile_registry() {
+    use profiles::{comp_sci_student_greeting, Cs, Developer, Registry, Staff, Undergrad};
+
+    let mut registry = Registry::new();
+    registry.add(Cs {
+        name: "Bill".to_owned(),
+        university: "Hust".to_owned(),
+        fav_language: "Rust".to_owned(),
+        git_username: "dreamfly".to_owned(),
+    });
+    registry.add(Undergrad {
+        name: "Lily".to_owned(),
+        university: "Hust".to_owned(),
+    });
+    registry.add(Developer {
+        name: "Tom".to_owned(),
+        fav_language: "C++".to_owned(),
+    });
+    registry.add(Staff {
+        name: "Ann".to_owned(),
+    });
+
+    print!("{}", registry.report());
+
+    let from_hust: Vec<String> = registry
+        .students()
+        .filter(|s| s.university() == "Hust")
+        .map(|s| s.name())
+        .collect();
+    println!("Hust students: {:?}", from_hust);
+    for cs in registry.cs_students() {
+        println!("{}", comp_sci_student_greeting(cs));
+    }
+    if let Some(ann) = registry.find("Ann") {
+        println!("Ann programs: {}", ann.as_programmer().is_some());
+    }
+
+    let lines = registry.to_lines();
+    print!("{}", lines);
+    match Registry::from_lines(&lines) {
+        Ok(copy) => println!(
+            "reloaded {} profiles, empty: {}",
+            copy.len(),
+            copy.is_empty()
+        ),
+        Err(e) => println!("{}", e),
+    }
+    if let Err(e) = Registry::from_lines("name=Eve;git=eve") {
+        println!("{}", e);
+    }
+}
+
+// `UsernameWidget`/`AgeWidget` as a form framework: typed text, number and choice widgets behind
+// one `Widget` trait, filled in from any `BufRead`, here a canned answer sheet
+fn traits_form_widgets() {
+    use forms::{ChoiceField, ChoiceWidget, Form, NumberField, NumberWidget, TextField};
+    use std::io;
+
+    let mut form = Form::new()
+        .field(
+            "username",
+            TextField::new("Username").length(3, 16).check(|s| {
+                match s.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    true => Ok(()),
+                    false => Err("only letters and digits".to_owned()),
+                }
+            }),
+        )
+        .field("age", NumberField::new("Age").range(0, 150))
+        .field(
+            "language",
+            ChoiceField::new("Favorite language", &["Rust", "C++", "Go"]),
+        )
+        .field("team", TextField::new("Team").optional())
+        .field(
+            "shell",
+            ChoiceField::new("Shell", &["bash", "zsh", "fish"]).optional(),
+        )
+        .field(
+            "threads",
+            NumberField::new("Threads").range(1, 64).default_value(4),
+        );
+
+    let answers = "Rust acean\nRustacean\nold\n28\nrust\n\n\n8\n";
+    let stdout = io::stdout();
+    if let Err(e) = form.prompt(answers.as_bytes(), stdout.lock()) {
+        println!("{}", e);
+    }
+    println!();
+    // Answers can also be set directly
+    if let Err(e) = form.set("shell", "tcsh") {
+        println!("{}", e);
+    }
+    form.set("team", "compiler").unwrap();
+    print!("{}", form);
+
+    match form.validate() {
+        Ok(()) => println!(
+            "{} ({}) likes {:?}, {:?} threads",
+            form.text("username").unwrap(),
+            form.number("age").unwrap(),
+            form.choice("language"),
+            form.number("threads")
+        ),
+        Err(errors) => {
+            for e in errors {
+                println!("{}", e);
+            }
+        }
+    }
+
+    // A widget implementing two typed traits has two `get`s, as `Form` had above
+    let mut level = ChoiceField::new("Level", &["junior", "senior"]);
+    if let Err(e) = NumberWidget::set(&mut level, 3) {
+        println!("level 3: {}", e);
+    }
+    NumberWidget::set(&mut level, 2).unwrap();
+    println!(
+        "level {:?} is {:?}",
+        <ChoiceField as NumberWidget>::get(&level),
+        <ChoiceField as ChoiceWidget>::get(&level)
+    );
+
+    // An incomplete answer sheet
+    let mut unfinished = Form::new().field("name", TextField::new("Name"));
+    if let Err(e) = unfinished.prompt("".as_bytes(), io::sink()) {
+        println!("{}", e);
+    }
+    if let Err(errors) = unfinished.validate() {
+        println!("{}", errors[0]);
+    }
 }
 
 fn traits_disambiguating_overlapping() {
@@ -375,9 +1056,10 @@ fn return_traits_with_dyn() {
         }
     }
 
-    let mut source = random::default().seed([0, 1]);
+    use rng::Rng;
+    let mut source = rng::Pcg32::new(0, 1);
 
-    let rand_number = source.read::<f64>();
+    let rand_number = source.gen_f64();
     println!("random number is {}", rand_number);
     let animal = random_animal(rand_number);
     println!(
@@ -0,0 +1,475 @@
+// Real merging for the `chain` + `cycle` of `combine_vecs`, which only concatenates
+// - `merge`/`merge_by` interleave k sorted streams into one sorted stream through a binary heap of
+//   the current head of every stream: O(log k) per item, and lazy, so the streams can be endless
+// - `round_robin` takes one item from each stream in turn
+// - `dedup_sorted` drops the repeats of a sorted stream
+// - `external_sort` sorts more lines than fit in memory: sorted runs of `chunk` lines are written
+//   to temporary files, then streamed back through `merge_by`. At most `MAX_FAN_IN` runs are open
+//   at once, more are first merged into longer runs, `MAX_FAN_IN` at a time
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+// A min-heap of `(head, source)` under `cmp`. Equal heads leave in source order, so the merge is
+// stable: of two equal items, the one from the earlier stream comes first
+pub struct KMergeBy<I: Iterator, F> {
+    sources: Vec<I>,
+    heap: Vec<(I::Item, usize)>,
+    cmp: F,
+}
+
+pub type KMerge<I> = KMergeBy<I, fn(&<I as Iterator>::Item, &<I as Iterator>::Item) -> Ordering>;
+
+pub fn merge<S>(streams: S) -> KMerge<<S::Item as IntoIterator>::IntoIter>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator,
+    <S::Item as IntoIterator>::Item: Ord,
+{
+    merge_by(streams, Ord::cmp)
+}
+
+pub fn merge_by<S, F>(streams: S, cmp: F) -> KMergeBy<<S::Item as IntoIterator>::IntoIter, F>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator,
+    F: FnMut(&<S::Item as IntoIterator>::Item, &<S::Item as IntoIterator>::Item) -> Ordering,
+{
+    let mut merge = KMergeBy {
+        sources: streams.into_iter().map(IntoIterator::into_iter).collect(),
+        heap: Vec::new(),
+        cmp,
+    };
+    for source in 0..merge.sources.len() {
+        merge.refill(source);
+    }
+    merge
+}
+
+impl<I: Iterator, F: FnMut(&I::Item, &I::Item) -> Ordering> KMergeBy<I, F> {
+    fn less(&mut self, a: usize, b: usize) -> bool {
+        let ((x, i), (y, j)) = (&self.heap[a], &self.heap[b]);
+        (self.cmp)(x, y).then(i.cmp(j)) == Ordering::Less
+    }
+
+    fn refill(&mut self, source: usize) {
+        if let Some(item) = self.sources[source].next() {
+            self.heap.push((item, source));
+            self.sift_up(self.heap.len() - 1);
+        }
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !self.less(i, parent) {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut smallest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len() && self.less(child, smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == i {
+                break;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+impl<I: Iterator, F: FnMut(&I::Item, &I::Item) -> Ordering> Iterator for KMergeBy<I, F> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        let last = self.heap.len() - 1;
+        self.heap.swap(0, last);
+        let (item, source) = self.heap.pop().expect("the heap isn't empty");
+        self.sift_down(0);
+        self.refill(source);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.sources
+            .iter()
+            .fold((self.heap.len(), Some(self.heap.len())), |(lo, hi), s| {
+                let (s_lo, s_hi) = s.size_hint();
+                (
+                    lo.saturating_add(s_lo),
+                    hi.and_then(|hi| s_hi.and_then(|s_hi| hi.checked_add(s_hi))),
+                )
+            })
+    }
+}
+
+// One item of every stream in turn, skipping the streams which have ended
+pub struct RoundRobin<I> {
+    streams: VecDeque<I>,
+}
+
+pub fn round_robin<S>(streams: S) -> RoundRobin<<S::Item as IntoIterator>::IntoIter>
+where
+    S: IntoIterator,
+    S::Item: IntoIterator,
+{
+    RoundRobin {
+        streams: streams.into_iter().map(IntoIterator::into_iter).collect(),
+    }
+}
+
+impl<I: Iterator> Iterator for RoundRobin<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        while let Some(mut stream) = self.streams.pop_front() {
+            if let Some(item) = stream.next() {
+                self.streams.push_back(stream);
+                return Some(item);
+            }
+        }
+        None
+    }
+}
+
+// Only the repeats next to each other are dropped, which is all of them in a sorted stream
+pub struct Dedup<I: Iterator> {
+    inner: Peekable<I>,
+}
+
+pub fn dedup_sorted<I>(stream: I) -> Dedup<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: PartialEq,
+{
+    Dedup {
+        inner: stream.into_iter().peekable(),
+    }
+}
+
+impl<I: Iterator> Iterator for Dedup<I>
+where
+    I::Item: PartialEq,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.inner.next()?;
+        while self.inner.next_if_eq(&item).is_some() {}
+        Some(item)
+    }
+}
+
+// The lines of one sorted run file
+struct Run {
+    lines: io::Lines<BufReader<File>>,
+}
+
+impl Iterator for Run {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        self.lines.next()
+    }
+}
+
+// Errors sort first, so a failed read comes out of the merge right away
+fn errors_first(a: &io::Result<String>, b: &io::Result<String>) -> Ordering {
+    match (a, b) {
+        (Ok(a), Ok(b)) => a.cmp(b),
+        (Err(_), Ok(_)) => Ordering::Less,
+        (Ok(_), Err(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => Ordering::Equal,
+    }
+}
+
+type MergedRuns = KMergeBy<Run, fn(&io::Result<String>, &io::Result<String>) -> Ordering>;
+
+// The sorted lines, read back from the run files, which are removed when this is dropped
+pub struct ExternalSort {
+    files: Vec<PathBuf>,
+    merged: MergedRuns,
+}
+
+impl ExternalSort {
+    pub fn runs(&self) -> &[PathBuf] {
+        &self.files
+    }
+}
+
+impl Iterator for ExternalSort {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<io::Result<String>> {
+        self.merged.next()
+    }
+}
+
+impl Drop for ExternalSort {
+    fn drop(&mut self) {
+        for file in &self.files {
+            // a run which can't be removed is left behind, a `drop` has nowhere to report it
+            let _ = fs::remove_file(file);
+        }
+    }
+}
+
+// Distinguishes the run files of the sorts of one process
+static SORTS: AtomicUsize = AtomicUsize::new(0);
+
+// Run files open at once, well below the usual limit of 1024 file descriptors
+const MAX_FAN_IN: usize = 64;
+
+fn write_run(path: &Path, lines: &mut Vec<String>) -> io::Result<()> {
+    lines.sort_unstable();
+    let mut out = BufWriter::new(File::create(path)?);
+    for line in lines.drain(..) {
+        writeln!(out, "{}", line)?;
+    }
+    out.flush()
+}
+
+fn open_runs(paths: &[PathBuf]) -> io::Result<MergedRuns> {
+    let runs = paths
+        .iter()
+        .map(|path| {
+            Ok(Run {
+                lines: BufReader::new(File::open(path)?).lines(),
+            })
+        })
+        .collect::<io::Result<Vec<Run>>>()?;
+    Ok(merge_by(runs, errors_first as fn(&_, &_) -> Ordering))
+}
+
+fn merge_runs(paths: &[PathBuf], path: &Path) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for line in open_runs(paths)? {
+        writeln!(out, "{}", line?)?;
+    }
+    out.flush()
+}
+
+// A line is read back up to its '\n', and `lines` also drops a '\r' before it
+fn check_line(line: &str) -> io::Result<()> {
+    if line.contains('\n') || line.ends_with('\r') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{:?} can't be stored as one line", line),
+        ));
+    }
+    Ok(())
+}
+
+// At most `chunk` lines are held in memory at once. A line containing '\n' or ending in '\r' fails
+// the sort with `InvalidInput`
+pub fn external_sort<I>(lines: I, chunk: usize, dir: &Path) -> io::Result<ExternalSort>
+where
+    I: IntoIterator<Item = String>,
+{
+    external_sort_with_fan_in(lines, chunk, MAX_FAN_IN, dir)
+}
+
+fn external_sort_with_fan_in<I>(
+    lines: I,
+    chunk: usize,
+    fan_in: usize,
+    dir: &Path,
+) -> io::Result<ExternalSort>
+where
+    I: IntoIterator<Item = String>,
+{
+    assert!(chunk > 0, "runs need at least one line");
+    assert!(fan_in > 1, "merging needs at least two runs at a time");
+    let sort = SORTS.fetch_add(1, AtomicOrdering::Relaxed);
+    // every run file made, the merged ones are removed along the way
+    let mut created = Vec::new();
+    let new_run = |created: &mut Vec<PathBuf>| {
+        let path = dir.join(format!(
+            "run-{}-{}-{}.txt",
+            process::id(),
+            sort,
+            created.len()
+        ));
+        created.push(path.clone());
+        path
+    };
+    let mut buffer = Vec::with_capacity(chunk);
+    let mut lines = lines.into_iter().peekable();
+
+    let written = (|| {
+        let mut files = Vec::new();
+        while lines.peek().is_some() {
+            buffer.extend(lines.by_ref().take(chunk));
+            buffer.iter().try_for_each(|line| check_line(line))?;
+            let path = new_run(&mut created);
+            write_run(&path, &mut buffer)?;
+            files.push(path);
+        }
+        while files.len() > fan_in {
+            let mut merged = Vec::new();
+            for group in files.chunks(fan_in) {
+                let path = new_run(&mut created);
+                merge_runs(group, &path)?;
+                for file in group {
+                    fs::remove_file(file)?;
+                }
+                merged.push(path);
+            }
+            files = merged;
+        }
+        let merged = open_runs(&files)?;
+        Ok((files, merged))
+    })();
+
+    match written {
+        Ok((files, merged)) => Ok(ExternalSort { files, merged }),
+        Err(e) => {
+            for file in &created {
+                let _ = fs::remove_file(file);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::{Pcg32, Rng};
+    use std::env;
+
+    #[test]
+    fn k_way_merge() {
+        let merged: Vec<i32> =
+            merge(vec![vec![1, 4, 7], vec![2, 5, 8, 9], vec![], vec![3, 6]]).collect();
+        assert_eq!(merged, (1..=9).collect::<Vec<_>>());
+        assert_eq!(merge(vec![vec![1, 2], vec![3]]).size_hint(), (3, Some(3)));
+
+        // lazy: endless streams merge fine
+        let evens = (0..).step_by(2);
+        let odds = (1..).step_by(2);
+        let first: Vec<u32> = merge(vec![evens, odds]).take(6).collect();
+        assert_eq!(first, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn merge_by_key_is_stable() {
+        let web = vec![(1, "web: start"), (3, "web: request"), (5, "web: stop")];
+        let db = vec![(1, "db: start"), (3, "db: query")];
+        let merged: Vec<&str> = merge_by(vec![web, db], |a, b| a.0.cmp(&b.0))
+            .map(|(_, line)| line)
+            .collect();
+        assert_eq!(
+            merged,
+            [
+                "web: start",
+                "db: start",
+                "web: request",
+                "db: query",
+                "web: stop"
+            ]
+        );
+
+        let descending: Vec<u8> =
+            merge_by(vec![vec![9, 3], vec![8, 4, 1]], |a, b| b.cmp(a)).collect();
+        assert_eq!(descending, [9, 8, 4, 3, 1]);
+    }
+
+    #[test]
+    fn round_robin_and_dedup() {
+        let turns: Vec<char> =
+            round_robin(vec!["ab".chars(), "".chars(), "cdef".chars(), "g".chars()]).collect();
+        assert_eq!(turns, ['a', 'c', 'g', 'b', 'd', 'e', 'f']);
+
+        let unique: Vec<u8> =
+            dedup_sorted(merge(vec![vec![1, 2, 2, 5], vec![2, 3, 5, 5]])).collect();
+        assert_eq!(unique, [1, 2, 3, 5]);
+        assert_eq!(dedup_sorted(Vec::<u8>::new()).next(), None);
+    }
+
+    #[test]
+    fn external_sort_round_trip() {
+        let mut lines: Vec<String> = (0..250).map(|i| format!("line {:03}", i % 100)).collect();
+        Pcg32::new(5, 0).shuffle(&mut lines);
+
+        let dir = env::temp_dir();
+        let sorted = external_sort(lines.clone(), 32, &dir).unwrap();
+        let runs = sorted.runs().to_vec();
+        assert_eq!(runs.len(), 8);
+        assert!(runs.iter().all(|run| run.exists()));
+
+        let got: Vec<String> = sorted.collect::<io::Result<_>>().unwrap();
+        lines.sort();
+        assert_eq!(got, lines);
+        // the iterator was consumed and dropped by `collect`
+        assert!(runs.iter().all(|run| !run.exists()));
+
+        let empty = external_sort(Vec::new(), 4, &dir).unwrap();
+        assert!(empty.runs().is_empty());
+        assert_eq!(empty.count(), 0);
+    }
+
+    #[test]
+    fn external_sort_merges_in_passes() {
+        let mut lines: Vec<String> = (0..500).map(|i| format!("{:04}", i * 7 % 500)).collect();
+        Pcg32::new(6, 0).shuffle(&mut lines);
+
+        // 50 runs, merged 4 at a time: 13, then 4 runs
+        let dir = env::temp_dir();
+        let sorted = external_sort_with_fan_in(lines.clone(), 10, 4, &dir).unwrap();
+        let runs = sorted.runs().to_vec();
+        assert_eq!(runs.len(), 4);
+        assert!(runs.iter().all(|run| run.exists()));
+
+        let got: Vec<String> = sorted.collect::<io::Result<_>>().unwrap();
+        lines.sort();
+        assert_eq!(got, lines);
+        assert!(runs.iter().all(|run| !run.exists()));
+    }
+
+    #[test]
+    fn external_sort_refuses_lines_it_cant_store() {
+        let dir = env::temp_dir();
+        for bad in ["two\nlines", "windows\r"] {
+            let lines = vec!["a".to_owned(), bad.to_owned()];
+            let result = external_sort(lines, 1, &dir);
+            assert_eq!(
+                result.err().map(|e| e.kind()),
+                Some(io::ErrorKind::InvalidInput)
+            );
+        }
+        // a '\r' inside a line is kept
+        let got: Vec<String> = external_sort(vec!["a\rb".to_owned()], 1, &dir)
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(got, ["a\rb"]);
+    }
+
+    #[test]
+    fn external_sort_reports_io_errors() {
+        let missing = env::temp_dir().join("no-such-dir-for-external-sort");
+        let result = external_sort(vec![String::from("a")], 1, &missing);
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+}
@@ -0,0 +1,494 @@
+// Numeric newtypes without the hand-written boilerplate. `traits_derive` derives what `Inches`
+// and `Centimeters` need and leaves `Seconds` without `Debug` or `PartialEq`; `newtype!` declares
+// the wrapper and opts into the rest, trait by trait
+//
+// newtype! {
+//     pub struct Inches(f64) unit "in";
+//     with add, sub, neg, scale, sum, ord;
+//     convert Centimeters = 2.54, Feet = 1.0 / 12.0;
+// }
+//
+// Always: `Debug`, `Clone`, `Copy`, `Default`, `Display` with the unit ("12 in", precision and
+// width apply to the number), `From` the inner type and back (so `Into` both ways), `new`,
+// `value`, `to::<Sibling>()` and the checked `try_to::<Sibling>()`
+// `with`, each optional:
+// - `add`, `sub`: `+`, `-`, `+=`, `-=` between two values of the unit
+// - `neg`: unary `-`
+// - `scale`: `*` and `/` by the inner type, and unit / unit, which is a plain ratio
+// - `sum`: `Iterator::sum`
+// - `eq` (`PartialEq`), `ord` (`PartialEq` + `PartialOrd`) or `total` (`Eq` + `Ord` + `Hash` and
+//   the partial ones, integers only): one of them at most, each includes the one before
+// `convert Sibling = ratio`: `From` both ways, `1 self = ratio sibling`. Between integer units
+// with an integer ratio the conversion is exact integer arithmetic, otherwise it goes through
+// `f64`. An integer result is truncated toward zero; one which doesn't fit makes `try_to` return
+// `None` and `From` panic, like an integer overflow. Declare each pair on one side only, the
+// other direction comes with it
+use std::convert::TryFrom;
+use std::fmt::Display;
+
+// What a unit can wrap, the primitive numbers
+pub trait UnitNumber: Copy + Display {
+    const INTEGER: bool;
+
+    // `None` for floats, and integers beyond `i128`
+    fn to_i128(self) -> Option<i128>;
+    fn from_i128(value: i128) -> Option<Self>;
+    fn to_f64(self) -> f64;
+    // Truncated toward zero, `None` when out of range
+    fn from_f64(value: f64) -> Option<Self>;
+}
+
+macro_rules! unit_integers {
+    ($($t:ty),+) => {$(
+        impl UnitNumber for $t {
+            const INTEGER: bool = true;
+
+            fn to_i128(self) -> Option<i128> {
+                i128::try_from(self).ok()
+            }
+
+            fn from_i128(value: i128) -> Option<$t> {
+                <$t>::try_from(value).ok()
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            // `MIN` is 0 or a power of two and `MAX + 1` one, both exact in `f64`
+            fn from_f64(value: f64) -> Option<$t> {
+                let value = value.trunc();
+                if value >= <$t>::MIN as f64 && value < <$t>::MAX as f64 + 1.0 {
+                    Some(value as $t)
+                } else {
+                    None
+                }
+            }
+        }
+    )+};
+}
+
+macro_rules! unit_floats {
+    ($($t:ty),+) => {$(
+        impl UnitNumber for $t {
+            const INTEGER: bool = false;
+
+            fn to_i128(self) -> Option<i128> {
+                None
+            }
+
+            fn from_i128(value: i128) -> Option<$t> {
+                Some(value as $t)
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn from_f64(value: f64) -> Option<$t> {
+                Some(value as $t)
+            }
+        }
+    )+};
+}
+
+unit_integers!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+unit_floats!(f32, f64);
+
+// `value * ratio`, or `value / ratio` when not `multiply`
+pub fn convert_value<A: UnitNumber, B: UnitNumber>(
+    value: A,
+    ratio: f64,
+    multiply: bool,
+) -> Option<B> {
+    let integer_ratio = Some(ratio as i128).filter(|&r| r as f64 == ratio && r != 0);
+    if let (true, Some(v), Some(r)) = (B::INTEGER, value.to_i128(), integer_ratio) {
+        let converted = if multiply { v.checked_mul(r)? } else { v / r };
+        return B::from_i128(converted);
+    }
+    let v = value.to_f64();
+    B::from_f64(if multiply { v * ratio } else { v / ratio })
+}
+
+// A conversion which may not fit, behind `try_to`
+pub trait ConvertUnit<T>: Sized {
+    fn convert_unit(value: T) -> Option<Self>;
+}
+
+macro_rules! newtype {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($inner:ty) unit $unit:literal;
+        $(with $($opt:ident),+ ;)?
+        $(convert $($sibling:ident = $ratio:expr),+ ;)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, Default)]
+        $vis struct $name(pub $inner);
+
+        impl $name {
+            #[allow(dead_code)]
+            pub const UNIT: &'static str = $unit;
+
+            #[allow(dead_code)]
+            $vis const fn new(value: $inner) -> $name {
+                $name(value)
+            }
+
+            #[allow(dead_code)]
+            $vis const fn value(self) -> $inner {
+                self.0
+            }
+
+            // Into any unit with a conversion from this one
+            #[allow(dead_code)]
+            $vis fn to<T: From<$name>>(self) -> T {
+                T::from(self)
+            }
+
+            // `None` when the value doesn't fit in the other unit
+            #[allow(dead_code)]
+            $vis fn try_to<T: $crate::newtype::ConvertUnit<$name>>(self) -> Option<T> {
+                T::convert_unit(self)
+            }
+        }
+
+        impl std::fmt::Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                std::fmt::Display::fmt(&self.0, f)?;
+                write!(f, " {}", $unit)
+            }
+        }
+
+        impl From<$inner> for $name {
+            fn from(value: $inner) -> $name {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> $inner {
+                value.0
+            }
+        }
+
+        $($(newtype!(@with $opt $name $inner);)+)?
+        $($(newtype!(@convert $name $sibling ($ratio));)+)?
+    };
+
+    (@with add $name:ident $inner:ty) => {
+        impl std::ops::Add for $name {
+            type Output = $name;
+
+            fn add(self, rhs: $name) -> $name {
+                $name(self.0 + rhs.0)
+            }
+        }
+
+        impl std::ops::AddAssign for $name {
+            fn add_assign(&mut self, rhs: $name) {
+                self.0 += rhs.0;
+            }
+        }
+    };
+
+    (@with sub $name:ident $inner:ty) => {
+        impl std::ops::Sub for $name {
+            type Output = $name;
+
+            fn sub(self, rhs: $name) -> $name {
+                $name(self.0 - rhs.0)
+            }
+        }
+
+        impl std::ops::SubAssign for $name {
+            fn sub_assign(&mut self, rhs: $name) {
+                self.0 -= rhs.0;
+            }
+        }
+    };
+
+    (@with neg $name:ident $inner:ty) => {
+        impl std::ops::Neg for $name {
+            type Output = $name;
+
+            fn neg(self) -> $name {
+                $name(-self.0)
+            }
+        }
+    };
+
+    (@with scale $name:ident $inner:ty) => {
+        impl std::ops::Mul<$inner> for $name {
+            type Output = $name;
+
+            fn mul(self, k: $inner) -> $name {
+                $name(self.0 * k)
+            }
+        }
+
+        impl std::ops::Mul<$name> for $inner {
+            type Output = $name;
+
+            fn mul(self, value: $name) -> $name {
+                $name(self * value.0)
+            }
+        }
+
+        impl std::ops::Div<$inner> for $name {
+            type Output = $name;
+
+            fn div(self, k: $inner) -> $name {
+                $name(self.0 / k)
+            }
+        }
+
+        impl std::ops::Div for $name {
+            type Output = $inner;
+
+            fn div(self, rhs: $name) -> $inner {
+                self.0 / rhs.0
+            }
+        }
+
+        impl std::ops::MulAssign<$inner> for $name {
+            fn mul_assign(&mut self, k: $inner) {
+                self.0 *= k;
+            }
+        }
+
+        impl std::ops::DivAssign<$inner> for $name {
+            fn div_assign(&mut self, k: $inner) {
+                self.0 /= k;
+            }
+        }
+    };
+
+    (@with sum $name:ident $inner:ty) => {
+        impl std::iter::Sum for $name {
+            fn sum<I: Iterator<Item = $name>>(iter: I) -> $name {
+                $name(iter.map(|v| v.0).sum())
+            }
+        }
+    };
+
+    (@with eq $name:ident $inner:ty) => {
+        impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                self.0 == other.0
+            }
+        }
+    };
+
+    (@with ord $name:ident $inner:ty) => {
+        newtype!(@with eq $name $inner);
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &$name) -> Option<std::cmp::Ordering> {
+                self.0.partial_cmp(&other.0)
+            }
+        }
+    };
+
+    (@with total $name:ident $inner:ty) => {
+        newtype!(@with eq $name $inner);
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &$name) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for $name {
+            fn cmp(&self, other: &$name) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl std::hash::Hash for $name {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.0.hash(state);
+            }
+        }
+    };
+
+    (@convert $name:ident $sibling:ident ($ratio:expr)) => {
+        // `as f64` is a no-op for a float ratio
+        #[allow(clippy::unnecessary_cast)]
+        impl $crate::newtype::ConvertUnit<$name> for $sibling {
+            fn convert_unit(value: $name) -> Option<$sibling> {
+                $crate::newtype::convert_value(value.0, ($ratio) as f64, true).map($sibling)
+            }
+        }
+
+        #[allow(clippy::unnecessary_cast)]
+        impl $crate::newtype::ConvertUnit<$sibling> for $name {
+            fn convert_unit(value: $sibling) -> Option<$name> {
+                $crate::newtype::convert_value(value.0, ($ratio) as f64, false).map($name)
+            }
+        }
+
+        newtype!(@from $name $sibling);
+        newtype!(@from $sibling $name);
+    };
+
+    (@from $from:ident $to:ident) => {
+        impl From<$from> for $to {
+            fn from(value: $from) -> $to {
+                $crate::newtype::ConvertUnit::convert_unit(value).unwrap_or_else(|| {
+                    panic!("{} doesn't fit in {}", value, <$to>::UNIT)
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use newtype;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    newtype! {
+        pub struct Centimeters(f64) unit "cm";
+        with add, sub, neg, scale, sum, ord;
+    }
+
+    newtype! {
+        pub struct Feet(f64) unit "ft";
+        with ord;
+    }
+
+    newtype! {
+        // Conversions out of here, the reverse ones come with them
+        pub struct Inches(f64) unit "in";
+        with add, scale, ord;
+        convert Centimeters = 2.54, Feet = 1.0 / 12.0;
+    }
+
+    newtype! {
+        #[must_use]
+        pub struct Seconds(i64) unit "s";
+        with add, sub, neg, scale, sum, total;
+    }
+
+    newtype! {
+        pub struct Minutes(i64) unit "min";
+        with total;
+        convert Seconds = 60;
+    }
+
+    newtype! {
+        // Nothing opted into
+        pub struct Grams(u32) unit "g";
+    }
+
+    #[test]
+    fn arithmetic() {
+        let a = Centimeters(10.0);
+        let b = Centimeters::new(2.5);
+        assert_eq!(a + b, Centimeters(12.5));
+        assert_eq!(a - b, Centimeters(7.5));
+        assert_eq!(-a, Centimeters(-10.0));
+        assert_eq!(a * 3.0, 3.0 * a);
+        assert_eq!(a / 4.0, b);
+        assert_eq!(a / b, 4.0);
+
+        let mut t = Seconds(30);
+        t += Seconds(45);
+        t -= Seconds(15);
+        t *= 2;
+        t /= 3;
+        assert_eq!(t, Seconds(40));
+        assert_eq!(
+            vec![Seconds(1), Seconds(2)].into_iter().sum::<Seconds>(),
+            Seconds(3)
+        );
+    }
+
+    #[test]
+    fn ordering_and_hashing() {
+        assert!(Feet(1.0) < Feet(3.0));
+        assert_eq!(Feet(f64::NAN).partial_cmp(&Feet(1.0)), None);
+
+        let mut times = vec![Seconds(30), Seconds(-5), Seconds(12)];
+        times.sort();
+        assert_eq!(times, [Seconds(-5), Seconds(12), Seconds(30)]);
+        assert_eq!(times.iter().max(), Some(&Seconds(30)));
+        let unique: HashSet<Seconds> = vec![Seconds(1), Seconds(1), Seconds(2)]
+            .into_iter()
+            .collect();
+        assert_eq!(unique.len(), 2);
+    }
+
+    #[test]
+    fn display_and_inner_conversions() {
+        assert_eq!(Inches(12.0).to_string(), "12 in");
+        assert_eq!(format!("{:.2}", Centimeters(30.48)), "30.48 cm");
+        assert_eq!(format!("{:>5}", Seconds(7)), "    7 s");
+        assert_eq!(Grams::UNIT, "g");
+
+        let g: Grams = 500.into();
+        let raw: u32 = g.into();
+        assert_eq!((raw, g.value(), Grams::default().0), (500, 500, 0));
+    }
+
+    #[test]
+    fn sibling_conversions() {
+        let foot = Inches(12.0);
+        assert!((foot.to::<Centimeters>() - Centimeters(30.48)).0.abs() < 1e-9);
+        assert_eq!(foot.to::<Feet>(), Feet(1.0));
+        assert_eq!(Inches::from(Feet(2.0)), Inches(24.0));
+        assert_eq!(Inches::from(Centimeters(25.4)), Inches(10.0));
+
+        assert_eq!(Minutes(3).to::<Seconds>(), Seconds(180));
+        // integer units truncate
+        assert_eq!(Minutes::from(Seconds(150)), Minutes(2));
+        assert_eq!(Minutes::from(Seconds(-150)), Minutes(-2));
+        assert_eq!(Inches(2.0).try_to::<Feet>(), Some(Feet(2.0 / 12.0)));
+    }
+
+    newtype! {
+        pub struct Nanoseconds(u64) unit "ns";
+        with total;
+    }
+
+    newtype! {
+        pub struct Secs(u64) unit "s";
+        with total;
+        convert Nanoseconds = 1_000_000_000, Millis = 1_000.0;
+    }
+
+    newtype! {
+        pub struct Millis(u32) unit "ms";
+        with total;
+    }
+
+    #[test]
+    fn integer_conversions_are_exact_and_checked() {
+        // the last second which fits, in `f64` the nanoseconds would be rounded
+        let last = Secs(u64::MAX / 1_000_000_000);
+        assert_eq!(
+            last.to::<Nanoseconds>(),
+            Nanoseconds(18_446_744_073_000_000_000)
+        );
+        assert_eq!(Secs::from(Nanoseconds(u64::MAX)), last);
+        assert_eq!(Secs(last.0 + 1).try_to::<Nanoseconds>(), None);
+
+        // into a narrower inner type, still checked
+        assert_eq!(
+            Secs(4_294_967).try_to::<Millis>(),
+            Some(Millis(4_294_967_000))
+        );
+        assert_eq!(Secs(4_294_968).try_to::<Millis>(), None);
+        assert_eq!(Millis(2_500).try_to::<Secs>(), Some(Secs(2)));
+    }
+
+    #[test]
+    #[should_panic(expected = "18446744074 s doesn't fit in ns")]
+    fn overflowing_conversions_panic() {
+        let _ = Nanoseconds::from(Secs(18_446_744_074));
+    }
+}
@@ -0,0 +1,498 @@
+// A directory of profiles on the supertrait chain of `traits_supertraits`
+// The registry only knows `Box<dyn Person>`, so the roles are queried through `Person` itself:
+// `as_student`, `as_programmer` and `as_cs_student` default to `None` and each type implementing
+// a sub-trait overrides its own, returning `Some(self)`. That is the upcast a `dyn Person` can't
+// do on its own, a trait object doesn't know which other traits its type implements
+// Profiles are stored one per line as `key=value` fields separated by `;`:
+// name=Bill;university=Hust;language=Rust;git=bill
+// and the concrete type is picked from the fields present
+use std::fmt;
+
+pub trait Person {
+    fn name(&self) -> String;
+
+    fn as_student(&self) -> Option<&dyn Student> {
+        None
+    }
+
+    fn as_programmer(&self) -> Option<&dyn Programmer> {
+        None
+    }
+
+    fn as_cs_student(&self) -> Option<&dyn CsStudent> {
+        None
+    }
+}
+
+pub trait Student: Person {
+    fn university(&self) -> String;
+}
+
+pub trait Programmer: Person {
+    fn fav_language(&self) -> String;
+}
+
+pub trait CsStudent: Programmer + Student {
+    fn git_username(&self) -> String;
+}
+
+pub fn comp_sci_student_greeting(student: &dyn CsStudent) -> String {
+    format!(
+        "My name is {}, graduated from {}, favorite PL is {}, git account is {}",
+        student.name(),
+        student.university(),
+        student.fav_language(),
+        student.git_username()
+    )
+}
+
+// Neither a student nor a programmer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Staff {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Undergrad {
+    pub name: String,
+    pub university: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Developer {
+    pub name: String,
+    pub fav_language: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cs {
+    pub name: String,
+    pub university: String,
+    pub fav_language: String,
+    pub git_username: String,
+}
+
+impl Person for Staff {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Person for Undergrad {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn as_student(&self) -> Option<&dyn Student> {
+        Some(self)
+    }
+}
+
+impl Student for Undergrad {
+    fn university(&self) -> String {
+        self.university.clone()
+    }
+}
+
+impl Person for Developer {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn as_programmer(&self) -> Option<&dyn Programmer> {
+        Some(self)
+    }
+}
+
+impl Programmer for Developer {
+    fn fav_language(&self) -> String {
+        self.fav_language.clone()
+    }
+}
+
+impl Person for Cs {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn as_student(&self) -> Option<&dyn Student> {
+        Some(self)
+    }
+
+    fn as_programmer(&self) -> Option<&dyn Programmer> {
+        Some(self)
+    }
+
+    fn as_cs_student(&self) -> Option<&dyn CsStudent> {
+        Some(self)
+    }
+}
+
+impl Student for Cs {
+    fn university(&self) -> String {
+        self.university.clone()
+    }
+}
+
+impl Programmer for Cs {
+    fn fav_language(&self) -> String {
+        self.fav_language.clone()
+    }
+}
+
+impl CsStudent for Cs {
+    fn git_username(&self) -> String {
+        self.git_username.clone()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    // 1-based
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+// `\`, `;`, `=`, newlines, tabs and carriage returns are escaped, and so is whitespace at either
+// end, which `from_lines` would trim: any name survives a round trip
+fn escape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for (i, c) in value.char_indices() {
+        let at_edge = i == 0 || i + c.len_utf8() == value.len();
+        match c {
+            '\\' | ';' | '=' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ' ' if at_edge => out.push_str("\\s"),
+            c if at_edge && c.is_whitespace() => {
+                out.push_str(&format!("\\u{{{:x}}}", c as u32));
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+// The `\u{..}` escape, after the `u`
+fn unescape_code_point(chars: &mut std::str::Chars) -> Result<char, String> {
+    if chars.next() != Some('{') {
+        return Err("\\u without {".to_owned());
+    }
+    let hex: String = chars.by_ref().take_while(|&c| c != '}').collect();
+    u32::from_str_radix(&hex, 16)
+        .ok()
+        .and_then(char::from_u32)
+        .ok_or_else(|| format!("bad escape \\u{{{}}}", hex))
+}
+
+// Splits on the unescaped `separator` and unescapes every part
+fn split_unescaped(s: &str, separator: char) -> Result<Vec<String>, String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => parts.last_mut().unwrap().push('\n'),
+                Some('r') => parts.last_mut().unwrap().push('\r'),
+                Some('t') => parts.last_mut().unwrap().push('\t'),
+                Some('s') => parts.last_mut().unwrap().push(' '),
+                Some('u') => parts
+                    .last_mut()
+                    .unwrap()
+                    .push(unescape_code_point(&mut chars)?),
+                Some(c @ ('\\' | ';' | '=')) => parts.last_mut().unwrap().push(c),
+                Some(c) => return Err(format!("unknown escape \\{}", c)),
+                None => return Err("dangling \\ at the end".to_owned()),
+            },
+            c if c == separator => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+    Ok(parts)
+}
+
+#[derive(Default)]
+pub struct Registry {
+    people: Vec<Box<dyn Person>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    pub fn add<P: Person + 'static>(&mut self, person: P) {
+        self.people.push(Box::new(person));
+    }
+
+    pub fn len(&self) -> usize {
+        self.people.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.people.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Person> {
+        self.people.iter().map(|p| p.as_ref())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&dyn Person> {
+        self.iter().find(|p| p.name() == name)
+    }
+
+    pub fn students(&self) -> impl Iterator<Item = &dyn Student> {
+        self.iter().filter_map(|p| p.as_student())
+    }
+
+    pub fn programmers(&self) -> impl Iterator<Item = &dyn Programmer> {
+        self.iter().filter_map(|p| p.as_programmer())
+    }
+
+    pub fn cs_students(&self) -> impl Iterator<Item = &dyn CsStudent> {
+        self.iter().filter_map(|p| p.as_cs_student())
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{} people: {} students, {} programmers, {} CS students\n",
+            self.len(),
+            self.students().count(),
+            self.programmers().count(),
+            self.cs_students().count()
+        );
+        for person in self.iter() {
+            let mut roles = Vec::new();
+            if let Some(s) = person.as_student() {
+                roles.push(format!("studies at {}", s.university()));
+            }
+            if let Some(p) = person.as_programmer() {
+                roles.push(format!("writes {}", p.fav_language()));
+            }
+            if let Some(cs) = person.as_cs_student() {
+                roles.push(format!("git {}", cs.git_username()));
+            }
+            if roles.is_empty() {
+                roles.push("no roles".to_owned());
+            }
+            report.push_str(&format!("- {}: {}\n", person.name(), roles.join(", ")));
+        }
+        report
+    }
+
+    pub fn to_lines(&self) -> String {
+        let mut out = String::new();
+        for person in self.iter() {
+            let mut fields = vec![format!("name={}", escape(&person.name()))];
+            if let Some(s) = person.as_student() {
+                fields.push(format!("university={}", escape(&s.university())));
+            }
+            if let Some(p) = person.as_programmer() {
+                fields.push(format!("language={}", escape(&p.fav_language())));
+            }
+            if let Some(cs) = person.as_cs_student() {
+                fields.push(format!("git={}", escape(&cs.git_username())));
+            }
+            out.push_str(&fields.join(";"));
+            out.push('\n');
+        }
+        out
+    }
+
+    // Blank lines and `#` comments are skipped
+    pub fn from_lines(input: &str) -> Result<Registry, ParseError> {
+        let mut registry = Registry::new();
+        for (n, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |reason: String| ParseError {
+                line: n + 1,
+                reason,
+            };
+
+            let (mut name, mut university, mut language, mut git) = (None, None, None, None);
+            for field in split_fields(line).map_err(error)? {
+                let (key, value) = field;
+                let slot = match key.as_str() {
+                    "name" => &mut name,
+                    "university" => &mut university,
+                    "language" => &mut language,
+                    "git" => &mut git,
+                    _ => return Err(error(format!("unknown field {:?}", key))),
+                };
+                if slot.replace(value).is_some() {
+                    return Err(error(format!("{:?} is given twice", key)));
+                }
+            }
+
+            let name = name.ok_or_else(|| error("missing name".to_owned()))?;
+            match (university, language, git) {
+                (None, None, None) => registry.add(Staff { name }),
+                (Some(university), None, None) => registry.add(Undergrad { name, university }),
+                (None, Some(fav_language), None) => registry.add(Developer { name, fav_language }),
+                (Some(university), Some(fav_language), Some(git_username)) => registry.add(Cs {
+                    name,
+                    university,
+                    fav_language,
+                    git_username,
+                }),
+                _ => {
+                    return Err(error(
+                        "a git account needs a university and a language, and vice versa"
+                            .to_owned(),
+                    ))
+                }
+            }
+        }
+        Ok(registry)
+    }
+}
+
+// `key=value;key=value` into unescaped pairs
+fn split_fields(line: &str) -> Result<Vec<(String, String)>, String> {
+    // split on `;` first, keeping the escapes, then on `=`
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let bytes = line.as_bytes();
+    for i in 0..=bytes.len() {
+        if i < bytes.len() && escaped {
+            escaped = false;
+            continue;
+        }
+        if i == bytes.len() || bytes[i] == b';' {
+            let raw = &line[start..i];
+            let mut parts = split_unescaped(raw, '=')?;
+            if parts.len() != 2 {
+                return Err(format!("{:?} is not key=value", raw));
+            }
+            let value = parts.pop().unwrap();
+            let key = parts.pop().unwrap();
+            fields.push((key, value));
+            start = i + 1;
+        } else if bytes[i] == b'\\' {
+            escaped = true;
+        }
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registry() -> Registry {
+        let mut r = Registry::new();
+        r.add(Staff {
+            name: "Ada".to_owned(),
+        });
+        r.add(Undergrad {
+            name: "Bo".to_owned(),
+            university: "MIT".to_owned(),
+        });
+        r.add(Developer {
+            name: "Cy".to_owned(),
+            fav_language: "Go".to_owned(),
+        });
+        r.add(Cs {
+            name: "Bill".to_owned(),
+            university: "Hust".to_owned(),
+            fav_language: "Rust".to_owned(),
+            git_username: "bill".to_owned(),
+        });
+        r
+    }
+
+    #[test]
+    fn role_queries() {
+        let r = registry();
+        let students: Vec<String> = r.students().map(|s| s.name()).collect();
+        assert_eq!(students, ["Bo", "Bill"]);
+        let languages: Vec<String> = r.programmers().map(|p| p.fav_language()).collect();
+        assert_eq!(languages, ["Go", "Rust"]);
+        assert_eq!(r.cs_students().count(), 1);
+
+        assert!(r.find("Ada").unwrap().as_student().is_none());
+        let bill = r.find("Bill").unwrap().as_cs_student().unwrap();
+        assert_eq!(
+            comp_sci_student_greeting(bill),
+            "My name is Bill, graduated from Hust, favorite PL is Rust, git account is bill"
+        );
+    }
+
+    #[test]
+    fn report_lists_roles() {
+        assert_eq!(
+            registry().report(),
+            "4 people: 2 students, 2 programmers, 1 CS students\n\
+             - Ada: no roles\n\
+             - Bo: studies at MIT\n\
+             - Cy: writes Go\n\
+             - Bill: studies at Hust, writes Rust, git bill\n"
+        );
+    }
+
+    #[test]
+    fn lines_round_trip() {
+        let mut r = registry();
+        r.add(Staff {
+            name: "odd;name=with\\escapes\nand a newline".to_owned(),
+        });
+        let lines = r.to_lines();
+        assert!(lines.starts_with("name=Ada\nname=Bo;university=MIT\n"));
+
+        let parsed = Registry::from_lines(&lines).unwrap();
+        assert_eq!(parsed.to_lines(), lines);
+        assert_eq!(parsed.report(), r.report());
+    }
+
+    #[test]
+    fn edge_whitespace_round_trips() {
+        let names = ["Ada ", "\tBo", " Cy\r", "\u{a0}Di\u{2003}", "Ed \t Fo"];
+        let mut r = Registry::new();
+        for name in names {
+            r.add(Developer {
+                name: name.to_owned(),
+                fav_language: format!("{} ", name),
+            });
+        }
+        let lines = r.to_lines();
+        assert!(lines.starts_with("name=Ada\\s;language=Ada \\s\n"));
+
+        let parsed = Registry::from_lines(&lines).unwrap();
+        let parsed_names: Vec<String> = parsed.programmers().map(|p| p.name()).collect();
+        assert_eq!(parsed_names, names);
+        assert_eq!(parsed.to_lines(), lines);
+
+        let err = Registry::from_lines("name=\\u{d800}").err().unwrap();
+        assert_eq!(err.reason, "bad escape \\u{d800}");
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        let input = "# directory\nname=Ada\n\nname=Bo;git=bo\n";
+        let err = Registry::from_lines(input).err().unwrap();
+        assert_eq!(err.line, 4);
+
+        let err = Registry::from_lines("university=MIT").err().unwrap();
+        assert_eq!(err.to_string(), "line 1: missing name");
+        let err = Registry::from_lines("name=A;name=B").err().unwrap();
+        assert_eq!(err.reason, "\"name\" is given twice");
+        let err = Registry::from_lines("name=A;age=3").err().unwrap();
+        assert_eq!(err.reason, "unknown field \"age\"");
+        let err = Registry::from_lines("name").err().unwrap();
+        assert_eq!(err.reason, "\"name\" is not key=value");
+    }
+}
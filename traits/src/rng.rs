@@ -0,0 +1,363 @@
+// A project-owned random number generator, replacing the `random` crate of
+// `return_traits_with_dyn`
+// Every generator only has to produce raw `u64`s, `Rng` derives the rest from them, so all
+// generators agree on what "a uniform index" or "a weighted pick" means and a seed always replays
+// the same simulation:
+// - `Pcg32` (PCG-XSH-RR, M. O'Neill) and `XorShift64Star` (S. Vigna) are small, fast and
+//   deterministic, not cryptographic
+// - `Recorder` tapes the raw values drawn from any generator and `Replay` plays a tape back, so a
+//   test can pin the exact sequence a piece of code consumes
+use std::ops::Range;
+
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // Uniform in `[0, 1)`, from the top 53 bits
+    fn gen_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    // Uniform in `range`, without the bias of a plain `%`: the lowest `2^64 % span` raw values
+    // are drawn again. Panics on an empty range
+    fn gen_range(&mut self, range: Range<u64>) -> u64 {
+        assert!(range.start < range.end, "empty range {:?}", range);
+        let span = range.end - range.start;
+        let threshold = span.wrapping_neg() % span;
+        loop {
+            let x = self.next_u64();
+            if x >= threshold {
+                return range.start + x % span;
+            }
+        }
+    }
+
+    fn gen_index(&mut self, len: usize) -> usize {
+        self.gen_range(0..len as u64) as usize
+    }
+
+    fn gen_bool(&mut self, p: f64) -> bool {
+        self.gen_f64() < p
+    }
+
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T>
+    where
+        Self: Sized,
+    {
+        if items.is_empty() {
+            None
+        } else {
+            Some(&items[self.gen_index(items.len())])
+        }
+    }
+
+    // Picks `item` with probability `weight / total`. Integer weights keep it exact, so the same
+    // raw value always picks the same item. `None` when every weight is 0
+    fn choose_weighted<'a, T>(&mut self, items: &'a [(u32, T)]) -> Option<&'a T>
+    where
+        Self: Sized,
+    {
+        let total: u64 = items.iter().map(|(w, _)| *w as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut ticket = self.gen_range(0..total);
+        for (weight, item) in items {
+            if ticket < *weight as u64 {
+                return Some(item);
+            }
+            ticket -= *weight as u64;
+        }
+        unreachable!("the ticket is below the total weight")
+    }
+
+    // Fisher-Yates
+    fn shuffle<T>(&mut self, items: &mut [T])
+    where
+        Self: Sized,
+    {
+        for i in (1..items.len()).rev() {
+            let j = self.gen_index(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+// So a `&mut R` can be handed to code taking `impl Rng` without giving the generator away
+impl<R: Rng + ?Sized> Rng for &mut R {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        (**self).next_u32()
+    }
+}
+
+// 64-bit state, 32-bit output. Generators with different `stream`s are independent
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6_364_136_223_846_793_005;
+
+    // `pcg32_srandom_r` of the reference implementation
+    pub fn new(seed: u64, stream: u64) -> Pcg32 {
+        let mut rng = Pcg32 {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self
+            .state
+            .wrapping_mul(Pcg32::MULTIPLIER)
+            .wrapping_add(self.inc);
+    }
+}
+
+impl Rng for Pcg32 {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        let low = self.next_u32() as u64;
+        (high << 32) | low
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XorShift64Star {
+    state: u64,
+}
+
+impl XorShift64Star {
+    // The state must never be 0, a 0 seed is replaced by a fixed constant
+    pub fn new(seed: u64) -> XorShift64Star {
+        XorShift64Star {
+            state: if seed == 0 {
+                0x9E37_79B9_7F4A_7C15
+            } else {
+                seed
+            },
+        }
+    }
+}
+
+impl Rng for XorShift64Star {
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+}
+
+// Passes the values of `R` through and keeps a copy of each
+#[derive(Debug, Clone)]
+pub struct Recorder<R> {
+    inner: R,
+    tape: Vec<u64>,
+}
+
+impl<R: Rng> Recorder<R> {
+    pub fn new(inner: R) -> Recorder<R> {
+        Recorder {
+            inner,
+            tape: Vec::new(),
+        }
+    }
+
+    pub fn tape(&self) -> &[u64] {
+        &self.tape
+    }
+
+    pub fn into_replay(self) -> Replay {
+        Replay::new(self.tape)
+    }
+}
+
+impl<R: Rng> Rng for Recorder<R> {
+    fn next_u64(&mut self) -> u64 {
+        let x = self.inner.next_u64();
+        self.tape.push(x);
+        x
+    }
+
+    // Forwarded, `R` may have a `next_u32` of its own. Taped in the high half, where the default
+    // `next_u32` of `Replay` reads it back
+    fn next_u32(&mut self) -> u32 {
+        let x = self.inner.next_u32();
+        self.tape.push((x as u64) << 32);
+        x
+    }
+}
+
+// Plays a tape back and panics when it runs out: drawing more values than recorded means the
+// code under test changed its behaviour
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    tape: Vec<u64>,
+    position: usize,
+}
+
+impl Replay {
+    pub fn new(tape: Vec<u64>) -> Replay {
+        Replay { tape, position: 0 }
+    }
+
+    // A tape for which `gen_f64` returns these values, each in `[0, 1)`, rounded down to a
+    // multiple of 2^-53
+    pub fn from_unit_floats(values: &[f64]) -> Replay {
+        let tape = values
+            .iter()
+            .map(|v| {
+                assert!((0.0..1.0).contains(v), "{} is outside [0, 1)", v);
+                ((v * (1u64 << 53) as f64) as u64) << 11
+            })
+            .collect();
+        Replay::new(tape)
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.tape.len() - self.position
+    }
+}
+
+impl Rng for Replay {
+    fn next_u64(&mut self) -> u64 {
+        let x = *self
+            .tape
+            .get(self.position)
+            .unwrap_or_else(|| panic!("replay tape exhausted after {} values", self.tape.len()));
+        self.position += 1;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcg32_matches_the_reference_output() {
+        // `pcg32-demo` of the reference implementation, seeded with 42 and 54
+        let mut rng = Pcg32::new(42, 54);
+        let first: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+        assert_eq!(
+            first,
+            [0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e]
+        );
+    }
+
+    #[test]
+    fn seeds_are_reproducible() {
+        let draw = |seed| {
+            let mut rng = XorShift64Star::new(seed);
+            (0..5).map(|_| rng.gen_range(10..20)).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        assert!(draw(0).iter().all(|x| (10..20).contains(x)));
+    }
+
+    #[test]
+    fn ranges_are_uniform_enough() {
+        let mut rng = Pcg32::new(1, 1);
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 {
+            counts[rng.gen_index(6)] += 1;
+        }
+        assert!(
+            counts.iter().all(|&c| (9_500..10_500).contains(&c)),
+            "{:?}",
+            counts
+        );
+
+        let f = rng.gen_f64();
+        assert!((0.0..1.0).contains(&f));
+    }
+
+    #[test]
+    fn weighted_choice_follows_the_weights() {
+        let items = [(0, "never"), (1, "rare"), (3, "common")];
+        // the ticket is `x % 4`: 0 is "rare", 1..=3 are "common"
+        let mut rng = Replay::new(vec![0, 1, 3, 4]);
+        let picks: Vec<&str> = (0..4)
+            .map(|_| *rng.choose_weighted(&items).unwrap())
+            .collect();
+        assert_eq!(picks, ["rare", "common", "common", "rare"]);
+        assert_eq!(rng.choose_weighted(&[(0, 1)]), None);
+        assert_eq!(rng.choose::<u8>(&[]), None);
+    }
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut rng = Pcg32::new(3, 0);
+        let mut v: Vec<u32> = (0..20).collect();
+        rng.shuffle(&mut v);
+        assert_ne!(v, (0..20).collect::<Vec<_>>());
+        v.sort_unstable();
+        assert_eq!(v, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let mut recorder = Recorder::new(Pcg32::new(9, 9));
+        let mut deck: Vec<u32> = (0..10).collect();
+        recorder.shuffle(&mut deck);
+        let roll = recorder.gen_range(1..7);
+
+        let mut replay = recorder.into_replay();
+        let mut again: Vec<u32> = (0..10).collect();
+        replay.shuffle(&mut again);
+        assert_eq!(again, deck);
+        assert_eq!(replay.gen_range(1..7), roll);
+        assert_eq!(replay.remaining(), 0);
+    }
+
+    #[test]
+    fn recording_keeps_the_u32_stream() {
+        let mut plain = Pcg32::new(42, 54);
+        let mut recorder = Recorder::new(Pcg32::new(42, 54));
+        let expected: Vec<u32> = (0..6).map(|_| plain.next_u32()).collect();
+        let recorded: Vec<u32> = (0..6).map(|_| recorder.next_u32()).collect();
+        assert_eq!(recorded, expected);
+
+        let mut replay = recorder.into_replay();
+        let replayed: Vec<u32> = (0..6).map(|_| replay.next_u32()).collect();
+        assert_eq!(replayed, expected);
+    }
+
+    #[test]
+    fn unit_floats_replay_exactly() {
+        let mut rng = Replay::from_unit_floats(&[0.25, 0.75]);
+        assert_eq!(rng.gen_f64(), 0.25);
+        assert!(!rng.gen_bool(0.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "replay tape exhausted")]
+    fn exhausted_replay_panics() {
+        let mut rng = Replay::new(vec![1]);
+        rng.next_u64();
+        rng.next_u64();
+    }
+}
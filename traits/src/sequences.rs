@@ -0,0 +1,554 @@
+// The `Fibonacci` of `traits_iterators` as a family of integer sequences which don't overflow
+// silently: every iterator is generic over the integer type, does its arithmetic checked and
+// ends with `None` at the first term which doesn't fit, so `Fibonacci::<u8>::new().count()` is
+// the number of Fibonacci numbers an `u8` can hold
+// `nth` jumps instead of stepping: fast doubling for Fibonacci and Lucas numbers, powers of the
+// companion matrix for other linear recurrences, both O(log n)
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+
+pub trait Int: Copy + Ord + Debug {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn is_even(self) -> bool;
+    fn half(self) -> Self;
+    fn from_u64(n: u64) -> Option<Self>;
+}
+
+macro_rules! impl_int {
+    ($($t:ty),+) => {$(
+        impl Int for $t {
+            const ZERO: $t = 0;
+            const ONE: $t = 1;
+
+            fn checked_add(self, other: $t) -> Option<$t> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_sub(self, other: $t) -> Option<$t> {
+                <$t>::checked_sub(self, other)
+            }
+
+            fn checked_mul(self, other: $t) -> Option<$t> {
+                <$t>::checked_mul(self, other)
+            }
+
+            fn is_even(self) -> bool {
+                self % 2 == 0
+            }
+
+            fn half(self) -> $t {
+                self / 2
+            }
+
+            fn from_u64(n: u64) -> Option<$t> {
+                <$t>::try_from(n).ok()
+            }
+        }
+    )+};
+}
+
+impl_int!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+// (F(n), F(n + 1)), `None` when F(n + 1) doesn't fit. Every intermediate value is at most
+// F(n + 1): `2b - a` is computed as `b + (b - a)`
+fn fib_pair<T: Int>(n: u64) -> Option<(T, T)> {
+    if n == 0 {
+        return Some((T::ZERO, T::ONE));
+    }
+    let (a, b) = fib_pair::<T>(n / 2)?;
+    // F(2k) = F(k) * (2 F(k + 1) - F(k)), F(2k + 1) = F(k)^2 + F(k + 1)^2
+    let even = a.checked_mul(b.checked_add(b.checked_sub(a)?)?)?;
+    let odd = a.checked_mul(a)?.checked_add(b.checked_mul(b)?)?;
+    if n.is_multiple_of(2) {
+        Some((even, odd))
+    } else {
+        Some((odd, even.checked_add(odd)?))
+    }
+}
+
+// F(n) on its own, which may fit where F(n + 1) doesn't
+pub fn fibonacci<T: Int>(n: u64) -> Option<T> {
+    if n == 0 {
+        return Some(T::ZERO);
+    }
+    let (a, b) = fib_pair::<T>(n / 2)?;
+    if n.is_multiple_of(2) {
+        a.checked_mul(b.checked_add(b.checked_sub(a)?)?)
+    } else {
+        a.checked_mul(a)?.checked_add(b.checked_mul(b)?)
+    }
+}
+
+// L(n) = F(n - 1) + F(n + 1) = 2 F(n + 1) - F(n), and F(n + 1) <= L(n) for n >= 1
+pub fn lucas<T: Int>(n: u64) -> Option<T> {
+    if n == 0 {
+        return T::ONE.checked_add(T::ONE);
+    }
+    let (a, b) = fib_pair::<T>(n)?;
+    b.checked_add(b.checked_sub(a)?)
+}
+
+// Fibonacci or Lucas numbers from index 0: 0, 1, 1, 2, 3... or 2, 1, 3, 4, 7...
+// Unlike the tutorial's version, which starts at F(1), the sequence starts at F(0)
+#[derive(Debug, Clone)]
+pub struct Fibonacci<T> {
+    term: fn(u64) -> Option<T>,
+    n: u64,
+    curr: Option<T>,
+    next: Option<T>,
+}
+
+impl<T: Int> Fibonacci<T> {
+    pub fn new() -> Fibonacci<T> {
+        Fibonacci::starting(fibonacci::<T>, 0)
+    }
+
+    pub fn lucas() -> Fibonacci<T> {
+        Fibonacci::starting(lucas::<T>, 0)
+    }
+
+    fn starting(term: fn(u64) -> Option<T>, n: u64) -> Fibonacci<T> {
+        Fibonacci {
+            term,
+            n,
+            curr: term(n),
+            next: n.checked_add(1).and_then(term),
+        }
+    }
+
+    // Index of the term `next` returns
+    pub fn index(&self) -> u64 {
+        self.n
+    }
+}
+
+impl<T: Int> Default for Fibonacci<T> {
+    fn default() -> Fibonacci<T> {
+        Fibonacci::new()
+    }
+}
+
+impl<T: Int> Iterator for Fibonacci<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let curr = self.curr?;
+        let after = match self.next {
+            Some(next) => curr.checked_add(next),
+            None => None,
+        };
+        self.curr = self.next;
+        self.next = after;
+        self.n += 1;
+        Some(curr)
+    }
+
+    fn nth(&mut self, k: usize) -> Option<T> {
+        self.curr?;
+        match self.n.checked_add(k as u64) {
+            Some(n) => *self = Fibonacci::starting(self.term, n),
+            None => self.curr = None,
+        }
+        self.next()
+    }
+}
+
+// a(n + k) = c[0] a(n) + c[1] a(n + 1) + ... + c[k - 1] a(n + k - 1), from the first `k` terms
+#[derive(Debug, Clone)]
+pub struct LinearRecurrence<T> {
+    coefficients: Vec<T>,
+    // The next terms to yield, `k` of them until a term overflowed, fewer from then on
+    window: VecDeque<T>,
+    overflowed: bool,
+}
+
+impl<T: Int> LinearRecurrence<T> {
+    // Panics unless there is one initial term per coefficient
+    pub fn new(coefficients: &[T], initial: &[T]) -> LinearRecurrence<T> {
+        assert_eq!(
+            coefficients.len(),
+            initial.len(),
+            "one initial term per coefficient"
+        );
+        assert!(!coefficients.is_empty(), "a recurrence needs a coefficient");
+        LinearRecurrence {
+            coefficients: coefficients.to_vec(),
+            window: initial.iter().copied().collect(),
+            overflowed: false,
+        }
+    }
+
+    fn complete(&self) -> bool {
+        !self.overflowed && self.window.len() == self.coefficients.len()
+    }
+
+    fn following(&self) -> Option<T> {
+        self.coefficients
+            .iter()
+            .zip(&self.window)
+            .try_fold(T::ZERO, |sum, (&c, &a)| sum.checked_add(c.checked_mul(a)?))
+    }
+
+    // The companion matrix `m`, with `m * (a(n) .. a(n + k)) = (a(n + 1) .. a(n + k + 1))`
+    fn companion(&self) -> Vec<Vec<T>> {
+        let k = self.coefficients.len();
+        let mut m = vec![vec![T::ZERO; k]; k];
+        for (i, row) in m.iter_mut().enumerate().take(k - 1) {
+            row[i + 1] = T::ONE;
+        }
+        m[k - 1] = self.coefficients.clone();
+        m
+    }
+
+    // Skips `steps` terms through `companion^steps`, `None` if any entry of a power or of the new
+    // window overflows, even one past the term asked for
+    fn jump(&self, steps: u64) -> Option<VecDeque<T>> {
+        let window: Vec<T> = self.window.iter().copied().collect();
+        let power = matrix_pow(self.companion(), steps)?;
+        power
+            .iter()
+            .map(|row| dot(row, &window))
+            .collect::<Option<VecDeque<T>>>()
+    }
+}
+
+fn dot<T: Int>(a: &[T], b: &[T]) -> Option<T> {
+    a.iter()
+        .zip(b)
+        .try_fold(T::ZERO, |sum, (&x, &y)| sum.checked_add(x.checked_mul(y)?))
+}
+
+fn matrix_mul<T: Int>(a: &[Vec<T>], b: &[Vec<T>]) -> Option<Vec<Vec<T>>> {
+    let columns: Vec<Vec<T>> = (0..b.len())
+        .map(|j| b.iter().map(|row| row[j]).collect())
+        .collect();
+    a.iter()
+        .map(|row| columns.iter().map(|column| dot(row, column)).collect())
+        .collect()
+}
+
+fn matrix_pow<T: Int>(mut base: Vec<Vec<T>>, mut exp: u64) -> Option<Vec<Vec<T>>> {
+    let k = base.len();
+    let mut result: Vec<Vec<T>> = (0..k)
+        .map(|i| {
+            (0..k)
+                .map(|j| if i == j { T::ONE } else { T::ZERO })
+                .collect()
+        })
+        .collect();
+    while exp > 0 {
+        if exp % 2 == 1 {
+            result = matrix_mul(&result, &base)?;
+        }
+        exp /= 2;
+        if exp > 0 {
+            base = matrix_mul(&base, &base)?;
+        }
+    }
+    Some(result)
+}
+
+impl<T: Int> Iterator for LinearRecurrence<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.complete() {
+            if let Some(following) = self.following() {
+                self.window.push_back(following);
+                return self.window.pop_front();
+            }
+            // the remaining terms drain, and no new one is computed
+            self.overflowed = true;
+        }
+        self.window.pop_front()
+    }
+
+    fn nth(&mut self, k: usize) -> Option<T> {
+        if self.complete() {
+            if let Some(window) = self.jump(k as u64) {
+                self.window = window;
+                return self.next();
+            }
+        }
+        // a power overflowed, but the term itself may still fit: step there
+        for _ in 0..k {
+            self.next()?;
+        }
+        self.next()
+    }
+}
+
+// The primes from `start` on, sieved one segment at a time, so memory stays at
+// O(sqrt(p) + SEGMENT) however far the iteration goes
+#[derive(Debug, Clone)]
+pub struct Primes<T> {
+    // The primes up to `base_limit`, which sieve every segment ending below `base_limit^2`
+    base: Vec<u64>,
+    base_limit: u64,
+    low: u64,
+    segment: Vec<bool>,
+    pos: usize,
+    exhausted: bool,
+    _type: std::marker::PhantomData<T>,
+}
+
+impl<T: Int> Primes<T> {
+    const SEGMENT: u64 = 1 << 15;
+
+    pub fn new() -> Primes<T> {
+        Primes::starting_at(2)
+    }
+
+    pub fn starting_at(start: u64) -> Primes<T> {
+        Primes {
+            base: Vec::new(),
+            base_limit: 1,
+            low: start.max(2),
+            segment: Vec::new(),
+            pos: 0,
+            exhausted: false,
+            _type: std::marker::PhantomData,
+        }
+    }
+
+    fn sieve_segment(&mut self) {
+        let high = self.low.saturating_add(Primes::<T>::SEGMENT);
+        let needed = isqrt(high - 1);
+        if needed > self.base_limit {
+            // doubling past `isqrt(u64::MAX)` would sieve a base no segment needs
+            self.base_limit = needed.max(self.base_limit * 2).min(isqrt(u64::MAX));
+            self.base = simple_sieve(self.base_limit);
+        }
+        let mut segment = vec![true; (high - self.low) as usize];
+        for &p in &self.base {
+            if p * p >= high {
+                break;
+            }
+            // the first multiple of `p` in the segment, not `p` itself
+            let first = match self.low.div_ceil(p).checked_mul(p) {
+                Some(first) => first.max(p * p),
+                None => continue,
+            };
+            for multiple in (first..high).step_by(p as usize) {
+                segment[(multiple - self.low) as usize] = false;
+            }
+        }
+        self.segment = segment;
+        self.pos = 0;
+    }
+}
+
+impl<T: Int> Default for Primes<T> {
+    fn default() -> Primes<T> {
+        Primes::new()
+    }
+}
+
+impl<T: Int> Iterator for Primes<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while !self.exhausted {
+            if self.pos == self.segment.len() {
+                self.low += self.segment.len() as u64;
+                self.sieve_segment();
+                // only at the end of the u64 range
+                self.exhausted = self.segment.is_empty();
+                continue;
+            }
+            let candidate = self.low + self.pos as u64;
+            let is_prime = self.segment[self.pos];
+            self.pos += 1;
+            if is_prime {
+                let prime = T::from_u64(candidate);
+                self.exhausted = prime.is_none();
+                return prime;
+            }
+        }
+        None
+    }
+}
+
+// The float estimate can be one off either way, near `u64::MAX` its square overflows
+fn isqrt(n: u64) -> u64 {
+    let mut r = (n as f64).sqrt() as u64;
+    while r.checked_mul(r).is_none_or(|square| square > n) {
+        r -= 1;
+    }
+    while (r + 1).checked_mul(r + 1).is_some_and(|square| square <= n) {
+        r += 1;
+    }
+    r
+}
+
+// Eratosthenes up to and including `limit`
+fn simple_sieve(limit: u64) -> Vec<u64> {
+    let mut is_prime = vec![true; limit as usize + 1];
+    let mut primes = Vec::new();
+    for n in 2..=limit {
+        if is_prime[n as usize] {
+            primes.push(n);
+            for multiple in (n * n..=limit).step_by(n as usize) {
+                is_prime[multiple as usize] = false;
+            }
+        }
+    }
+    primes
+}
+
+// n, then n / 2 or 3n + 1 until 1, which is included. Nothing for n <= 0, and the sequence is
+// cut short if 3n + 1 overflows
+#[derive(Debug, Clone)]
+pub struct Collatz<T> {
+    next: Option<T>,
+}
+
+impl<T: Int> Collatz<T> {
+    pub fn new(start: T) -> Collatz<T> {
+        Collatz {
+            next: if start > T::ZERO { Some(start) } else { None },
+        }
+    }
+}
+
+impl<T: Int> Iterator for Collatz<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let n = self.next?;
+        self.next = if n == T::ONE {
+            None
+        } else if n.is_even() {
+            Some(n.half())
+        } else {
+            n.checked_add(n)
+                .and_then(|d| d.checked_add(n))
+                .and_then(|t| t.checked_add(T::ONE))
+        };
+        Some(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fibonacci_stops_at_overflow() {
+        let small: Vec<u8> = Fibonacci::new().collect();
+        assert_eq!(small.len(), 14);
+        assert_eq!(small[..8], [0, 1, 1, 2, 3, 5, 8, 13]);
+        assert_eq!(small.last(), Some(&233));
+
+        // 47 terms after F(0) fit in an u32, the tutorial's iterator overflowed after them
+        assert_eq!(Fibonacci::<u32>::new().count(), 48);
+        assert_eq!(Fibonacci::<i32>::new().last(), Some(1_836_311_903));
+        assert_eq!(Fibonacci::<u128>::new().count(), 187);
+    }
+
+    #[test]
+    fn nth_jumps() {
+        let mut fib = Fibonacci::<u64>::new();
+        assert_eq!(fib.nth(90), Some(2_880_067_194_370_816_120));
+        assert_eq!(fib.index(), 91);
+        assert_eq!(fib.next(), Some(4_660_046_610_375_530_309));
+        // F(93) is the largest u64 Fibonacci number, F(94) doesn't fit
+        assert_eq!(fib.nth(1), Some(12_200_160_415_121_876_738));
+        assert_eq!(fib.next(), None);
+        assert_eq!(Fibonacci::<u64>::new().nth(94), None);
+        assert_eq!(fibonacci::<u8>(13), Some(233));
+        assert_eq!(fibonacci::<u8>(14), None);
+
+        let mut stepped = Fibonacci::<u128>::new();
+        for n in 0..186 {
+            assert_eq!(Fibonacci::<u128>::new().nth(n), stepped.next());
+        }
+    }
+
+    #[test]
+    fn lucas_numbers() {
+        let first: Vec<u16> = Fibonacci::lucas().take(8).collect();
+        assert_eq!(first, [2, 1, 3, 4, 7, 11, 18, 29]);
+        assert_eq!(Fibonacci::<u8>::lucas().last(), Some(199));
+        assert_eq!(lucas::<u64>(50), Some(28_143_753_123));
+        assert_eq!(Fibonacci::<u64>::lucas().nth(50), Some(28_143_753_123));
+    }
+
+    #[test]
+    fn linear_recurrences() {
+        let as_fib = LinearRecurrence::new(&[1u64, 1], &[0, 1]);
+        assert!(as_fib.eq(Fibonacci::<u64>::new()));
+
+        // Tribonacci
+        let mut trib = LinearRecurrence::new(&[1u32, 1, 1], &[0, 0, 1]);
+        let first: Vec<u32> = trib.clone().take(10).collect();
+        assert_eq!(first, [0, 0, 1, 1, 2, 4, 7, 13, 24, 44]);
+        assert_eq!(trib.nth(37), Some(1_132_436_852));
+        assert_eq!(trib.next(), Some(2_082_876_103));
+        assert_eq!(trib.next(), Some(3_831_006_429));
+        assert_eq!(trib.next(), None);
+
+        // a(n) = 2 a(n - 1) - a(n - 2) counts up, its matrix powers stay small
+        let mut naturals = LinearRecurrence::new(&[-1i64, 2], &[0, 1]);
+        assert_eq!(naturals.nth(1_000_000_000), Some(1_000_000_000));
+        assert_eq!(naturals.next(), Some(1_000_000_001));
+
+        // the jump to F(13) would need F(14) too, which overflows: falls back to stepping
+        let mut small = LinearRecurrence::new(&[1u8, 1], &[0, 1]);
+        assert_eq!(small.nth(13), Some(233));
+        assert_eq!(small.next(), None);
+    }
+
+    #[test]
+    fn primes_through_segments() {
+        let first: Vec<u8> = Primes::new().take(10).collect();
+        assert_eq!(first, [2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        assert_eq!(Primes::<u8>::new().last(), Some(251));
+        assert_eq!(Primes::<u8>::new().count(), 54);
+
+        // several segments, checked against trial division
+        let sieved: Vec<u64> = Primes::new().take_while(|&p| p < 200_000).collect();
+        assert_eq!(sieved.len(), 17_984);
+        assert!(sieved
+            .iter()
+            .step_by(997)
+            .all(|&p| (2..).take_while(|d| d * d <= p).all(|d| p % d != 0)));
+
+        let far: Vec<u64> = Primes::starting_at(1_000_000_000_000).take(3).collect();
+        assert_eq!(
+            far,
+            [1_000_000_000_039, 1_000_000_000_061, 1_000_000_000_063]
+        );
+        assert_eq!(Primes::<u16>::starting_at(65_500).last(), Some(65_521));
+    }
+
+    #[test]
+    fn isqrt_up_to_the_end_of_u64() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(99), 9);
+        assert_eq!(isqrt(100), 10);
+        assert_eq!(isqrt(u64::MAX), u32::MAX as u64);
+        assert_eq!(isqrt((u32::MAX as u64).pow(2)), u32::MAX as u64);
+        assert_eq!(isqrt((u32::MAX as u64).pow(2) - 1), u32::MAX as u64 - 1);
+    }
+
+    #[test]
+    fn collatz() {
+        let path: Vec<u32> = Collatz::new(6).collect();
+        assert_eq!(path, [6, 3, 10, 5, 16, 8, 4, 2, 1]);
+        assert_eq!(Collatz::new(27u64).count(), 112);
+        assert_eq!(Collatz::new(27u64).max(), Some(9_232));
+        assert_eq!(Collatz::new(0i32).next(), None);
+        // 3 * 107 + 1 doesn't fit
+        assert_eq!(
+            Collatz::new(27u8).collect::<Vec<_>>(),
+            [27, 82, 41, 124, 62, 31, 94, 47, 142, 71, 214, 107]
+        );
+    }
+}
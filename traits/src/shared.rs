@@ -0,0 +1,348 @@
+// `Pair(Box<i32>, Box<i32>)` of `traits_clone` copies both boxes on every `clone`. For a large
+// tree cloned per request that's a lot of copying nobody sees. `Shared<T>` shares its value
+// instead, until it's written to:
+// - `clone` (or `shallow_clone`) only bumps a reference count
+// - `make_mut` copies the value first if it's shared (copy on write), and only this level: the
+//   nested `Shared` children are shared by the copy, so a write deep in a tree copies one path
+// - `DeepClone::deep_clone` copies every level, nothing is shared with the original
+// - `into_inner` takes the value out, a shared one is copied like in `make_mut`
+// Every container reports to a `Tracker`, which counts the clones and copies of a whole tree
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[derive(Debug, Default)]
+struct Counters {
+    shallow_clones: AtomicUsize,
+    deep_clones: AtomicUsize,
+    copies_on_write: AtomicUsize,
+    writes_in_place: AtomicUsize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CloneReport {
+    pub shallow_clones: usize,
+    pub deep_clones: usize,
+    pub copies_on_write: usize,
+    pub writes_in_place: usize,
+}
+
+impl fmt::Display for CloneReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} shallow clones, {} deep clones, {} copies on write, {} writes in place",
+            self.shallow_clones, self.deep_clones, self.copies_on_write, self.writes_in_place
+        )
+    }
+}
+
+// Cheap to clone, clones count into the same counters
+#[derive(Debug, Clone, Default)]
+pub struct Tracker {
+    counters: Arc<Counters>,
+}
+
+impl Tracker {
+    pub fn new() -> Tracker {
+        Tracker::default()
+    }
+
+    pub fn report(&self) -> CloneReport {
+        let c = &self.counters;
+        CloneReport {
+            shallow_clones: c.shallow_clones.load(Ordering::Relaxed),
+            deep_clones: c.deep_clones.load(Ordering::Relaxed),
+            copies_on_write: c.copies_on_write.load(Ordering::Relaxed),
+            writes_in_place: c.writes_in_place.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        let c = &self.counters;
+        for counter in [
+            &c.shallow_clones,
+            &c.deep_clones,
+            &c.copies_on_write,
+            &c.writes_in_place,
+        ] {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn count(counter: &AtomicUsize) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub struct Shared<T> {
+    value: Arc<T>,
+    tracker: Tracker,
+}
+
+impl<T> Shared<T> {
+    // With a tracker of its own
+    pub fn new(value: T) -> Shared<T> {
+        Shared::tracked(value, &Tracker::new())
+    }
+
+    pub fn tracked(value: T, tracker: &Tracker) -> Shared<T> {
+        Shared {
+            value: Arc::new(value),
+            tracker: tracker.clone(),
+        }
+    }
+
+    pub fn tracker(&self) -> &Tracker {
+        &self.tracker
+    }
+
+    pub fn shallow_clone(&self) -> Shared<T> {
+        Tracker::count(&self.tracker.counters.shallow_clones);
+        Shared {
+            value: Arc::clone(&self.value),
+            tracker: self.tracker.clone(),
+        }
+    }
+
+    pub fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.value) > 1
+    }
+
+    // Number of containers sharing the value, this one included
+    pub fn share_count(&self) -> usize {
+        Arc::strong_count(&self.value)
+    }
+
+    pub fn ptr_eq(&self, other: &Shared<T>) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl<T: Clone> Shared<T> {
+    // Copies the value first if anything else shares it
+    pub fn make_mut(&mut self) -> &mut T {
+        if Arc::get_mut(&mut self.value).is_some() {
+            Tracker::count(&self.tracker.counters.writes_in_place);
+        } else {
+            Tracker::count(&self.tracker.counters.copies_on_write);
+            self.value = Arc::new(T::clone(&self.value));
+        }
+        Arc::get_mut(&mut self.value).expect("the value was just unshared")
+    }
+
+    // The value itself if it isn't shared, a copy otherwise, counted as a copy on write
+    pub fn into_inner(self) -> T {
+        let tracker = self.tracker;
+        Arc::try_unwrap(self.value).unwrap_or_else(|shared| {
+            Tracker::count(&tracker.counters.copies_on_write);
+            T::clone(&shared)
+        })
+    }
+}
+
+// `clone` shares, like `Rc`; `deep_clone` is the copy
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Shared<T> {
+        self.shallow_clone()
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Shared<T> {
+    fn eq(&self, other: &Shared<T>) -> bool {
+        self.ptr_eq(other) || self.value == other.value
+    }
+}
+
+// A copy sharing nothing with the original, at any depth
+pub trait DeepClone {
+    fn deep_clone(&self) -> Self;
+}
+
+impl<T: DeepClone> DeepClone for Shared<T> {
+    fn deep_clone(&self) -> Shared<T> {
+        Tracker::count(&self.tracker.counters.deep_clones);
+        Shared::tracked(self.value.deep_clone(), &self.tracker)
+    }
+}
+
+// Values without `Shared` inside are copied through `Clone`
+macro_rules! deep_clone_by_clone {
+    ($($t:ty),+) => {$(
+        impl DeepClone for $t {
+            fn deep_clone(&self) -> $t {
+                self.clone()
+            }
+        }
+    )+};
+}
+
+deep_clone_by_clone!(bool, char, i32, i64, u32, u64, usize, f64, String);
+
+impl<T: DeepClone> DeepClone for Vec<T> {
+    fn deep_clone(&self) -> Vec<T> {
+        self.iter().map(DeepClone::deep_clone).collect()
+    }
+}
+
+impl<T: DeepClone> DeepClone for Option<T> {
+    fn deep_clone(&self) -> Option<T> {
+        self.as_ref().map(DeepClone::deep_clone)
+    }
+}
+
+impl<K: Ord + Clone, V: DeepClone> DeepClone for BTreeMap<K, V> {
+    fn deep_clone(&self) -> BTreeMap<K, V> {
+        self.iter()
+            .map(|(k, v)| (k.clone(), v.deep_clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Node {
+        Leaf(String),
+        Branch(BTreeMap<String, Shared<Node>>),
+    }
+
+    impl DeepClone for Node {
+        fn deep_clone(&self) -> Node {
+            match self {
+                Node::Leaf(s) => Node::Leaf(s.deep_clone()),
+                Node::Branch(children) => Node::Branch(children.deep_clone()),
+            }
+        }
+    }
+
+    // root -> {db -> {host, port}, name}
+    fn config(tracker: &Tracker) -> Shared<Node> {
+        let leaf = |s: &str| Shared::tracked(Node::Leaf(s.to_owned()), tracker);
+        let mut db = BTreeMap::new();
+        db.insert("host".to_owned(), leaf("localhost"));
+        db.insert("port".to_owned(), leaf("5432"));
+        let mut root = BTreeMap::new();
+        root.insert("db".to_owned(), Shared::tracked(Node::Branch(db), tracker));
+        root.insert("name".to_owned(), leaf("app"));
+        Shared::tracked(Node::Branch(root), tracker)
+    }
+
+    fn child<'a>(node: &'a mut Shared<Node>, key: &str) -> &'a mut Shared<Node> {
+        match node.make_mut() {
+            Node::Branch(children) => children.get_mut(key).unwrap(),
+            Node::Leaf(_) => panic!("{} is below a leaf", key),
+        }
+    }
+
+    fn get<'a>(node: &'a Shared<Node>, key: &str) -> &'a Shared<Node> {
+        match &**node {
+            Node::Branch(children) => &children[key],
+            Node::Leaf(_) => panic!("{} is below a leaf", key),
+        }
+    }
+
+    #[test]
+    fn shares_until_written() {
+        let mut a = Shared::new(vec![1, 2, 3]);
+        let b = a.clone();
+        assert!(a.ptr_eq(&b) && a.is_shared());
+        assert_eq!(a.share_count(), 2);
+
+        a.make_mut().push(4);
+        assert!(!a.ptr_eq(&b));
+        assert_eq!((a.len(), b.len()), (4, 3));
+        a.make_mut().push(5);
+
+        let report = a.tracker().report();
+        assert_eq!(
+            report,
+            CloneReport {
+                shallow_clones: 1,
+                deep_clones: 0,
+                copies_on_write: 1,
+                writes_in_place: 1,
+            }
+        );
+        assert_eq!(b.into_inner(), [1, 2, 3]);
+
+        // `b` was the only owner, nothing was copied
+        assert_eq!(a.tracker().report().copies_on_write, 1);
+        let c = a.clone();
+        assert_eq!(c.into_inner(), [1, 2, 3, 4, 5]);
+        assert_eq!(a.tracker().report().copies_on_write, 2);
+    }
+
+    #[test]
+    fn writes_copy_one_path() {
+        let tracker = Tracker::new();
+        let original = config(&tracker);
+        let mut copy = original.clone();
+        *child(child(&mut copy, "db"), "port").make_mut() = Node::Leaf("6543".to_owned());
+
+        // root, db and port were copied, the other nodes are still shared
+        assert_eq!(tracker.report().copies_on_write, 3);
+        assert!(!copy.ptr_eq(&original));
+        assert!(get(&copy, "name").ptr_eq(get(&original, "name")));
+        assert!(get(get(&copy, "db"), "host").ptr_eq(get(get(&original, "db"), "host")));
+        assert_eq!(
+            *get(get(&original, "db"), "port"),
+            Shared::new(Node::Leaf("5432".to_owned()))
+        );
+        assert_ne!(copy, original);
+    }
+
+    #[test]
+    fn deep_clones_share_nothing() {
+        let tracker = Tracker::new();
+        let original = config(&tracker);
+        tracker.reset();
+        let copy = original.deep_clone();
+
+        assert_eq!(copy, original);
+        assert!(!copy.ptr_eq(&original));
+        assert!(!get(&copy, "name").ptr_eq(get(&original, "name")));
+        assert!(!get(get(&copy, "db"), "host").ptr_eq(get(get(&original, "db"), "host")));
+        // every one of the 5 nodes
+        assert_eq!(tracker.report().deep_clones, 5);
+        assert_eq!(tracker.report().shallow_clones, 0);
+    }
+
+    #[test]
+    fn clones_cross_threads() {
+        let base = Shared::new(vec![0u64; 1_000]);
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let mut mine = base.clone();
+                thread::spawn(move || {
+                    mine.make_mut()[0] = i;
+                    mine[0]
+                })
+            })
+            .collect();
+        let firsts: Vec<u64> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+        assert_eq!(firsts, [0, 1, 2, 3]);
+        assert_eq!(base[0], 0);
+        let report = base.tracker().report();
+        assert_eq!((report.shallow_clones, report.copies_on_write), (4, 4));
+    }
+}
@@ -0,0 +1,342 @@
+// 2D and 3D vectors, the `Foo + Bar = FooBar` of `traits_operator_overloading` grown into real
+// algebra for physics code
+// Every binary operator is implemented for owned values and references in all four combinations,
+// so `a + b`, `&a + b`, `a + &b` and `&a + &b` all work and code taking `&Vec3` doesn't have to
+// copy first. Both types are generated by the same `vector!` macro, only `cross` differs
+// Floats are compared with `ApproxEq`, `==` stays exact
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
+
+pub const EPSILON: f64 = 1e-9;
+
+pub trait ApproxEq {
+    fn approx_eq_eps(&self, other: &Self, epsilon: f64) -> bool;
+
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.approx_eq_eps(other, EPSILON)
+    }
+}
+
+impl ApproxEq for f64 {
+    fn approx_eq_eps(&self, other: &f64, epsilon: f64) -> bool {
+        (self - other).abs() <= epsilon
+    }
+}
+
+// `$op` for `$lhs op $rhs` through `$method`, with `$body` written against owned `a` and `b`
+macro_rules! forward_binop {
+    (impl $imp:ident, $method:ident for $t:ident, $rhs:ty, |$a:ident, $b:ident| $body:expr) => {
+        impl $imp<$rhs> for $t {
+            type Output = $t;
+
+            fn $method(self, rhs: $rhs) -> $t {
+                let ($a, $b) = (self, rhs);
+                $body
+            }
+        }
+
+        impl<'a> $imp<$rhs> for &'a $t {
+            type Output = $t;
+
+            fn $method(self, rhs: $rhs) -> $t {
+                (*self).$method(rhs)
+            }
+        }
+
+        impl<'b> $imp<&'b $rhs> for $t {
+            type Output = $t;
+
+            fn $method(self, rhs: &'b $rhs) -> $t {
+                self.$method(*rhs)
+            }
+        }
+
+        impl<'a, 'b> $imp<&'b $rhs> for &'a $t {
+            type Output = $t;
+
+            fn $method(self, rhs: &'b $rhs) -> $t {
+                (*self).$method(*rhs)
+            }
+        }
+    };
+}
+
+// `$op=` from `$op`, for an owned or borrowed right-hand side
+macro_rules! forward_assign {
+    (impl $imp:ident, $method:ident, $op:tt for $t:ident, $rhs:ty) => {
+        impl $imp<$rhs> for $t {
+            fn $method(&mut self, rhs: $rhs) {
+                *self = *self $op rhs;
+            }
+        }
+
+        impl<'b> $imp<&'b $rhs> for $t {
+            fn $method(&mut self, rhs: &'b $rhs) {
+                *self = *self $op *rhs;
+            }
+        }
+    };
+}
+
+macro_rules! vector {
+    ($t:ident, $len:expr, $($field:ident),+) => {
+        #[derive(Debug, Clone, Copy, Default, PartialEq)]
+        pub struct $t {
+            $(pub $field: f64,)+
+        }
+
+        impl $t {
+            pub const fn new($($field: f64),+) -> $t {
+                $t { $($field),+ }
+            }
+
+            pub const fn zero() -> $t {
+                $t { $($field: 0.0),+ }
+            }
+
+            pub fn dot(&self, other: &$t) -> f64 {
+                0.0 $(+ self.$field * other.$field)+
+            }
+
+            pub fn length_squared(&self) -> f64 {
+                self.dot(self)
+            }
+
+            pub fn length(&self) -> f64 {
+                self.length_squared().sqrt()
+            }
+
+            // The unit vector in the same direction, `None` for a (near) zero vector which has none
+            pub fn normalize(&self) -> Option<$t> {
+                let length = self.length();
+                if length <= EPSILON {
+                    None
+                } else {
+                    Some(self / length)
+                }
+            }
+
+            pub fn distance(&self, other: &$t) -> f64 {
+                (self - other).length()
+            }
+
+            pub fn lerp(&self, other: &$t, t: f64) -> $t {
+                self + (other - self) * t
+            }
+
+            pub fn to_array(self) -> [f64; $len] {
+                [$(self.$field),+]
+            }
+        }
+
+        impl ApproxEq for $t {
+            fn approx_eq_eps(&self, other: &$t, epsilon: f64) -> bool {
+                true $(&& self.$field.approx_eq_eps(&other.$field, epsilon))+
+            }
+        }
+
+        impl fmt::Display for $t {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let parts: Vec<String> = self.to_array().iter().map(|x| format!("{}", x)).collect();
+                write!(f, "({})", parts.join(", "))
+            }
+        }
+
+        forward_binop!(impl Add, add for $t, $t, |a, b| $t { $($field: a.$field + b.$field),+ });
+        forward_binop!(impl Sub, sub for $t, $t, |a, b| $t { $($field: a.$field - b.$field),+ });
+        forward_binop!(impl Mul, mul for $t, f64, |a, k| $t { $($field: a.$field * k),+ });
+        forward_binop!(impl Div, div for $t, f64, |a, k| $t { $($field: a.$field / k),+ });
+        forward_assign!(impl AddAssign, add_assign, + for $t, $t);
+        forward_assign!(impl SubAssign, sub_assign, - for $t, $t);
+        forward_assign!(impl MulAssign, mul_assign, * for $t, f64);
+        forward_assign!(impl DivAssign, div_assign, / for $t, f64);
+
+        // `k * v` as well as `v * k`
+        impl Mul<$t> for f64 {
+            type Output = $t;
+
+            fn mul(self, v: $t) -> $t {
+                v * self
+            }
+        }
+
+        impl<'b> Mul<&'b $t> for f64 {
+            type Output = $t;
+
+            fn mul(self, v: &'b $t) -> $t {
+                v * self
+            }
+        }
+
+        impl Neg for $t {
+            type Output = $t;
+
+            fn neg(self) -> $t {
+                $t { $($field: -self.$field),+ }
+            }
+        }
+
+        impl<'a> Neg for &'a $t {
+            type Output = $t;
+
+            fn neg(self) -> $t {
+                -*self
+            }
+        }
+
+        impl Sum for $t {
+            fn sum<I: Iterator<Item = $t>>(iter: I) -> $t {
+                iter.fold($t::zero(), |acc, v| acc + v)
+            }
+        }
+
+        impl<'a> Sum<&'a $t> for $t {
+            fn sum<I: Iterator<Item = &'a $t>>(iter: I) -> $t {
+                iter.fold($t::zero(), |acc, v| acc + v)
+            }
+        }
+
+        // Components by position, panics past the last one like a slice would
+        impl Index<usize> for $t {
+            type Output = f64;
+
+            fn index(&self, i: usize) -> &f64 {
+                [$(&self.$field),+]
+                    .get(i)
+                    .copied()
+                    .unwrap_or_else(|| panic!("index {} out of range for {}", i, stringify!($t)))
+            }
+        }
+
+        impl IndexMut<usize> for $t {
+            fn index_mut(&mut self, i: usize) -> &mut f64 {
+                let $t { $($field),+ } = self;
+                // By value, to move the one `&mut` out: `[..].into_iter()` would iterate by
+                // reference in edition 2018
+                IntoIterator::into_iter([$($field),+])
+                    .nth(i)
+                    .unwrap_or_else(|| panic!("index {} out of range for {}", i, stringify!($t)))
+            }
+        }
+    };
+}
+
+vector!(Vec2, 2, x, y);
+vector!(Vec3, 3, x, y, z);
+
+impl Vec2 {
+    // The z of the 3D cross product of the two vectors in the xy plane: the signed area of their
+    // parallelogram, positive when `other` is counter-clockwise from `self`
+    pub fn cross(&self, other: &Vec2) -> f64 {
+        self.x * other.y - self.y * other.x
+    }
+
+    // Rotated a quarter turn counter-clockwise
+    pub fn perp(&self) -> Vec2 {
+        Vec2::new(-self.y, self.x)
+    }
+
+    pub fn extend(&self, z: f64) -> Vec3 {
+        Vec3::new(self.x, self.y, z)
+    }
+}
+
+impl Vec3 {
+    pub fn cross(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn truncate(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Borrowing `Copy` operands is what's under test here
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn owned_and_borrowed_operands() {
+        let a = Vec3::new(1.0, 2.0, 3.0);
+        let b = Vec3::new(4.0, 5.0, 6.0);
+        let sum = Vec3::new(5.0, 7.0, 9.0);
+        assert_eq!(a + b, sum);
+        assert_eq!(&a + b, sum);
+        assert_eq!(a + &b, sum);
+        assert_eq!(&a + &b, sum);
+        assert_eq!(&b - &a, Vec3::new(3.0, 3.0, 3.0));
+        assert_eq!(&a * 2.0, 2.0 * &a);
+        assert_eq!(a * 2.0 / 2.0, a);
+        assert_eq!(-&a, Vec3::new(-1.0, -2.0, -3.0));
+        assert_eq!([a, b].iter().sum::<Vec3>(), sum);
+    }
+
+    #[test]
+    fn compound_assignment() {
+        let mut v = Vec2::new(1.0, 1.0);
+        v += Vec2::new(1.0, 2.0);
+        v -= &Vec2::new(0.5, 0.5);
+        v *= 2.0;
+        v /= &4.0;
+        assert_eq!(v, Vec2::new(0.75, 1.25));
+    }
+
+    #[test]
+    fn indexing() {
+        let mut v = Vec3::new(1.0, 2.0, 3.0);
+        v[2] = 9.0;
+        v[0] += 1.0;
+        assert_eq!((v[0], v[1], v[2]), (2.0, 2.0, 9.0));
+        assert_eq!(v.to_array(), [2.0, 2.0, 9.0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "index 2 out of range for Vec2")]
+    fn indexing_past_the_end_panics() {
+        let _ = Vec2::zero()[2];
+    }
+
+    #[test]
+    fn products() {
+        let x = Vec3::new(1.0, 0.0, 0.0);
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        assert_eq!(x.cross(&y), Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(y.cross(&x), Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(x.dot(&y), 0.0);
+        assert_eq!(
+            Vec3::new(1.0, 2.0, 3.0).dot(&Vec3::new(4.0, 5.0, 6.0)),
+            32.0
+        );
+
+        let a = Vec2::new(2.0, 0.0);
+        assert_eq!(a.cross(&Vec2::new(0.0, 3.0)), 6.0);
+        assert_eq!(a.cross(&a.perp()), a.length_squared());
+        assert_eq!(a.extend(1.0).cross(&x), Vec3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn normalization_and_approximate_equality() {
+        let v = Vec2::new(3.0, 4.0);
+        assert_eq!(v.length(), 5.0);
+        assert_eq!(v.normalize(), Some(Vec2::new(0.6, 0.8)));
+        assert_eq!(Vec2::zero().normalize(), None);
+
+        let n = Vec3::new(1.0, 1.0, 1.0).normalize().unwrap();
+        assert!(n.length().approx_eq(&1.0));
+        let walked = (0..10).map(|_| Vec2::new(0.1, 0.2)).sum::<Vec2>();
+        assert_ne!(walked, Vec2::new(1.0, 2.0));
+        assert!(walked.approx_eq(&Vec2::new(1.0, 2.0)));
+        assert!(!n.approx_eq_eps(&(n * 1.001), 1e-6));
+        assert!(Vec2::zero().lerp(&v, 0.5).approx_eq(&Vec2::new(1.5, 2.0)));
+        assert_eq!(v.distance(&Vec2::zero()), 5.0);
+    }
+}